@@ -4,7 +4,7 @@ use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
 use crate::database;
-use crate::models::{LoginRequest, LoginResponse, UserInfo};
+use crate::models::{CreateUserRequest, LoginRequest, LoginResponse, User, UserInfo, UserRole};
 
 // JWT secret - generated once and stored
 static JWT_SECRET: Lazy<Vec<u8>> = Lazy::new(|| {
@@ -65,7 +65,7 @@ pub fn verify_token(token: &str) -> Result<Claims, String> {
     .map_err(|e| format!("Invalid token: {}", e))
 }
 
-pub fn login(req: LoginRequest) -> Result<LoginResponse, String> {
+pub fn verify_credentials(req: &LoginRequest) -> Result<User, String> {
     // Find user by username
     let user = database::find_user_by_username(&req.username).ok_or("사용자를 찾을 수 없습니다")?;
 
@@ -74,6 +74,31 @@ pub fn login(req: LoginRequest) -> Result<LoginResponse, String> {
         return Err("비밀번호가 일치하지 않습니다".to_string());
     }
 
+    Ok(user)
+}
+
+// Checks the password of whoever signs off a sensitive change and that their
+// role lets them
+pub fn verify_approver(req: &LoginRequest) -> Result<User, String> {
+    let user = verify_credentials(req)?;
+    if !user.role.can_approve() {
+        return Err("승인 권한이 없는 계정입니다".to_string());
+    }
+    Ok(user)
+}
+
+// Only the owner can unlock a closed day or add accounts
+pub fn verify_owner(req: &LoginRequest) -> Result<User, String> {
+    let user = verify_credentials(req)?;
+    if user.role != UserRole::Owner {
+        return Err("사장님 계정으로만 승인할 수 있습니다".to_string());
+    }
+    Ok(user)
+}
+
+pub fn login(req: LoginRequest) -> Result<LoginResponse, String> {
+    let user = verify_credentials(&req)?;
+
     // Generate token
     let token = generate_token(&user.id, &user.username)?;
 
@@ -82,6 +107,7 @@ pub fn login(req: LoginRequest) -> Result<LoginResponse, String> {
         user: UserInfo {
             id: user.id,
             username: user.username,
+            role: user.role,
             created_at: user.created_at,
         },
     })
@@ -95,6 +121,46 @@ pub fn get_current_user(token: &str) -> Result<UserInfo, String> {
     Ok(UserInfo {
         id: user.id,
         username: user.username,
+        role: user.role,
+        created_at: user.created_at,
+    })
+}
+
+pub fn get_users() -> Vec<UserInfo> {
+    database::get_users()
+        .into_iter()
+        .map(|user| UserInfo {
+            id: user.id,
+            username: user.username,
+            role: user.role,
+            created_at: user.created_at,
+        })
+        .collect()
+}
+
+pub fn create_user(req: CreateUserRequest) -> Result<UserInfo, String> {
+    verify_owner(&req.owner)?;
+
+    let username = req.username.trim();
+    if username.is_empty() {
+        return Err("아이디를 입력해주세요".to_string());
+    }
+    if req.password.len() < 4 {
+        return Err("비밀번호는 4자 이상이어야 합니다".to_string());
+    }
+    if database::find_user_by_username(username).is_some() {
+        return Err("이미 사용 중인 아이디입니다".to_string());
+    }
+
+    let password_hash = bcrypt::hash(&req.password, bcrypt::DEFAULT_COST)
+        .map_err(|e| format!("비밀번호 암호화 실패: {}", e))?;
+    let user = database::create_user(username, &password_hash, req.role)
+        .map_err(|e| format!("계정 생성 실패: {}", e))?;
+
+    Ok(UserInfo {
+        id: user.id,
+        username: user.username,
+        role: user.role,
         created_at: user.created_at,
     })
 }
@@ -1,4 +1,4 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use once_cell::sync::OnceCell;
 use parking_lot::Mutex;
 use rusqlite::{Connection, Result};
@@ -6,6 +6,7 @@ use std::path::PathBuf;
 use uuid::Uuid;
 
 use crate::models::*;
+use crate::{phone, photos};
 
 static DB: OnceCell<Mutex<Connection>> = OnceCell::new();
 static DB_PATH: OnceCell<PathBuf> = OnceCell::new();
@@ -42,11 +43,13 @@ pub fn backup_database(backup_path: &str) -> std::result::Result<(), String> {
     // Ensure all writes are flushed
     {
         let conn = get_db().lock();
-        conn.execute("PRAGMA wal_checkpoint(FULL)", [])
+        // The pragma returns a status row, so it has to be run as a query
+        conn.query_row("PRAGMA wal_checkpoint(FULL)", [], |_| Ok(()))
             .map_err(|e| format!("WAL checkpoint 실패: {}", e))?;
     }
 
     std::fs::copy(&db_path, backup_path).map_err(|e| format!("백업 실패: {}", e))?;
+    photos::backup(backup_path)?;
 
     Ok(())
 }
@@ -78,10 +81,37 @@ pub fn restore_database(backup_path: &str) -> std::result::Result<(), String> {
 
     // Copy backup to DB location
     std::fs::copy(backup_path, &db_path).map_err(|e| format!("복원 실패: {}", e))?;
+    photos::restore(backup_path)?;
 
     Ok(())
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum WriteError {
+    // A check repeated inside a write transaction failed, usually because
+    // another caller changed the same rows first. Shown to the user as is.
+    #[error("{0}")]
+    Conflict(String),
+    #[error(transparent)]
+    Database(#[from] rusqlite::Error),
+}
+
+pub type WriteResult<T> = std::result::Result<T, WriteError>;
+
+pub fn conflict(message: &str) -> WriteError {
+    WriteError::Conflict(message.to_string())
+}
+
+// One database in the temp directory shared by every test in the run
+#[cfg(test)]
+pub fn init_test_database() {
+    static INIT: std::sync::Once = std::sync::Once::new();
+    INIT.call_once(|| {
+        let dir = std::env::temp_dir().join(format!("timehair-test-{}", std::process::id()));
+        init_database(dir).expect("Failed to initialize test database");
+    });
+}
+
 pub fn get_db() -> &'static Mutex<Connection> {
     DB.get().expect("Database not initialized")
 }
@@ -93,6 +123,7 @@ fn create_tables(conn: &Connection) -> Result<()> {
             id TEXT PRIMARY KEY,
             username TEXT UNIQUE NOT NULL,
             password TEXT NOT NULL,
+            role TEXT NOT NULL DEFAULT 'staff',
             created_at TEXT NOT NULL,
             updated_at TEXT NOT NULL,
             deleted_at TEXT
@@ -107,6 +138,18 @@ fn create_tables(conn: &Connection) -> Result<()> {
             name TEXT NOT NULL,
             phone TEXT UNIQUE NOT NULL,
             stamps INTEGER DEFAULT 0,
+            stamps_earned_at TEXT,
+            prepaid_balance INTEGER DEFAULT 0,
+            prepaid_expires_at TEXT,
+            points INTEGER DEFAULT 0,
+            tier_id TEXT,
+            birthday TEXT,
+            gender TEXT,
+            memo TEXT,
+            marketing_consent INTEGER DEFAULT 0,
+            marketing_consent_at TEXT,
+            tags TEXT NOT NULL DEFAULT '[]',
+            custom_fields TEXT NOT NULL DEFAULT '{}',
             created_at TEXT NOT NULL,
             updated_at TEXT NOT NULL,
             deleted_at TEXT
@@ -171,10 +214,29 @@ fn create_tables(conn: &Connection) -> Result<()> {
             ledger_entry_id TEXT,
             name TEXT NOT NULL,
             length TEXT,
-            price INTEGER NOT NULL
+            category TEXT,
+            price INTEGER NOT NULL,
+            member_package_id TEXT
+        )",
+        [],
+    )?;
+
+    // Staff who worked on a service line and their revenue share
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS service_staff (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            selected_service_id INTEGER NOT NULL,
+            staff_id TEXT NOT NULL,
+            staff_name TEXT NOT NULL,
+            share_percent INTEGER NOT NULL,
+            FOREIGN KEY (selected_service_id) REFERENCES selected_services(id)
         )",
         [],
     )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_service_staff_service ON service_staff(selected_service_id)",
+        [],
+    )?;
 
     // Reservations table
     conn.execute(
@@ -190,6 +252,11 @@ fn create_tables(conn: &Connection) -> Result<()> {
             reserved_at TEXT NOT NULL,
             estimated_duration INTEGER NOT NULL,
             status TEXT DEFAULT 'scheduled',
+            deposit_amount INTEGER DEFAULT 0,
+            deposit_method TEXT,
+            deposit_status TEXT DEFAULT 'none',
+            deposit_paid_at TEXT,
+            external_uid TEXT,
             created_at TEXT NOT NULL,
             updated_at TEXT NOT NULL,
             deleted_at TEXT
@@ -201,1048 +268,5589 @@ fn create_tables(conn: &Connection) -> Result<()> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS ledger_entries (
             id TEXT PRIMARY KEY,
+            entry_type TEXT DEFAULT 'service',
             reservation_id TEXT,
             member_id TEXT,
             member_name TEXT NOT NULL,
             seat_id INTEGER NOT NULL,
             staff_id TEXT NOT NULL,
             staff_name TEXT NOT NULL,
+            gross_amount INTEGER,
+            discount_amount INTEGER DEFAULT 0,
             total_price INTEGER NOT NULL,
+            deposit_applied INTEGER DEFAULT 0,
+            refund_of TEXT,
+            refund_reason TEXT,
+            approved_by TEXT,
+            corrected_at TEXT,
             completed_at TEXT NOT NULL,
             created_at TEXT NOT NULL
         )",
         [],
     )?;
 
-    Ok(())
-}
-
-fn seed_data(conn: &Connection) -> Result<()> {
-    let now = Utc::now().to_rfc3339();
-
-    // Check if admin user exists
-    let user_count: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM users WHERE deleted_at IS NULL",
+    // Payments table (one or more per ledger entry)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS payments (
+            id TEXT PRIMARY KEY,
+            ledger_entry_id TEXT NOT NULL,
+            method TEXT NOT NULL,
+            amount INTEGER NOT NULL,
+            approval_number TEXT,
+            paid_at TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (ledger_entry_id) REFERENCES ledger_entries(id)
+        )",
         [],
-        |row| row.get(0),
     )?;
-
-    if user_count == 0 {
-        // Create default admin user
-        let password_hash =
-            bcrypt::hash("12344321", bcrypt::DEFAULT_COST).expect("Failed to hash password");
-
-        conn.execute(
-            "INSERT INTO users (id, username, password, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5)",
-            [&Uuid::new_v4().to_string(), "admin", &password_hash, &now, &now],
-        )?;
-    }
-
-    // Check if staff exists
-    let staff_count: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM staff WHERE deleted_at IS NULL",
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_payments_ledger_entry ON payments(ledger_entry_id)",
         [],
-        |row| row.get(0),
     )?;
 
-    if staff_count == 0 {
-        // Create default staff
-        let staff_names = ["원장", "직원1", "직원2", "직원3", "직원4"];
-        for name in staff_names {
-            conn.execute(
-                "INSERT INTO staff (id, name, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
-                [&Uuid::new_v4().to_string(), name, &now, &now],
-            )?;
-        }
-    }
-
-    // Check if seats exist
-    let seat_count: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM seats WHERE deleted_at IS NULL",
+    // Discount rules and coupons
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS discount_rules (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            value INTEGER NOT NULL,
+            service_name TEXT,
+            member_only INTEGER DEFAULT 0,
+            student_only INTEGER DEFAULT 0,
+            starts_at TEXT,
+            ends_at TEXT,
+            active INTEGER DEFAULT 1,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            deleted_at TEXT
+        )",
         [],
-        |row| row.get(0),
     )?;
 
-    if seat_count == 0 {
-        // Create default seats
-        for i in 1..=6 {
-            conn.execute(
-                "INSERT INTO seats (id, name, status, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5)",
-                [&i.to_string(), &format!("{}번 좌석", i), "available", &now, &now],
-            )?;
-        }
-    }
-
-    Ok(())
-}
-
-// ==================== User Operations ====================
-pub fn find_user_by_username(username: &str) -> Option<User> {
-    let conn = get_db().lock();
-    conn.query_row(
-        "SELECT id, username, password, created_at, updated_at FROM users WHERE username = ?1 AND deleted_at IS NULL",
-        [username],
-        |row| {
-            Ok(User {
-                id: row.get(0)?,
-                username: row.get(1)?,
-                password: row.get(2)?,
-                created_at: row.get::<_, String>(3)?.parse().unwrap_or_default(),
-                updated_at: row.get::<_, String>(4)?.parse().unwrap_or_default(),
-            })
-        },
-    ).ok()
-}
-
-pub fn find_user_by_id(id: &str) -> Option<User> {
-    let conn = get_db().lock();
-    conn.query_row(
-        "SELECT id, username, password, created_at, updated_at FROM users WHERE id = ?1 AND deleted_at IS NULL",
-        [id],
-        |row| {
-            Ok(User {
-                id: row.get(0)?,
-                username: row.get(1)?,
-                password: row.get(2)?,
-                created_at: row.get::<_, String>(3)?.parse().unwrap_or_default(),
-                updated_at: row.get::<_, String>(4)?.parse().unwrap_or_default(),
-            })
-        },
-    ).ok()
-}
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS coupons (
+            id TEXT PRIMARY KEY,
+            code TEXT NOT NULL,
+            name TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            value INTEGER NOT NULL,
+            max_uses INTEGER,
+            used_count INTEGER DEFAULT 0,
+            expires_at TEXT,
+            active INTEGER DEFAULT 1,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            deleted_at TEXT
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_coupons_code ON coupons(code)",
+        [],
+    )?;
 
-// ==================== Member Operations ====================
-pub fn get_members(search: Option<&str>) -> Vec<Member> {
-    let conn = get_db().lock();
+    // Discount lines applied to a ledger entry
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ledger_discounts (
+            id TEXT PRIMARY KEY,
+            ledger_entry_id TEXT NOT NULL,
+            source TEXT NOT NULL,
+            reference_id TEXT,
+            name TEXT NOT NULL,
+            amount INTEGER NOT NULL,
+            kind TEXT,
+            value INTEGER,
+            service_name TEXT,
+            single_line INTEGER DEFAULT 0,
+            FOREIGN KEY (ledger_entry_id) REFERENCES ledger_entries(id)
+        )",
+        [],
+    )?;
 
-    if let Some(search) = search {
-        let search_pattern = format!("%{}%", search);
-        let mut stmt = conn
-            .prepare("SELECT id, name, phone, stamps, created_at, updated_at FROM members WHERE deleted_at IS NULL AND (name LIKE ?1 OR phone LIKE ?1) ORDER BY created_at DESC")
-            .unwrap();
-        let iter = stmt
-            .query_map([&search_pattern], |row| {
-                Ok(Member {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    phone: row.get(2)?,
-                    stamps: row.get(3)?,
-                    created_at: row.get::<_, String>(4)?.parse().unwrap_or_default(),
-                    updated_at: row.get::<_, String>(5)?.parse().unwrap_or_default(),
-                })
-            })
-            .unwrap();
-        iter.filter_map(|r| r.ok()).collect()
-    } else {
-        let mut stmt = conn
-            .prepare("SELECT id, name, phone, stamps, created_at, updated_at FROM members WHERE deleted_at IS NULL ORDER BY created_at DESC")
-            .unwrap();
-        let iter = stmt
-            .query_map([], |row| {
-                Ok(Member {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    phone: row.get(2)?,
-                    stamps: row.get(3)?,
-                    created_at: row.get::<_, String>(4)?.parse().unwrap_or_default(),
-                    updated_at: row.get::<_, String>(5)?.parse().unwrap_or_default(),
-                })
-            })
-            .unwrap();
-        iter.filter_map(|r| r.ok()).collect()
-    }
-}
+    // Correction history for ledger entries (never updated or deleted)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ledger_corrections (
+            id TEXT PRIMARY KEY,
+            ledger_entry_id TEXT NOT NULL,
+            corrected_by TEXT NOT NULL,
+            reason TEXT NOT NULL,
+            changes TEXT NOT NULL,
+            original TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (ledger_entry_id) REFERENCES ledger_entries(id)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_ledger_corrections_entry ON ledger_corrections(ledger_entry_id)",
+        [],
+    )?;
 
-pub fn get_member_by_id(id: &str) -> Option<Member> {
-    let conn = get_db().lock();
-    conn.query_row(
-        "SELECT id, name, phone, stamps, created_at, updated_at FROM members WHERE id = ?1 AND deleted_at IS NULL",
-        [id],
-        |row| {
-            Ok(Member {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                phone: row.get(2)?,
-                stamps: row.get(3)?,
-                created_at: row.get::<_, String>(4)?.parse().unwrap_or_default(),
-                updated_at: row.get::<_, String>(5)?.parse().unwrap_or_default(),
-            })
-        },
-    ).ok()
-}
+    // End-of-day closings, one per business date
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS daily_closings (
+            id TEXT PRIMARY KEY,
+            business_date TEXT NOT NULL UNIQUE,
+            opening_float INTEGER NOT NULL,
+            cash_sales INTEGER NOT NULL,
+            cash_refunds INTEGER NOT NULL,
+            cash_tips INTEGER DEFAULT 0,
+            prepaid_cash INTEGER DEFAULT 0,
+            expected_cash INTEGER NOT NULL,
+            counted_cash INTEGER NOT NULL,
+            difference INTEGER NOT NULL,
+            gross_revenue INTEGER NOT NULL,
+            total_discount INTEGER NOT NULL,
+            total_refunds INTEGER NOT NULL,
+            total_revenue INTEGER NOT NULL,
+            total_count INTEGER NOT NULL,
+            closed_by TEXT NOT NULL,
+            memo TEXT,
+            closed_at TEXT NOT NULL
+        )",
+        [],
+    )?;
 
-pub fn get_member_by_phone(phone: &str) -> Option<Member> {
-    let conn = get_db().lock();
-    conn.query_row(
-        "SELECT id, name, phone, stamps, created_at, updated_at FROM members WHERE phone = ?1 AND deleted_at IS NULL",
-        [phone],
-        |row| {
-            Ok(Member {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                phone: row.get(2)?,
-                stamps: row.get(3)?,
-                created_at: row.get::<_, String>(4)?.parse().unwrap_or_default(),
-                updated_at: row.get::<_, String>(5)?.parse().unwrap_or_default(),
-            })
-        },
-    ).ok()
-}
+    // Commission rules; `tiers` holds a JSON array for tiered rules
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS commission_rules (
+            id TEXT PRIMARY KEY,
+            staff_id TEXT,
+            service_category TEXT,
+            kind TEXT NOT NULL,
+            value INTEGER DEFAULT 0,
+            tiers TEXT NOT NULL DEFAULT '[]',
+            active INTEGER DEFAULT 1,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            deleted_at TEXT
+        )",
+        [],
+    )?;
 
-pub fn create_member(name: &str, phone: &str) -> Result<Member> {
-    let conn = get_db().lock();
-    let id = Uuid::new_v4().to_string();
-    let now = Utc::now();
-    let now_str = now.to_rfc3339();
+    // Tips recorded against ledger entries
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS tips (
+            id TEXT PRIMARY KEY,
+            ledger_entry_id TEXT NOT NULL,
+            staff_id TEXT NOT NULL,
+            staff_name TEXT NOT NULL,
+            amount INTEGER NOT NULL,
+            method TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (ledger_entry_id) REFERENCES ledger_entries(id)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_tips_ledger_entry ON tips(ledger_entry_id)",
+        [],
+    )?;
 
+    // Prepaid balance movements; `amount` is the change to the balance and
+    // `paid_amount` the money taken in (top-up) or paid back (refund)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS prepaid_transactions (
+            id TEXT PRIMARY KEY,
+            member_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            amount INTEGER NOT NULL,
+            bonus_amount INTEGER DEFAULT 0,
+            paid_amount INTEGER DEFAULT 0,
+            method TEXT,
+            ledger_entry_id TEXT,
+            balance_after INTEGER NOT NULL,
+            expires_at TEXT,
+            memo TEXT,
+            created_by TEXT,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (member_id) REFERENCES members(id)
+        )",
+        [],
+    )?;
     conn.execute(
-        "INSERT INTO members (id, name, phone, stamps, created_at, updated_at) VALUES (?1, ?2, ?3, 0, ?4, ?5)",
-        [&id, name, phone, &now_str, &now_str],
+        "CREATE INDEX IF NOT EXISTS idx_prepaid_transactions_member ON prepaid_transactions(member_id, created_at)",
+        [],
     )?;
 
-    Ok(Member {
-        id,
-        name: name.to_string(),
-        phone: phone.to_string(),
-        stamps: 0,
-        created_at: now,
-        updated_at: now,
-    })
-}
+    // Package tickets: definitions, passes bought by members and their uses
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS packages (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            service_name TEXT NOT NULL,
+            total_count INTEGER NOT NULL,
+            price INTEGER NOT NULL,
+            validity_days INTEGER DEFAULT 0,
+            active INTEGER DEFAULT 1,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            deleted_at TEXT
+        )",
+        [],
+    )?;
 
-pub fn update_member(id: &str, name: &str, phone: &str) -> Result<Member> {
-    {
-        let conn = get_db().lock();
-        let now = Utc::now().to_rfc3339();
-        conn.execute(
-            "UPDATE members SET name = ?1, phone = ?2, updated_at = ?3 WHERE id = ?4 AND deleted_at IS NULL",
-            [name, phone, &now, id],
-        )?;
-    }
-    get_member_by_id(id).ok_or(rusqlite::Error::QueryReturnedNoRows)
-}
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS member_packages (
+            id TEXT PRIMARY KEY,
+            member_id TEXT NOT NULL,
+            package_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            service_name TEXT NOT NULL,
+            total_count INTEGER NOT NULL,
+            remaining_count INTEGER NOT NULL,
+            price INTEGER NOT NULL,
+            payment_method TEXT NOT NULL,
+            purchased_at TEXT NOT NULL,
+            expires_at TEXT,
+            created_by TEXT NOT NULL,
+            ledger_entry_id TEXT,
+            FOREIGN KEY (member_id) REFERENCES members(id),
+            FOREIGN KEY (package_id) REFERENCES packages(id)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_member_packages_member ON member_packages(member_id)",
+        [],
+    )?;
 
-pub fn delete_member(id: &str) -> Result<()> {
-    let conn = get_db().lock();
-    let now = Utc::now().to_rfc3339();
     conn.execute(
-        "UPDATE members SET deleted_at = ?1 WHERE id = ?2",
-        [&now, id],
+        "CREATE TABLE IF NOT EXISTS package_usages (
+            id TEXT PRIMARY KEY,
+            member_package_id TEXT NOT NULL,
+            ledger_entry_id TEXT NOT NULL,
+            service_name TEXT NOT NULL,
+            used_at TEXT NOT NULL,
+            restored_at TEXT,
+            FOREIGN KEY (member_package_id) REFERENCES member_packages(id)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_package_usages_ledger_entry ON package_usages(ledger_entry_id)",
+        [],
     )?;
-    Ok(())
-}
 
-pub fn add_stamp(id: &str) -> Result<Member> {
-    {
-        let conn = get_db().lock();
-        let now = Utc::now().to_rfc3339();
-        conn.execute(
-            "UPDATE members SET stamps = stamps + 1, updated_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
-            [&now, id],
+    // Stamp reward programs; `eligible_services` holds a JSON array
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS stamp_programs (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            stamps_required INTEGER NOT NULL,
+            reward_kind TEXT NOT NULL,
+            reward_value INTEGER DEFAULT 0,
+            reward_service_name TEXT,
+            amount_per_stamp INTEGER,
+            eligible_services TEXT NOT NULL DEFAULT '[]',
+            expiry_days INTEGER DEFAULT 0,
+            active INTEGER DEFAULT 1,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            deleted_at TEXT
+        )",
+        [],
+    )?;
+
+    // Stamp history; members.stamps is the running total of `amount`
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS stamp_transactions (
+            id TEXT PRIMARY KEY,
+            member_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            amount INTEGER NOT NULL,
+            balance_after INTEGER NOT NULL,
+            ledger_entry_id TEXT,
+            program_id TEXT,
+            actor TEXT,
+            reason TEXT,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (member_id) REFERENCES members(id)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_stamp_transactions_member ON stamp_transactions(member_id, created_at)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_stamp_transactions_ledger_entry ON stamp_transactions(ledger_entry_id)",
+        [],
+    )?;
+
+    // Loyalty point history; members.points is the running total of `amount`
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS point_transactions (
+            id TEXT PRIMARY KEY,
+            member_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            amount INTEGER NOT NULL,
+            balance_after INTEGER NOT NULL,
+            ledger_entry_id TEXT,
+            source_entry_id TEXT,
+            actor TEXT,
+            reason TEXT,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (member_id) REFERENCES members(id)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_point_transactions_member ON point_transactions(member_id, created_at)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_point_transactions_source ON point_transactions(source_entry_id)",
+        [],
+    )?;
+
+    // Membership tiers and the history of members moving between them
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS membership_tiers (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            level INTEGER NOT NULL,
+            min_spend INTEGER DEFAULT 0,
+            min_visits INTEGER DEFAULT 0,
+            discount_percent INTEGER DEFAULT 0,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            deleted_at TEXT
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS tier_changes (
+            id TEXT PRIMARY KEY,
+            member_id TEXT NOT NULL,
+            member_name TEXT NOT NULL,
+            from_tier_id TEXT,
+            from_tier_name TEXT,
+            to_tier_id TEXT,
+            to_tier_name TEXT,
+            upgrade INTEGER NOT NULL,
+            spend INTEGER NOT NULL,
+            visits INTEGER NOT NULL,
+            changed_at TEXT NOT NULL,
+            FOREIGN KEY (member_id) REFERENCES members(id)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_tier_changes_changed_at ON tier_changes(changed_at)",
+        [],
+    )?;
+
+    // Treatment notes; `color_formula` holds a JSON object
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS treatment_notes (
+            id TEXT PRIMARY KEY,
+            member_id TEXT NOT NULL,
+            ledger_entry_id TEXT,
+            session_id TEXT,
+            staff_id TEXT,
+            staff_name TEXT,
+            color_formula TEXT,
+            perm_rod_size TEXT,
+            scalp_condition TEXT,
+            hair_condition TEXT,
+            allergies TEXT,
+            memo TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            deleted_at TEXT,
+            FOREIGN KEY (member_id) REFERENCES members(id)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_treatment_notes_member ON treatment_notes(member_id, created_at)",
+        [],
+    )?;
+
+    // Member photos; the files are stored by content hash (see photos.rs)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS member_photos (
+            id TEXT PRIMARY KEY,
+            member_id TEXT NOT NULL,
+            ledger_entry_id TEXT,
+            kind TEXT NOT NULL,
+            content_hash TEXT NOT NULL,
+            extension TEXT NOT NULL,
+            width INTEGER NOT NULL,
+            height INTEGER NOT NULL,
+            file_size INTEGER NOT NULL,
+            memo TEXT,
+            taken_at TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            deleted_at TEXT,
+            FOREIGN KEY (member_id) REFERENCES members(id)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_member_photos_member ON member_photos(member_id, taken_at)",
+        [],
+    )?;
+
+    // Members folded into another one; the removed member's row stays deleted
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS member_merges (
+            id TEXT PRIMARY KEY,
+            survivor_id TEXT NOT NULL,
+            merged_member_id TEXT NOT NULL,
+            merged_name TEXT NOT NULL,
+            merged_phone TEXT NOT NULL,
+            stamps INTEGER NOT NULL,
+            prepaid_balance INTEGER NOT NULL,
+            points INTEGER NOT NULL,
+            ledger_entries INTEGER NOT NULL,
+            reservations INTEGER NOT NULL,
+            merged_by TEXT NOT NULL,
+            reason TEXT,
+            merged_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Settings table (JSON values keyed by name)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Notification queue
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS notifications (
+            id TEXT PRIMARY KEY,
+            kind TEXT NOT NULL,
+            channel TEXT NOT NULL,
+            member_id TEXT,
+            reservation_id TEXT,
+            recipient TEXT NOT NULL,
+            body TEXT NOT NULL,
+            status TEXT DEFAULT 'pending',
+            attempts INTEGER DEFAULT 0,
+            last_error TEXT,
+            provider_message_id TEXT,
+            scheduled_at TEXT NOT NULL,
+            sent_at TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_notifications_status ON notifications(status, scheduled_at)",
+        [],
+    )?;
+
+    // Columns added after the initial release
+    add_column_if_missing(conn, "selected_services", "category", "TEXT")?;
+    add_column_if_missing(conn, "selected_services", "member_package_id", "TEXT")?;
+    add_column_if_missing(conn, "reservations", "deposit_amount", "INTEGER DEFAULT 0")?;
+    add_column_if_missing(conn, "reservations", "deposit_method", "TEXT")?;
+    add_column_if_missing(
+        conn,
+        "reservations",
+        "deposit_status",
+        "TEXT DEFAULT 'none'",
+    )?;
+    add_column_if_missing(conn, "reservations", "deposit_paid_at", "TEXT")?;
+    add_column_if_missing(conn, "reservations", "external_uid", "TEXT")?;
+    add_column_if_missing(
+        conn,
+        "ledger_entries",
+        "entry_type",
+        "TEXT DEFAULT 'service'",
+    )?;
+    add_column_if_missing(
+        conn,
+        "ledger_entries",
+        "deposit_applied",
+        "INTEGER DEFAULT 0",
+    )?;
+    add_column_if_missing(conn, "ledger_entries", "gross_amount", "INTEGER")?;
+    add_column_if_missing(
+        conn,
+        "ledger_entries",
+        "discount_amount",
+        "INTEGER DEFAULT 0",
+    )?;
+    conn.execute(
+        "UPDATE ledger_entries SET gross_amount = total_price WHERE gross_amount IS NULL",
+        [],
+    )?;
+    add_column_if_missing(conn, "ledger_entries", "refund_of", "TEXT")?;
+    add_column_if_missing(conn, "ledger_entries", "refund_reason", "TEXT")?;
+    add_column_if_missing(conn, "ledger_entries", "approved_by", "TEXT")?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_ledger_entries_refund_of ON ledger_entries(refund_of)",
+        [],
+    )?;
+    add_column_if_missing(conn, "ledger_entries", "corrected_at", "TEXT")?;
+    add_column_if_missing(conn, "ledger_discounts", "kind", "TEXT")?;
+    add_column_if_missing(conn, "ledger_discounts", "value", "INTEGER")?;
+    add_column_if_missing(conn, "ledger_discounts", "service_name", "TEXT")?;
+    add_column_if_missing(conn, "ledger_discounts", "single_line", "INTEGER DEFAULT 0")?;
+    add_column_if_missing(conn, "daily_closings", "cash_tips", "INTEGER DEFAULT 0")?;
+    add_column_if_missing(conn, "members", "prepaid_balance", "INTEGER DEFAULT 0")?;
+    add_column_if_missing(conn, "members", "prepaid_expires_at", "TEXT")?;
+    add_column_if_missing(conn, "daily_closings", "prepaid_cash", "INTEGER DEFAULT 0")?;
+    add_column_if_missing(conn, "members", "stamps_earned_at", "TEXT")?;
+    add_column_if_missing(conn, "members", "points", "INTEGER DEFAULT 0")?;
+    add_column_if_missing(conn, "members", "tier_id", "TEXT")?;
+    add_column_if_missing(conn, "members", "birthday", "TEXT")?;
+    add_column_if_missing(conn, "members", "gender", "TEXT")?;
+    add_column_if_missing(conn, "members", "memo", "TEXT")?;
+    add_column_if_missing(conn, "members", "marketing_consent", "INTEGER DEFAULT 0")?;
+    add_column_if_missing(conn, "members", "marketing_consent_at", "TEXT")?;
+    add_column_if_missing(conn, "members", "tags", "TEXT NOT NULL DEFAULT '[]'")?;
+    // Accounts from before roles become staff; only the seeded admin, or the
+    // oldest account if it was removed, keeps the right to approve
+    if add_column_if_missing(conn, "users", "role", "TEXT NOT NULL DEFAULT 'staff'")? {
+        conn.execute(
+            "UPDATE users SET role = 'owner' WHERE id = (
+                SELECT id FROM users WHERE deleted_at IS NULL
+                ORDER BY username = 'admin' DESC, created_at ASC LIMIT 1
+             )",
+            [],
         )?;
     }
-    get_member_by_id(id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+    add_column_if_missing(
+        conn,
+        "members",
+        "custom_fields",
+        "TEXT NOT NULL DEFAULT '{}'",
+    )?;
+    // Stamps collected before the history existed become an opening balance
+    conn.execute(
+        "INSERT INTO stamp_transactions (id, member_id, kind, amount, balance_after, reason, created_at)
+         SELECT lower(hex(randomblob(16))), id, 'opening', stamps, stamps, '이력 도입 전 적립분', updated_at
+         FROM members m
+         WHERE stamps > 0 AND NOT EXISTS (SELECT 1 FROM stamp_transactions t WHERE t.member_id = m.id)",
+        [],
+    )?;
+
+    Ok(())
 }
 
-pub fn reset_stamps(id: &str) -> Result<Member> {
-    {
-        let conn = get_db().lock();
-        let now = Utc::now().to_rfc3339();
+// Returns whether the column had to be added
+fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<bool> {
+    let exists = conn
+        .prepare(&format!(
+            "SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1",
+            table
+        ))?
+        .exists([column])?;
+
+    if !exists {
         conn.execute(
-            "UPDATE members SET stamps = 0, updated_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
-            [&now, id],
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
         )?;
     }
-    get_member_by_id(id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+
+    Ok(!exists)
 }
 
-pub fn phone_exists(phone: &str, exclude_id: Option<&str>) -> bool {
+fn seed_data(conn: &Connection) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+
+    // Check if admin user exists
+    let user_count: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM users WHERE deleted_at IS NULL",
+        [],
+        |row| row.get(0),
+    )?;
+
+    if user_count == 0 {
+        // Create default admin user
+        let password_hash =
+            bcrypt::hash("12344321", bcrypt::DEFAULT_COST).expect("Failed to hash password");
+
+        conn.execute(
+            "INSERT INTO users (id, username, password, role, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            [
+                &Uuid::new_v4().to_string(),
+                "admin",
+                &password_hash,
+                UserRole::Owner.as_str(),
+                &now,
+                &now,
+            ],
+        )?;
+    }
+
+    // Check if staff exists
+    let staff_count: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM staff WHERE deleted_at IS NULL",
+        [],
+        |row| row.get(0),
+    )?;
+
+    if staff_count == 0 {
+        // Create default staff
+        let staff_names = ["원장", "직원1", "직원2", "직원3", "직원4"];
+        for name in staff_names {
+            conn.execute(
+                "INSERT INTO staff (id, name, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
+                [&Uuid::new_v4().to_string(), name, &now, &now],
+            )?;
+        }
+    }
+
+    // Check if seats exist
+    let seat_count: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM seats WHERE deleted_at IS NULL",
+        [],
+        |row| row.get(0),
+    )?;
+
+    if seat_count == 0 {
+        // Create default seats
+        for i in 1..=6 {
+            conn.execute(
+                "INSERT INTO seats (id, name, status, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                [&i.to_string(), &format!("{}번 좌석", i), "available", &now, &now],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+// ==================== Settings Operations ====================
+pub fn get_setting<T: serde::de::DeserializeOwned + Default>(key: &str) -> T {
     let conn = get_db().lock();
-    let count: i32 = if let Some(exclude) = exclude_id {
-        conn.query_row(
-            "SELECT COUNT(*) FROM members WHERE phone = ?1 AND id != ?2 AND deleted_at IS NULL",
-            [phone, exclude],
-            |row| row.get(0),
-        )
-        .unwrap_or(0)
-    } else {
-        conn.query_row(
-            "SELECT COUNT(*) FROM members WHERE phone = ?1 AND deleted_at IS NULL",
-            [phone],
-            |row| row.get(0),
-        )
-        .unwrap_or(0)
-    };
-    count > 0
+    conn.query_row("SELECT value FROM settings WHERE key = ?1", [key], |row| {
+        row.get::<_, String>(0)
+    })
+    .ok()
+    .and_then(|value| serde_json::from_str(&value).ok())
+    .unwrap_or_default()
 }
 
-// ==================== Staff Operations ====================
-pub fn get_staff_list() -> Vec<Staff> {
+pub fn save_setting<T: serde::Serialize>(key: &str, value: &T) -> Result<()> {
     let conn = get_db().lock();
-    let mut stmt = conn
-        .prepare("SELECT id, name, created_at, updated_at FROM staff WHERE deleted_at IS NULL ORDER BY created_at ASC")
-        .unwrap();
+    let now = Utc::now().to_rfc3339();
+    let json = serde_json::to_string(value)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    conn.execute(
+        "INSERT INTO settings (key, value, updated_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+        [key, &json, &now],
+    )?;
+    Ok(())
+}
 
-    let iter = stmt
-        .query_map([], |row| {
-            Ok(Staff {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                created_at: row.get::<_, String>(2)?.parse().unwrap_or_default(),
-                updated_at: row.get::<_, String>(3)?.parse().unwrap_or_default(),
-            })
-        })
-        .unwrap();
+pub fn get_receipt_settings() -> ReceiptSettings {
+    get_setting("receipt")
+}
 
-    iter.filter_map(|r| r.ok()).collect()
+pub fn save_receipt_settings(settings: &ReceiptSettings) -> Result<()> {
+    save_setting("receipt", settings)
 }
 
-pub fn get_staff_by_id(id: &str) -> Option<Staff> {
+pub fn get_api_server_settings() -> ApiServerSettings {
+    get_setting("api_server")
+}
+
+pub fn save_api_server_settings(settings: &ApiServerSettings) -> Result<ApiServerSettings> {
+    save_setting("api_server", settings)?;
+    Ok(get_api_server_settings())
+}
+
+// ==================== User Operations ====================
+const USER_COLUMNS: &str = "id, username, password, role, created_at, updated_at";
+
+fn map_user(row: &rusqlite::Row) -> rusqlite::Result<User> {
+    Ok(User {
+        id: row.get(0)?,
+        username: row.get(1)?,
+        password: row.get(2)?,
+        role: UserRole::from_str(&row.get::<_, String>(3)?),
+        created_at: row.get::<_, String>(4)?.parse().unwrap_or_default(),
+        updated_at: row.get::<_, String>(5)?.parse().unwrap_or_default(),
+    })
+}
+
+pub fn find_user_by_username(username: &str) -> Option<User> {
     let conn = get_db().lock();
     conn.query_row(
-        "SELECT id, name, created_at, updated_at FROM staff WHERE id = ?1 AND deleted_at IS NULL",
-        [id],
-        |row| {
-            Ok(Staff {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                created_at: row.get::<_, String>(2)?.parse().unwrap_or_default(),
-                updated_at: row.get::<_, String>(3)?.parse().unwrap_or_default(),
-            })
-        },
+        &format!(
+            "SELECT {} FROM users WHERE username = ?1 AND deleted_at IS NULL",
+            USER_COLUMNS
+        ),
+        [username],
+        map_user,
     )
     .ok()
 }
 
-pub fn create_staff(name: &str) -> Result<Staff> {
+pub fn find_user_by_id(id: &str) -> Option<User> {
     let conn = get_db().lock();
-    let id = Uuid::new_v4().to_string();
-    let now = Utc::now();
-    let now_str = now.to_rfc3339();
-
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM users WHERE id = ?1 AND deleted_at IS NULL",
+            USER_COLUMNS
+        ),
+        [id],
+        map_user,
+    )
+    .ok()
+}
+
+pub fn get_users() -> Vec<User> {
+    let conn = get_db().lock();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM users WHERE deleted_at IS NULL ORDER BY created_at ASC",
+            USER_COLUMNS
+        ))
+        .unwrap();
+    stmt.query_map([], map_user)
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
+}
+
+pub fn create_user(username: &str, password_hash: &str, role: UserRole) -> Result<User> {
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+
+    {
+        let conn = get_db().lock();
+        conn.execute(
+            "INSERT INTO users (id, username, password, role, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            [&id, username, password_hash, role.as_str(), &now, &now],
+        )?;
+    }
+
+    find_user_by_id(&id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
+
+// ==================== Member Operations ====================
+const MEMBER_COLUMNS: &str = "id, name, phone, stamps, created_at, updated_at, prepaid_balance, prepaid_expires_at, points, tier_id,
+    (SELECT name FROM membership_tiers WHERE membership_tiers.id = members.tier_id),
+    birthday, gender, memo, marketing_consent, marketing_consent_at, tags, custom_fields";
+
+fn map_member(row: &rusqlite::Row) -> rusqlite::Result<Member> {
+    let phone: String = row.get(2)?;
+    Ok(Member {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        phone_display: phone::format(&phone),
+        phone,
+        stamps: row.get(3)?,
+        created_at: row.get::<_, String>(4)?.parse().unwrap_or_default(),
+        updated_at: row.get::<_, String>(5)?.parse().unwrap_or_default(),
+        prepaid_balance: row.get(6)?,
+        prepaid_expires_at: row
+            .get::<_, Option<String>>(7)?
+            .and_then(|s| s.parse().ok()),
+        points: row.get(8)?,
+        tier_id: row.get(9)?,
+        tier_name: row.get(10)?,
+        birthday: row
+            .get::<_, Option<String>>(11)?
+            .and_then(|s| s.parse().ok()),
+        gender: row
+            .get::<_, Option<String>>(12)?
+            .map(|g| Gender::from_str(&g)),
+        memo: row.get(13)?,
+        marketing_consent: row.get(14)?,
+        marketing_consent_at: row
+            .get::<_, Option<String>>(15)?
+            .and_then(|s| s.parse().ok()),
+        tags: serde_json::from_str(&row.get::<_, String>(16)?).unwrap_or_default(),
+        custom_fields: serde_json::from_str(&row.get::<_, String>(17)?).unwrap_or_default(),
+        packages: vec![],
+    })
+}
+
+fn load_member_packages(conn: &Connection, members: &mut [Member]) {
+    let now = Utc::now().to_rfc3339();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM member_packages WHERE member_id = ?1 AND {} ORDER BY {}",
+            MEMBER_PACKAGE_COLUMNS, USABLE_PACKAGE_CONDITION, USABLE_PACKAGE_ORDER
+        ))
+        .unwrap();
+
+    for member in members.iter_mut() {
+        member.packages = stmt
+            .query_map([&member.id, &now], map_member_package)
+            .unwrap()
+            .filter_map(|r| r.ok())
+            .collect();
+    }
+}
+
+pub fn get_members(query: &MemberQuery) -> Vec<Member> {
+    let conn = get_db().lock();
+
+    let mut sql = format!(
+        "SELECT {} FROM members WHERE deleted_at IS NULL",
+        MEMBER_COLUMNS
+    );
+    let mut params: Vec<String> = vec![];
+
+    if let Some(search) = query.search.as_deref().filter(|s| !s.trim().is_empty()) {
+        let mut condition = "name LIKE ?1 OR phone LIKE ?1 OR memo LIKE ?1 OR tags LIKE ?1
+                 OR EXISTS (SELECT 1 FROM json_each(members.custom_fields) WHERE value LIKE ?1)"
+            .to_string();
+        params.push(format!("%{}%", search.trim()));
+        // Phones are stored as E.164, so typed digits match without the leading 0
+        if let Some(pattern) = phone::search_pattern(search) {
+            condition.push_str(" OR phone LIKE ?2");
+            params.push(pattern);
+        }
+        sql.push_str(&format!(" AND ({})", condition));
+    }
+    if let Some(tag) = &query.tag {
+        params.push(tag.clone());
+        sql.push_str(&format!(
+            " AND EXISTS (SELECT 1 FROM json_each(members.tags) WHERE value = ?{})",
+            params.len()
+        ));
+    }
+    if let Some(gender) = query.gender {
+        params.push(gender.as_str().to_string());
+        sql.push_str(&format!(" AND gender = ?{}", params.len()));
+    }
+    if let Some(consent) = query.marketing_consent {
+        sql.push_str(if consent {
+            " AND marketing_consent = 1"
+        } else {
+            " AND COALESCE(marketing_consent, 0) = 0"
+        });
+    }
+    sql.push_str(" ORDER BY created_at DESC");
+
+    let mut stmt = conn.prepare(&sql).unwrap();
+    let mut members: Vec<Member> = stmt
+        .query_map(rusqlite::params_from_iter(params.iter()), map_member)
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect();
+
+    load_member_packages(&conn, &mut members);
+    members
+}
+
+// Members whose birthday falls in this week (Monday to Sunday) or month. A
+// 29 February birthday counts as 28 February in other years.
+pub fn get_birthday_members(range: BirthdayRange) -> Vec<Member> {
+    use chrono::Datelike;
+
+    let today = chrono::Local::now().date_naive();
+    let (start, end) = match range {
+        BirthdayRange::Week => {
+            let start =
+                today - chrono::Duration::days(i64::from(today.weekday().num_days_from_monday()));
+            (start, start + chrono::Duration::days(6))
+        }
+        BirthdayRange::Month => {
+            let start = today.with_day(1).unwrap_or(today);
+            let next = start
+                .checked_add_months(chrono::Months::new(1))
+                .unwrap_or(start);
+            (start, next.pred_opt().unwrap_or(start))
+        }
+    };
+
+    // The birthday as it falls in the given year
+    let in_year = |birthday: chrono::NaiveDate, year: i32| {
+        chrono::NaiveDate::from_ymd_opt(year, birthday.month(), birthday.day())
+            .or_else(|| chrono::NaiveDate::from_ymd_opt(year, 2, 28))
+    };
+
+    let mut members: Vec<(chrono::NaiveDate, Member)> = get_members(&MemberQuery::default())
+        .into_iter()
+        .filter_map(|m| {
+            let birthday = m.birthday?;
+            // A week can run across New Year
+            [start.year(), end.year()]
+                .into_iter()
+                .filter_map(|year| in_year(birthday, year))
+                .find(|d| *d >= start && *d <= end)
+                .map(|d| (d, m))
+        })
+        .collect();
+    members.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.name.cmp(&b.1.name)));
+    members.into_iter().map(|(_, m)| m).collect()
+}
+
+pub fn get_member_by_id(id: &str) -> Option<Member> {
+    let conn = get_db().lock();
+    let mut member = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM members WHERE id = ?1 AND deleted_at IS NULL",
+                MEMBER_COLUMNS
+            ),
+            [id],
+            map_member,
+        )
+        .ok()?;
+    load_member_packages(&conn, std::slice::from_mut(&mut member));
+    Some(member)
+}
+
+pub fn get_member_by_phone(phone: &str) -> Option<Member> {
+    let conn = get_db().lock();
+    let mut member = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM members WHERE phone = ?1 AND deleted_at IS NULL",
+                MEMBER_COLUMNS
+            ),
+            [phone],
+            map_member,
+        )
+        .ok()?;
+    load_member_packages(&conn, std::slice::from_mut(&mut member));
+    Some(member)
+}
+
+pub fn create_member(name: &str, phone: &str, details: &MemberDetails) -> Result<Member> {
+    let id = Uuid::new_v4().to_string();
+    let to_json = |e: serde_json::Error| rusqlite::Error::ToSqlConversionFailure(Box::new(e));
+    let tags = serde_json::to_string(&details.tags).map_err(to_json)?;
+    let custom_fields = serde_json::to_string(&details.custom_fields).map_err(to_json)?;
+    {
+        let conn = get_db().lock();
+        let now_str = Utc::now().to_rfc3339();
+
+        conn.execute(
+            "INSERT INTO members (id, name, phone, stamps, birthday, gender, memo, marketing_consent, marketing_consent_at, tags, custom_fields, created_at, updated_at)
+             VALUES (?1, ?2, ?3, 0, ?4, ?5, ?6, ?7, CASE WHEN ?7 THEN ?10 END, ?8, ?9, ?10, ?10)",
+            rusqlite::params![
+                id,
+                name,
+                phone,
+                details.birthday.map(|d| d.to_string()),
+                details.gender.map(|g| g.as_str()),
+                details.memo,
+                details.marketing_consent,
+                tags,
+                custom_fields,
+                now_str
+            ],
+        )?;
+    }
+    get_member_by_id(&id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
+
+// ==================== Member Merge Operations ====================
+const MEMBER_MERGE_COLUMNS: &str = "id, survivor_id, merged_member_id, merged_name, merged_phone, stamps, prepaid_balance, points, ledger_entries, reservations, merged_by, reason, merged_at";
+
+fn map_member_merge(row: &rusqlite::Row) -> rusqlite::Result<MemberMerge> {
+    Ok(MemberMerge {
+        id: row.get(0)?,
+        survivor_id: row.get(1)?,
+        merged_member_id: row.get(2)?,
+        merged_name: row.get(3)?,
+        merged_phone: row.get(4)?,
+        stamps: row.get(5)?,
+        prepaid_balance: row.get(6)?,
+        points: row.get(7)?,
+        ledger_entries: row.get(8)?,
+        reservations: row.get(9)?,
+        merged_by: row.get(10)?,
+        reason: row.get(11)?,
+        merged_at: row.get::<_, String>(12)?.parse().unwrap_or_default(),
+    })
+}
+
+// Tables whose rows follow a member into the survivor
+const MEMBER_OWNED_TABLES: &[&str] = &[
+    "service_sessions",
+    "prepaid_transactions",
+    "member_packages",
+    "stamp_transactions",
+    "point_transactions",
+    "tier_changes",
+    "treatment_notes",
+    "member_photos",
+    "notifications",
+];
+
+// Moves everything the duplicate owns onto the survivor, adds its stamps,
+// prepaid balance and points, writes `details` to the survivor and deletes
+// the duplicate, all in one transaction. The balances are read inside it, so
+// a checkout or top-up that lands first is carried over too.
+pub fn merge_members(
+    survivor: &Member,
+    duplicate: &Member,
+    details: &MemberDetails,
+    merged_by: &str,
+    reason: Option<&str>,
+) -> WriteResult<MemberMerge> {
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    let to_json = |e: serde_json::Error| rusqlite::Error::ToSqlConversionFailure(Box::new(e));
+    let tags = serde_json::to_string(&details.tags).map_err(to_json)?;
+    let custom_fields = serde_json::to_string(&details.custom_fields).map_err(to_json)?;
+    let mut conn = get_db().lock();
+    let tx = conn.transaction()?;
+
+    let (stamps, prepaid_balance, prepaid_expires_at, points): (i32, i32, Option<String>, i32) = tx
+        .query_row(
+            "SELECT stamps, prepaid_balance, prepaid_expires_at, points FROM members
+             WHERE id = ?1 AND deleted_at IS NULL
+               AND EXISTS (SELECT 1 FROM members WHERE id = ?2 AND deleted_at IS NULL)",
+            [&duplicate.id, &survivor.id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .map_err(|_| conflict("이미 병합되었거나 삭제된 회원입니다"))?;
+
+    let ledger_entries = tx.execute(
+        "UPDATE ledger_entries SET member_id = ?1 WHERE member_id = ?2",
+        [&survivor.id, &duplicate.id],
+    )?;
+    let reservations = tx.execute(
+        "UPDATE reservations SET member_id = ?1 WHERE member_id = ?2",
+        [&survivor.id, &duplicate.id],
+    )?;
+    for table in MEMBER_OWNED_TABLES {
+        tx.execute(
+            &format!("UPDATE {} SET member_id = ?1 WHERE member_id = ?2", table),
+            [&survivor.id, &duplicate.id],
+        )?;
+    }
+
+    // The later expiry and the later stamp date win
+    tx.execute(
+        "UPDATE members SET
+            stamps = stamps + ?1,
+            prepaid_balance = prepaid_balance + ?2,
+            prepaid_expires_at = NULLIF(MAX(COALESCE(prepaid_expires_at, ''), COALESCE(?3, '')), ''),
+            points = points + ?4,
+            stamps_earned_at = NULLIF(MAX(COALESCE(stamps_earned_at, ''), COALESCE((SELECT stamps_earned_at FROM members WHERE id = ?5), '')), ''),
+            birthday = ?6, gender = ?7, memo = ?8, tags = ?9, custom_fields = ?10, updated_at = ?11
+         WHERE id = ?12",
+        rusqlite::params![
+            stamps,
+            prepaid_balance,
+            prepaid_expires_at,
+            points,
+            duplicate.id,
+            details.birthday.map(|d| d.to_string()),
+            details.gender.map(|g| g.as_str()),
+            details.memo,
+            tags,
+            custom_fields,
+            now,
+            survivor.id
+        ],
+    )?;
+    tx.execute(
+        "UPDATE members SET stamps = 0, prepaid_balance = 0, points = 0, deleted_at = ?1, updated_at = ?1 WHERE id = ?2",
+        [&now, &duplicate.id],
+    )?;
+
+    tx.execute(
+        &format!(
+            "INSERT INTO member_merges ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            MEMBER_MERGE_COLUMNS
+        ),
+        rusqlite::params![
+            id,
+            survivor.id,
+            duplicate.id,
+            duplicate.name,
+            duplicate.phone,
+            stamps,
+            prepaid_balance,
+            points,
+            ledger_entries as i32,
+            reservations as i32,
+            merged_by,
+            reason,
+            now
+        ],
+    )?;
+
+    let merge = tx.query_row(
+        &format!(
+            "SELECT {} FROM member_merges WHERE id = ?1",
+            MEMBER_MERGE_COLUMNS
+        ),
+        [&id],
+        map_member_merge,
+    )?;
+    tx.commit()?;
+    Ok(merge)
+}
+
+pub fn get_member_merges(member_id: &str) -> Vec<MemberMerge> {
+    let conn = get_db().lock();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM member_merges WHERE survivor_id = ?1 OR merged_member_id = ?1 ORDER BY merged_at DESC",
+            MEMBER_MERGE_COLUMNS
+        ))
+        .unwrap();
+    stmt.query_map([member_id], map_member_merge)
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
+}
+
+// ==================== Prepaid Operations ====================
+const PREPAID_TRANSACTION_COLUMNS: &str = "id, member_id, kind, amount, bonus_amount, paid_amount, method, ledger_entry_id, balance_after, expires_at, memo, created_by, created_at";
+
+fn map_prepaid_transaction(row: &rusqlite::Row) -> rusqlite::Result<PrepaidTransaction> {
+    Ok(PrepaidTransaction {
+        id: row.get(0)?,
+        member_id: row.get(1)?,
+        kind: PrepaidTransactionKind::from_str(&row.get::<_, String>(2)?),
+        amount: row.get(3)?,
+        bonus_amount: row.get(4)?,
+        paid_amount: row.get(5)?,
+        method: row
+            .get::<_, Option<String>>(6)?
+            .map(|m| PaymentMethod::from_str(&m)),
+        ledger_entry_id: row.get(7)?,
+        balance_after: row.get(8)?,
+        expires_at: row
+            .get::<_, Option<String>>(9)?
+            .and_then(|s| s.parse().ok()),
+        memo: row.get(10)?,
+        created_by: row.get(11)?,
+        created_at: row.get::<_, String>(12)?.parse().unwrap_or_default(),
+    })
+}
+
+pub fn get_prepaid_transactions(member_id: &str) -> Vec<PrepaidTransaction> {
+    let conn = get_db().lock();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM prepaid_transactions WHERE member_id = ?1 ORDER BY created_at DESC, rowid DESC",
+            PREPAID_TRANSACTION_COLUMNS
+        ))
+        .unwrap();
+    stmt.query_map([member_id], map_prepaid_transaction)
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
+}
+
+// Moves a member's balance and records the movement in one transaction.
+// A top-up also pushes the expiry date out to `expires_at`.
+pub fn record_prepaid_transaction(
+    member_id: &str,
+    kind: PrepaidTransactionKind,
+    movement: &PrepaidMovement,
+) -> WriteResult<PrepaidTransaction> {
+    let mut conn = get_db().lock();
+    let tx = conn.transaction()?;
+    let transaction = insert_prepaid_transaction(&tx, member_id, kind, movement)?;
+    tx.commit()?;
+    Ok(transaction)
+}
+
+// The balance move itself, for callers that already hold a transaction
+fn insert_prepaid_transaction(
+    conn: &Connection,
+    member_id: &str,
+    kind: PrepaidTransactionKind,
+    movement: &PrepaidMovement,
+) -> WriteResult<PrepaidTransaction> {
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+
+    let balance: i32 = conn.query_row(
+        "SELECT prepaid_balance FROM members WHERE id = ?1 AND deleted_at IS NULL",
+        [member_id],
+        |row| row.get(0),
+    )?;
+    let balance_after = balance + movement.amount;
+    if balance_after < 0 {
+        return Err(conflict(&format!(
+            "선불권 잔액({}원)이 부족합니다",
+            balance
+        )));
+    }
+    let expires_at = movement.expires_at.map(|t| t.to_rfc3339());
+
+    conn.execute(
+        "INSERT INTO prepaid_transactions (id, member_id, kind, amount, bonus_amount, paid_amount, method, ledger_entry_id, balance_after, expires_at, memo, created_by, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        rusqlite::params![
+            id,
+            member_id,
+            kind.as_str(),
+            movement.amount,
+            movement.bonus_amount,
+            movement.paid_amount,
+            movement.method.map(|m| m.as_str()),
+            movement.ledger_entry_id,
+            balance_after,
+            expires_at,
+            movement.memo,
+            movement.created_by,
+            now
+        ],
+    )?;
+    conn.execute(
+        "UPDATE members SET prepaid_balance = ?1, prepaid_expires_at = COALESCE(?2, prepaid_expires_at), updated_at = ?3 WHERE id = ?4",
+        rusqlite::params![balance_after, expires_at, now, member_id],
+    )?;
+
+    Ok(conn.query_row(
+        &format!(
+            "SELECT {} FROM prepaid_transactions WHERE id = ?1",
+            PREPAID_TRANSACTION_COLUMNS
+        ),
+        [&id],
+        map_prepaid_transaction,
+    )?)
+}
+
+// Zeroes balances whose expiry date has passed and returns the expiries written
+pub fn expire_prepaid_balances() -> Vec<PrepaidTransaction> {
+    let expired: Vec<(String, i32)> = {
+        let conn = get_db().lock();
+        let mut stmt = conn
+            .prepare("SELECT id, prepaid_balance FROM members WHERE deleted_at IS NULL AND prepaid_balance > 0 AND prepaid_expires_at IS NOT NULL AND prepaid_expires_at < ?1")
+            .unwrap();
+        stmt.query_map([Utc::now().to_rfc3339()], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
+    };
+
+    expired
+        .into_iter()
+        .filter_map(|(member_id, balance)| {
+            let movement = PrepaidMovement {
+                amount: -balance,
+                memo: Some("유효기간 만료".to_string()),
+                ..Default::default()
+            };
+            record_prepaid_transaction(&member_id, PrepaidTransactionKind::Expire, &movement).ok()
+        })
+        .collect()
+}
+
+pub fn get_prepaid_policy() -> PrepaidPolicy {
+    get_setting("prepaid_policy")
+}
+
+pub fn save_prepaid_policy(policy: &PrepaidPolicy) -> Result<()> {
+    save_setting("prepaid_policy", policy)
+}
+
+// ==================== Point Operations ====================
+const POINT_TRANSACTION_COLUMNS: &str = "id, member_id, kind, amount, balance_after, ledger_entry_id, source_entry_id, actor, reason, created_at";
+
+fn map_point_transaction(row: &rusqlite::Row) -> rusqlite::Result<PointTransaction> {
+    Ok(PointTransaction {
+        id: row.get(0)?,
+        member_id: row.get(1)?,
+        kind: PointTransactionKind::from_str(&row.get::<_, String>(2)?),
+        amount: row.get(3)?,
+        balance_after: row.get(4)?,
+        ledger_entry_id: row.get(5)?,
+        source_entry_id: row.get(6)?,
+        actor: row.get(7)?,
+        reason: row.get(8)?,
+        created_at: row.get::<_, String>(9)?.parse().unwrap_or_default(),
+    })
+}
+
+// Moves a member's points and records the movement. Taking back points the
+// member already spent stops at zero; spending more than the balance fails.
+pub fn record_point_transaction(
+    member_id: &str,
+    kind: PointTransactionKind,
+    amount: i32,
+    movement: &PointMovement,
+) -> WriteResult<PointTransaction> {
+    let mut conn = get_db().lock();
+    let tx = conn.transaction()?;
+    let transaction = insert_point_transaction(&tx, member_id, kind, amount, movement)?;
+    tx.commit()?;
+    Ok(transaction)
+}
+
+fn insert_point_transaction(
+    conn: &Connection,
+    member_id: &str,
+    kind: PointTransactionKind,
+    amount: i32,
+    movement: &PointMovement,
+) -> WriteResult<PointTransaction> {
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+
+    let points: i32 = conn.query_row(
+        "SELECT points FROM members WHERE id = ?1 AND deleted_at IS NULL",
+        [member_id],
+        |row| row.get(0),
+    )?;
+    if kind == PointTransactionKind::Redeem && points + amount < 0 {
+        return Err(conflict(&format!("포인트 잔액({}P)이 부족합니다", points)));
+    }
+    let amount = amount.max(-points);
+    let balance_after = points + amount;
+
+    conn.execute(
+        &format!(
+            "INSERT INTO point_transactions ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            POINT_TRANSACTION_COLUMNS
+        ),
+        rusqlite::params![
+            id,
+            member_id,
+            kind.as_str(),
+            amount,
+            balance_after,
+            movement.ledger_entry_id,
+            movement.source_entry_id,
+            movement.actor,
+            movement.reason,
+            now
+        ],
+    )?;
+    conn.execute(
+        "UPDATE members SET points = ?1, updated_at = ?2 WHERE id = ?3",
+        rusqlite::params![balance_after, now, member_id],
+    )?;
+
+    Ok(conn.query_row(
+        &format!(
+            "SELECT {} FROM point_transactions WHERE id = ?1",
+            POINT_TRANSACTION_COLUMNS
+        ),
+        [&id],
+        map_point_transaction,
+    )?)
+}
+
+pub fn get_point_transactions(member_id: &str) -> Vec<PointTransaction> {
+    let conn = get_db().lock();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM point_transactions WHERE member_id = ?1 ORDER BY created_at DESC, rowid DESC",
+            POINT_TRANSACTION_COLUMNS
+        ))
+        .unwrap();
+    stmt.query_map([member_id], map_point_transaction)
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
+}
+
+// Everything recorded against a sale: its earn/redeem and later reversals
+pub fn get_point_transactions_for_sale(ledger_entry_id: &str) -> Vec<PointTransaction> {
+    let conn = get_db().lock();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM point_transactions WHERE ledger_entry_id = ?1 OR source_entry_id = ?1 ORDER BY created_at ASC, rowid ASC",
+            POINT_TRANSACTION_COLUMNS
+        ))
+        .unwrap();
+    stmt.query_map([ledger_entry_id], map_point_transaction)
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
+}
+
+pub fn get_member_fields() -> Vec<MemberFieldDefinition> {
+    get_setting("member_fields")
+}
+
+pub fn save_member_fields(fields: &[MemberFieldDefinition]) -> Result<()> {
+    save_setting("member_fields", &fields)
+}
+
+pub fn get_phone_normalization_report() -> PhoneNormalizationReport {
+    get_setting("phone_normalization_report")
+}
+
+pub fn save_phone_normalization_report(report: &PhoneNormalizationReport) -> Result<()> {
+    save_setting("phone_normalization_report", report)
+}
+
+pub fn get_point_policy() -> PointPolicy {
+    get_setting("point_policy")
+}
+
+pub fn save_point_policy(policy: &PointPolicy) -> Result<()> {
+    save_setting("point_policy", policy)
+}
+
+// ==================== Membership Tier Operations ====================
+const MEMBERSHIP_TIER_COLUMNS: &str =
+    "id, name, level, min_spend, min_visits, discount_percent, created_at, updated_at";
+
+fn map_membership_tier(row: &rusqlite::Row) -> rusqlite::Result<MembershipTier> {
+    Ok(MembershipTier {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        level: row.get(2)?,
+        min_spend: row.get(3)?,
+        min_visits: row.get(4)?,
+        discount_percent: row.get(5)?,
+        created_at: row.get::<_, String>(6)?.parse().unwrap_or_default(),
+        updated_at: row.get::<_, String>(7)?.parse().unwrap_or_default(),
+    })
+}
+
+pub fn get_membership_tiers() -> Vec<MembershipTier> {
+    let conn = get_db().lock();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM membership_tiers WHERE deleted_at IS NULL ORDER BY level ASC",
+            MEMBERSHIP_TIER_COLUMNS
+        ))
+        .unwrap();
+
+    let iter = stmt.query_map([], map_membership_tier).unwrap();
+    iter.filter_map(|r| r.ok()).collect()
+}
+
+// Also finds deleted tiers so members still on one can be moved off it
+pub fn get_membership_tier_by_id(id: &str) -> Option<MembershipTier> {
+    let conn = get_db().lock();
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM membership_tiers WHERE id = ?1",
+            MEMBERSHIP_TIER_COLUMNS
+        ),
+        [id],
+        map_membership_tier,
+    )
+    .ok()
+}
+
+pub fn create_membership_tier(request: &MembershipTierRequest) -> Result<MembershipTier> {
+    let id = Uuid::new_v4().to_string();
+    let now_str = Utc::now().to_rfc3339();
+
+    {
+        let conn = get_db().lock();
+        conn.execute(
+            "INSERT INTO membership_tiers (id, name, level, min_spend, min_visits, discount_percent, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)",
+            rusqlite::params![
+                id,
+                request.name,
+                request.level,
+                request.min_spend,
+                request.min_visits,
+                request.discount_percent,
+                now_str
+            ],
+        )?;
+    }
+
+    get_membership_tier_by_id(&id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
+
+pub fn update_membership_tier(id: &str, request: &MembershipTierRequest) -> Result<MembershipTier> {
+    {
+        let conn = get_db().lock();
+        let updated = conn.execute(
+            "UPDATE membership_tiers SET name = ?1, level = ?2, min_spend = ?3, min_visits = ?4, discount_percent = ?5, updated_at = ?6
+             WHERE id = ?7 AND deleted_at IS NULL",
+            rusqlite::params![
+                request.name,
+                request.level,
+                request.min_spend,
+                request.min_visits,
+                request.discount_percent,
+                Utc::now().to_rfc3339(),
+                id
+            ],
+        )?;
+        if updated == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+    }
+    get_membership_tier_by_id(id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
+
+pub fn delete_membership_tier(id: &str) -> Result<()> {
+    let conn = get_db().lock();
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE membership_tiers SET deleted_at = ?1 WHERE id = ?2",
+        [&now, id],
+    )?;
+    Ok(())
+}
+
+// Net spend and visits since `since`. A refund counts against the month of
+// the sale it refunds, and a fully refunded sale is not a visit.
+pub fn get_member_activity(member_id: &str, since: DateTime<Utc>) -> (i32, i32) {
+    let conn = get_db().lock();
+    let since = since.to_rfc3339();
+    conn.query_row(
+        "SELECT
+            COALESCE(SUM(l.total_price), 0),
+            COALESCE(SUM(CASE WHEN l.entry_type = 'service'
+                AND (l.total_price + COALESCE((SELECT SUM(r.total_price) FROM ledger_entries r WHERE r.refund_of = l.id), 0) > 0
+                     OR (l.total_price = 0 AND NOT EXISTS (SELECT 1 FROM ledger_entries r WHERE r.refund_of = l.id)))
+                THEN 1 ELSE 0 END), 0)
+         FROM ledger_entries l
+         WHERE l.member_id = ?1 AND l.entry_type IN ('service', 'package_sale', 'refund')
+           AND COALESCE((SELECT o.completed_at FROM ledger_entries o WHERE o.id = l.refund_of), l.completed_at) >= ?2",
+        [member_id, &since],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .unwrap_or((0, 0))
+}
+
+const TIER_CHANGE_COLUMNS: &str = "id, member_id, member_name, from_tier_id, from_tier_name, to_tier_id, to_tier_name, upgrade, spend, visits, changed_at";
+
+fn map_tier_change(row: &rusqlite::Row) -> rusqlite::Result<TierChange> {
+    Ok(TierChange {
+        id: row.get(0)?,
+        member_id: row.get(1)?,
+        member_name: row.get(2)?,
+        from_tier_id: row.get(3)?,
+        from_tier_name: row.get(4)?,
+        to_tier_id: row.get(5)?,
+        to_tier_name: row.get(6)?,
+        upgrade: row.get(7)?,
+        spend: row.get(8)?,
+        visits: row.get(9)?,
+        changed_at: row.get::<_, String>(10)?.parse().unwrap_or_default(),
+    })
+}
+
+// Puts the member on `to` and records the move
+pub fn change_member_tier(
+    member: &Member,
+    from: Option<&MembershipTier>,
+    to: Option<&MembershipTier>,
+    spend: i32,
+    visits: i32,
+) -> Result<TierChange> {
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    let level = |t: Option<&MembershipTier>| t.map(|t| t.level).unwrap_or(i32::MIN);
+    let mut conn = get_db().lock();
+    let tx = conn.transaction()?;
+
+    tx.execute(
+        "UPDATE members SET tier_id = ?1, updated_at = ?2 WHERE id = ?3",
+        rusqlite::params![to.map(|t| &t.id), now, member.id],
+    )?;
+    tx.execute(
+        &format!(
+            "INSERT INTO tier_changes ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            TIER_CHANGE_COLUMNS
+        ),
+        rusqlite::params![
+            id,
+            member.id,
+            member.name,
+            from.map(|t| &t.id),
+            from.map(|t| &t.name),
+            to.map(|t| &t.id),
+            to.map(|t| &t.name),
+            level(to) > level(from),
+            spend,
+            visits,
+            now
+        ],
+    )?;
+
+    let change = tx.query_row(
+        &format!(
+            "SELECT {} FROM tier_changes WHERE id = ?1",
+            TIER_CHANGE_COLUMNS
+        ),
+        [&id],
+        map_tier_change,
+    )?;
+    tx.commit()?;
+    Ok(change)
+}
+
+pub fn get_tier_changes(start_date: &str, end_date: &str) -> Vec<TierChange> {
+    let conn = get_db().lock();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM tier_changes WHERE DATE(changed_at, 'localtime') >= ?1 AND DATE(changed_at, 'localtime') <= ?2 ORDER BY changed_at DESC, rowid DESC",
+            TIER_CHANGE_COLUMNS
+        ))
+        .unwrap();
+    stmt.query_map([start_date, end_date], map_tier_change)
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
+}
+
+// ==================== Stamp Program Operations ====================
+const STAMP_PROGRAM_COLUMNS: &str = "id, name, stamps_required, reward_kind, reward_value, reward_service_name, amount_per_stamp, eligible_services, expiry_days, active, created_at, updated_at";
+
+fn map_stamp_program(row: &rusqlite::Row) -> rusqlite::Result<StampProgram> {
+    Ok(StampProgram {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        stamps_required: row.get(2)?,
+        reward_kind: StampRewardKind::from_str(&row.get::<_, String>(3)?),
+        reward_value: row.get(4)?,
+        reward_service_name: row.get(5)?,
+        amount_per_stamp: row.get(6)?,
+        eligible_services: serde_json::from_str(&row.get::<_, String>(7)?).unwrap_or_default(),
+        expiry_days: row.get(8)?,
+        active: row.get(9)?,
+        created_at: row.get::<_, String>(10)?.parse().unwrap_or_default(),
+        updated_at: row.get::<_, String>(11)?.parse().unwrap_or_default(),
+    })
+}
+
+pub fn get_stamp_programs() -> Vec<StampProgram> {
+    let conn = get_db().lock();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM stamp_programs WHERE deleted_at IS NULL ORDER BY created_at ASC",
+            STAMP_PROGRAM_COLUMNS
+        ))
+        .unwrap();
+
+    let iter = stmt.query_map([], map_stamp_program).unwrap();
+    iter.filter_map(|r| r.ok()).collect()
+}
+
+// Also finds deleted programs so old redemptions can still be traced
+pub fn get_stamp_program_by_id(id: &str) -> Option<StampProgram> {
+    let conn = get_db().lock();
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM stamp_programs WHERE id = ?1",
+            STAMP_PROGRAM_COLUMNS
+        ),
+        [id],
+        map_stamp_program,
+    )
+    .ok()
+}
+
+pub fn get_active_stamp_program() -> Option<StampProgram> {
+    let conn = get_db().lock();
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM stamp_programs WHERE active = 1 AND deleted_at IS NULL ORDER BY updated_at DESC LIMIT 1",
+            STAMP_PROGRAM_COLUMNS
+        ),
+        [],
+        map_stamp_program,
+    )
+    .ok()
+}
+
+// Saving an active program switches the others off
+fn deactivate_other_stamp_programs(conn: &Connection, id: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE stamp_programs SET active = 0, updated_at = ?1 WHERE id != ?2 AND active = 1",
+        [&Utc::now().to_rfc3339(), id],
+    )?;
+    Ok(())
+}
+
+pub fn create_stamp_program(request: &StampProgramRequest) -> Result<StampProgram> {
+    let id = Uuid::new_v4().to_string();
+    let now_str = Utc::now().to_rfc3339();
+    let eligible_services = serde_json::to_string(&request.eligible_services)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+
+    {
+        let conn = get_db().lock();
+        conn.execute(
+            "INSERT INTO stamp_programs (id, name, stamps_required, reward_kind, reward_value, reward_service_name, amount_per_stamp, eligible_services, expiry_days, active, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?11)",
+            rusqlite::params![
+                id,
+                request.name,
+                request.stamps_required,
+                request.reward_kind.as_str(),
+                request.reward_value,
+                request.reward_service_name,
+                request.amount_per_stamp,
+                eligible_services,
+                request.expiry_days,
+                request.active,
+                now_str
+            ],
+        )?;
+        if request.active {
+            deactivate_other_stamp_programs(&conn, &id)?;
+        }
+    }
+
+    get_stamp_program_by_id(&id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
+
+pub fn update_stamp_program(id: &str, request: &StampProgramRequest) -> Result<StampProgram> {
+    let eligible_services = serde_json::to_string(&request.eligible_services)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+
+    {
+        let conn = get_db().lock();
+        let updated = conn.execute(
+            "UPDATE stamp_programs SET name = ?1, stamps_required = ?2, reward_kind = ?3, reward_value = ?4, reward_service_name = ?5,
+                    amount_per_stamp = ?6, eligible_services = ?7, expiry_days = ?8, active = ?9, updated_at = ?10
+             WHERE id = ?11 AND deleted_at IS NULL",
+            rusqlite::params![
+                request.name,
+                request.stamps_required,
+                request.reward_kind.as_str(),
+                request.reward_value,
+                request.reward_service_name,
+                request.amount_per_stamp,
+                eligible_services,
+                request.expiry_days,
+                request.active,
+                Utc::now().to_rfc3339(),
+                id
+            ],
+        )?;
+        if updated == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+        if request.active {
+            deactivate_other_stamp_programs(&conn, id)?;
+        }
+    }
+    get_stamp_program_by_id(id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
+
+pub fn delete_stamp_program(id: &str) -> Result<()> {
+    let conn = get_db().lock();
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE stamp_programs SET deleted_at = ?1, active = 0 WHERE id = ?2",
+        [&now, id],
+    )?;
+    Ok(())
+}
+
+// ==================== Package Operations ====================
+const PACKAGE_COLUMNS: &str =
+    "id, name, service_name, total_count, price, validity_days, active, created_at, updated_at";
+
+fn map_package(row: &rusqlite::Row) -> rusqlite::Result<Package> {
+    Ok(Package {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        service_name: row.get(2)?,
+        total_count: row.get(3)?,
+        price: row.get(4)?,
+        validity_days: row.get(5)?,
+        active: row.get(6)?,
+        created_at: row.get::<_, String>(7)?.parse().unwrap_or_default(),
+        updated_at: row.get::<_, String>(8)?.parse().unwrap_or_default(),
+    })
+}
+
+pub fn get_packages() -> Vec<Package> {
+    let conn = get_db().lock();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM packages WHERE deleted_at IS NULL ORDER BY created_at ASC",
+            PACKAGE_COLUMNS
+        ))
+        .unwrap();
+
+    let iter = stmt.query_map([], map_package).unwrap();
+    iter.filter_map(|r| r.ok()).collect()
+}
+
+pub fn get_package_by_id(id: &str) -> Option<Package> {
+    let conn = get_db().lock();
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM packages WHERE id = ?1 AND deleted_at IS NULL",
+            PACKAGE_COLUMNS
+        ),
+        [id],
+        map_package,
+    )
+    .ok()
+}
+
+pub fn create_package(request: &PackageRequest) -> Result<Package> {
+    let id = Uuid::new_v4().to_string();
+    let now_str = Utc::now().to_rfc3339();
+
+    {
+        let conn = get_db().lock();
+        conn.execute(
+            "INSERT INTO packages (id, name, service_name, total_count, price, validity_days, active, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)",
+            rusqlite::params![
+                id,
+                request.name,
+                request.service_name,
+                request.total_count,
+                request.price,
+                request.validity_days,
+                request.active,
+                now_str
+            ],
+        )?;
+    }
+
+    get_package_by_id(&id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
+
+pub fn update_package(id: &str, request: &PackageRequest) -> Result<Package> {
+    {
+        let conn = get_db().lock();
+        conn.execute(
+            "UPDATE packages SET name = ?1, service_name = ?2, total_count = ?3, price = ?4, validity_days = ?5, active = ?6, updated_at = ?7
+             WHERE id = ?8 AND deleted_at IS NULL",
+            rusqlite::params![
+                request.name,
+                request.service_name,
+                request.total_count,
+                request.price,
+                request.validity_days,
+                request.active,
+                Utc::now().to_rfc3339(),
+                id
+            ],
+        )?;
+    }
+    get_package_by_id(id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
+
+pub fn delete_package(id: &str) -> Result<()> {
+    let conn = get_db().lock();
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE packages SET deleted_at = ?1 WHERE id = ?2",
+        [&now, id],
+    )?;
+    Ok(())
+}
+
+const MEMBER_PACKAGE_COLUMNS: &str = "id, member_id, package_id, name, service_name, total_count, remaining_count, price, payment_method, purchased_at, expires_at, created_by, ledger_entry_id";
+
+// Passes with visits left that have not expired (?2 = now); soonest to expire first
+const USABLE_PACKAGE_CONDITION: &str =
+    "remaining_count > 0 AND (expires_at IS NULL OR expires_at > ?2)";
+const USABLE_PACKAGE_ORDER: &str = "expires_at IS NULL, expires_at ASC, purchased_at ASC";
+
+fn map_member_package(row: &rusqlite::Row) -> rusqlite::Result<MemberPackage> {
+    Ok(MemberPackage {
+        id: row.get(0)?,
+        member_id: row.get(1)?,
+        package_id: row.get(2)?,
+        name: row.get(3)?,
+        service_name: row.get(4)?,
+        total_count: row.get(5)?,
+        remaining_count: row.get(6)?,
+        price: row.get(7)?,
+        payment_method: PaymentMethod::from_str(&row.get::<_, String>(8)?),
+        purchased_at: row.get::<_, String>(9)?.parse().unwrap_or_default(),
+        expires_at: row
+            .get::<_, Option<String>>(10)?
+            .and_then(|t| t.parse().ok()),
+        created_by: row.get(11)?,
+        ledger_entry_id: row.get(12)?,
+    })
+}
+
+pub fn get_member_packages(member_id: &str, usable_only: bool) -> Vec<MemberPackage> {
+    let conn = get_db().lock();
+    let now = Utc::now().to_rfc3339();
+
+    if usable_only {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM member_packages WHERE member_id = ?1 AND {} ORDER BY {}",
+                MEMBER_PACKAGE_COLUMNS, USABLE_PACKAGE_CONDITION, USABLE_PACKAGE_ORDER
+            ))
+            .unwrap();
+        let iter = stmt
+            .query_map([member_id, now.as_str()], map_member_package)
+            .unwrap();
+        iter.filter_map(|r| r.ok()).collect()
+    } else {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM member_packages WHERE member_id = ?1 ORDER BY purchased_at DESC",
+                MEMBER_PACKAGE_COLUMNS
+            ))
+            .unwrap();
+        let iter = stmt.query_map([member_id], map_member_package).unwrap();
+        iter.filter_map(|r| r.ok()).collect()
+    }
+}
+
+pub fn get_member_package_by_id(id: &str) -> Option<MemberPackage> {
+    let conn = get_db().lock();
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM member_packages WHERE id = ?1",
+            MEMBER_PACKAGE_COLUMNS
+        ),
+        [id],
+        map_member_package,
+    )
+    .ok()
+}
+
+pub fn get_member_package_by_ledger_id(ledger_entry_id: &str) -> Option<MemberPackage> {
+    let conn = get_db().lock();
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM member_packages WHERE ledger_entry_id = ?1",
+            MEMBER_PACKAGE_COLUMNS
+        ),
+        [ledger_entry_id],
+        map_member_package,
+    )
+    .ok()
+}
+
+// Sells a pass: the sale and its payment go in the ledger, and a pass bought
+// from the prepaid balance takes it off in the same transaction
+pub fn complete_package_sale(
+    member: &Member,
+    package: &Package,
+    payment: &PaymentInput,
+    created_by: &str,
+) -> WriteResult<CompletedPackageSale> {
+    let ledger_entry_id = Uuid::new_v4().to_string();
+    let member_package_id = Uuid::new_v4().to_string();
+    let now = Utc::now();
+    let now_str = now.to_rfc3339();
+    let expires_at = (package.validity_days > 0)
+        .then(|| (now + chrono::Duration::days(i64::from(package.validity_days))).to_rfc3339());
+
+    let mut conn = get_db().lock();
+    let tx = conn.transaction()?;
+
+    tx.execute(
+        "INSERT INTO ledger_entries (id, entry_type, reservation_id, member_id, member_name, seat_id, staff_id, staff_name, gross_amount, discount_amount, total_price, deposit_applied, completed_at, created_at)
+         VALUES (?1, ?2, NULL, ?3, ?4, 0, '', ?5, ?6, 0, ?6, 0, ?7, ?7)",
+        rusqlite::params![
+            ledger_entry_id,
+            LedgerEntryType::PackageSale.as_str(),
+            member.id,
+            member.name,
+            created_by,
+            package.price,
+            now_str
+        ],
+    )?;
+    tx.execute(
+        "INSERT INTO selected_services (ledger_entry_id, name, length, price) VALUES (?1, ?2, NULL, ?3)",
+        rusqlite::params![ledger_entry_id, package.name, package.price],
+    )?;
+    if payment.amount > 0 {
+        insert_payment(&tx, &ledger_entry_id, payment, &now_str)?;
+    }
+
+    tx.execute(
+        "INSERT INTO member_packages (id, member_id, package_id, name, service_name, total_count, remaining_count, price, payment_method, purchased_at, expires_at, created_by, ledger_entry_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        rusqlite::params![
+            member_package_id,
+            member.id,
+            package.id,
+            package.name,
+            package.service_name,
+            package.total_count,
+            package.price,
+            payment.method.as_str(),
+            now_str,
+            expires_at,
+            created_by,
+            ledger_entry_id
+        ],
+    )?;
+
+    let prepaid = if payment.method == PaymentMethod::Prepaid && payment.amount > 0 {
+        let movement = PrepaidMovement {
+            amount: -payment.amount,
+            ledger_entry_id: Some(ledger_entry_id.clone()),
+            memo: Some(format!("{} 구매", package.name)),
+            created_by: Some(created_by.to_string()),
+            ..Default::default()
+        };
+        Some(insert_prepaid_transaction(
+            &tx,
+            &member.id,
+            PrepaidTransactionKind::Use,
+            &movement,
+        )?)
+    } else {
+        None
+    };
+
+    let member_package = tx.query_row(
+        &format!(
+            "SELECT {} FROM member_packages WHERE id = ?1",
+            MEMBER_PACKAGE_COLUMNS
+        ),
+        [&member_package_id],
+        map_member_package,
+    )?;
+    tx.commit()?;
+
+    Ok(CompletedPackageSale {
+        member_package,
+        prepaid,
+    })
+}
+
+// Takes one visit off the pass for a completed sale
+fn take_package_visit(
+    conn: &Connection,
+    member_package_id: &str,
+    ledger_entry_id: &str,
+) -> WriteResult<()> {
+    let now = Utc::now().to_rfc3339();
+    let service_name: String = conn
+        .query_row(
+            &format!(
+                "SELECT service_name FROM member_packages WHERE id = ?1 AND {}",
+                USABLE_PACKAGE_CONDITION
+            ),
+            [member_package_id, now.as_str()],
+            |row| row.get(0),
+        )
+        .map_err(|_| conflict("회수권 잔여 횟수가 없습니다"))?;
+    conn.execute(
+        "UPDATE member_packages SET remaining_count = remaining_count - 1 WHERE id = ?1",
+        [member_package_id],
+    )?;
+    conn.execute(
+        "INSERT INTO package_usages (id, member_package_id, ledger_entry_id, service_name, used_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![
+            Uuid::new_v4().to_string(),
+            member_package_id,
+            ledger_entry_id,
+            service_name,
+            now
+        ],
+    )?;
+    Ok(())
+}
+
+// Gives back the visits a voided sale took and returns the passes touched
+fn restore_package_usages(conn: &Connection, ledger_entry_id: &str) -> Result<Vec<String>> {
+    let package_ids: Vec<String> = {
+        let mut stmt = conn.prepare(
+            "SELECT member_package_id FROM package_usages WHERE ledger_entry_id = ?1 AND restored_at IS NULL",
+        )?;
+        let iter = stmt.query_map([ledger_entry_id], |row| row.get(0))?;
+        iter.filter_map(|r| r.ok()).collect()
+    };
+    for package_id in &package_ids {
+        conn.execute(
+            "UPDATE member_packages SET remaining_count = remaining_count + 1 WHERE id = ?1",
+            [package_id],
+        )?;
+    }
+    conn.execute(
+        "UPDATE package_usages SET restored_at = ?1 WHERE ledger_entry_id = ?2 AND restored_at IS NULL",
+        [&Utc::now().to_rfc3339(), ledger_entry_id],
+    )?;
+
+    let mut unique = package_ids;
+    unique.sort();
+    unique.dedup();
+    Ok(unique)
+}
+
+pub fn get_package_usages(member_package_id: &str) -> Vec<PackageUsage> {
+    let conn = get_db().lock();
+    let mut stmt = conn
+        .prepare("SELECT id, member_package_id, ledger_entry_id, service_name, used_at, restored_at FROM package_usages WHERE member_package_id = ?1 ORDER BY used_at DESC")
+        .unwrap();
+
+    stmt.query_map([member_package_id], |row| {
+        Ok(PackageUsage {
+            id: row.get(0)?,
+            member_package_id: row.get(1)?,
+            ledger_entry_id: row.get(2)?,
+            service_name: row.get(3)?,
+            used_at: row.get::<_, String>(4)?.parse().unwrap_or_default(),
+            restored_at: row
+                .get::<_, Option<String>>(5)?
+                .and_then(|t| t.parse().ok()),
+        })
+    })
+    .unwrap()
+    .filter_map(|r| r.ok())
+    .collect()
+}
+
+pub fn update_member(id: &str, name: &str, phone: &str, details: &MemberDetails) -> Result<Member> {
+    let to_json = |e: serde_json::Error| rusqlite::Error::ToSqlConversionFailure(Box::new(e));
+    let tags = serde_json::to_string(&details.tags).map_err(to_json)?;
+    let custom_fields = serde_json::to_string(&details.custom_fields).map_err(to_json)?;
+    {
+        let conn = get_db().lock();
+        let now = Utc::now().to_rfc3339();
+        // Consent keeps its original date until it is withdrawn
+        conn.execute(
+            "UPDATE members SET name = ?1, phone = ?2, birthday = ?3, gender = ?4, memo = ?5,
+                    marketing_consent_at = CASE WHEN ?6 THEN COALESCE(CASE WHEN marketing_consent = 1 THEN marketing_consent_at END, ?9) END,
+                    marketing_consent = ?6, tags = ?7, custom_fields = ?8, updated_at = ?9
+             WHERE id = ?10 AND deleted_at IS NULL",
+            rusqlite::params![
+                name,
+                phone,
+                details.birthday.map(|d| d.to_string()),
+                details.gender.map(|g| g.as_str()),
+                details.memo,
+                details.marketing_consent,
+                tags,
+                custom_fields,
+                now,
+                id
+            ],
+        )?;
+    }
+    get_member_by_id(id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
+
+pub fn delete_member(id: &str) -> Result<()> {
+    let conn = get_db().lock();
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE members SET deleted_at = ?1 WHERE id = ?2",
+        [&now, id],
+    )?;
+    Ok(())
+}
+
+pub fn get_stamps_earned_at(id: &str) -> Option<DateTime<Utc>> {
+    let conn = get_db().lock();
+    conn.query_row(
+        "SELECT stamps_earned_at FROM members WHERE id = ?1",
+        [id],
+        |row| row.get::<_, Option<String>>(0),
+    )
+    .ok()
+    .flatten()
+    .and_then(|t| t.parse().ok())
+}
+
+// ==================== Stamp History Operations ====================
+const STAMP_TRANSACTION_COLUMNS: &str = "id, member_id, kind, amount, balance_after, ledger_entry_id, program_id, actor, reason, created_at";
+
+fn map_stamp_transaction(row: &rusqlite::Row) -> rusqlite::Result<StampTransaction> {
+    Ok(StampTransaction {
+        id: row.get(0)?,
+        member_id: row.get(1)?,
+        kind: StampTransactionKind::from_str(&row.get::<_, String>(2)?),
+        amount: row.get(3)?,
+        balance_after: row.get(4)?,
+        ledger_entry_id: row.get(5)?,
+        program_id: row.get(6)?,
+        actor: row.get(7)?,
+        reason: row.get(8)?,
+        created_at: row.get::<_, String>(9)?.parse().unwrap_or_default(),
+    })
+}
+
+// Every change to members.stamps goes through here so the history always
+// adds up to the balance. Removals never take the balance below zero, and a
+// redemption the balance does not cover fails.
+pub fn record_stamp_transaction(
+    member_id: &str,
+    kind: StampTransactionKind,
+    amount: i32,
+    movement: &StampMovement,
+) -> WriteResult<StampTransaction> {
+    let mut conn = get_db().lock();
+    let tx = conn.transaction()?;
+    let transaction = insert_stamp_transaction(&tx, member_id, kind, amount, movement)?;
+    tx.commit()?;
+    Ok(transaction)
+}
+
+fn insert_stamp_transaction(
+    conn: &Connection,
+    member_id: &str,
+    kind: StampTransactionKind,
+    amount: i32,
+    movement: &StampMovement,
+) -> WriteResult<StampTransaction> {
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+
+    let stamps: i32 = conn.query_row(
+        "SELECT stamps FROM members WHERE id = ?1 AND deleted_at IS NULL",
+        [member_id],
+        |row| row.get(0),
+    )?;
+    if kind == StampTransactionKind::Redeem && stamps + amount < 0 {
+        return Err(conflict(&format!("스탬프가 부족합니다 ({}개)", stamps)));
+    }
+    let amount = amount.max(-stamps);
+    let balance_after = stamps + amount;
+
+    conn.execute(
+        &format!(
+            "INSERT INTO stamp_transactions ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            STAMP_TRANSACTION_COLUMNS
+        ),
+        rusqlite::params![
+            id,
+            member_id,
+            kind.as_str(),
+            amount,
+            balance_after,
+            movement.ledger_entry_id,
+            movement.program_id,
+            movement.actor,
+            movement.reason,
+            now
+        ],
+    )?;
+
+    // Only newly earned stamps restart the expiry clock
+    let earned = amount > 0
+        && matches!(
+            kind,
+            StampTransactionKind::Earn | StampTransactionKind::ManualAdd
+        );
+    conn.execute(
+        "UPDATE members SET stamps = ?1, stamps_earned_at = CASE WHEN ?2 THEN ?3 ELSE stamps_earned_at END, updated_at = ?3 WHERE id = ?4",
+        rusqlite::params![balance_after, earned, now, member_id],
+    )?;
+
+    Ok(conn.query_row(
+        &format!(
+            "SELECT {} FROM stamp_transactions WHERE id = ?1",
+            STAMP_TRANSACTION_COLUMNS
+        ),
+        [&id],
+        map_stamp_transaction,
+    )?)
+}
+
+pub fn get_stamp_transactions(member_id: &str) -> Vec<StampTransaction> {
+    let conn = get_db().lock();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM stamp_transactions WHERE member_id = ?1 ORDER BY created_at DESC, rowid DESC",
+            STAMP_TRANSACTION_COLUMNS
+        ))
+        .unwrap();
+    stmt.query_map([member_id], map_stamp_transaction)
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
+}
+
+pub fn get_stamp_transactions_by_ledger_id(ledger_entry_id: &str) -> Vec<StampTransaction> {
+    let conn = get_db().lock();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM stamp_transactions WHERE ledger_entry_id = ?1 ORDER BY created_at ASC, rowid ASC",
+            STAMP_TRANSACTION_COLUMNS
+        ))
+        .unwrap();
+    stmt.query_map([ledger_entry_id], map_stamp_transaction)
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
+}
+
+// Zeroes stamps not topped up since `cutoff` and returns the expiries written
+pub fn expire_stamps(cutoff: DateTime<Utc>) -> Vec<StampTransaction> {
+    let expired: Vec<(String, i32)> = {
+        let conn = get_db().lock();
+        let mut stmt = conn
+            .prepare("SELECT id, stamps FROM members WHERE deleted_at IS NULL AND stamps > 0 AND stamps_earned_at IS NOT NULL AND stamps_earned_at < ?1")
+            .unwrap();
+        stmt.query_map([cutoff.to_rfc3339()], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .filter_map(|r| r.ok())
+            .collect()
+    };
+
+    expired
+        .into_iter()
+        .filter_map(|(member_id, stamps)| {
+            let movement = StampMovement {
+                reason: Some("유효기간 만료".to_string()),
+                ..Default::default()
+            };
+            record_stamp_transaction(&member_id, StampTransactionKind::Expire, -stamps, &movement)
+                .ok()
+        })
+        .collect()
+}
+
+// Rewrites members.stamps from the history wherever the two disagree and
+// returns what was out of line
+pub fn reconcile_stamps() -> Result<Vec<StampDiscrepancy>> {
+    let conn = get_db().lock();
+    let discrepancies: Vec<StampDiscrepancy> = {
+        let mut stmt = conn.prepare(
+            "SELECT m.id, m.name, m.stamps, COALESCE(SUM(t.amount), 0) AS history_total
+             FROM members m LEFT JOIN stamp_transactions t ON t.member_id = m.id
+             WHERE m.deleted_at IS NULL
+             GROUP BY m.id
+             HAVING m.stamps != history_total",
+        )?;
+        let iter = stmt.query_map([], |row| {
+            Ok(StampDiscrepancy {
+                member_id: row.get(0)?,
+                member_name: row.get(1)?,
+                stamps: row.get(2)?,
+                history_total: row.get(3)?,
+            })
+        })?;
+        iter.filter_map(|r| r.ok()).collect()
+    };
+
+    let now = Utc::now().to_rfc3339();
+    for discrepancy in &discrepancies {
+        conn.execute(
+            "UPDATE members SET stamps = ?1, updated_at = ?2 WHERE id = ?3",
+            rusqlite::params![discrepancy.history_total, now, discrepancy.member_id],
+        )?;
+    }
+    Ok(discrepancies)
+}
+
+// Rewrites member phones into E.164. Live members go first so they keep a
+// number when a deleted member typed the same one differently; a number that
+// would collide with another member is left alone and reported instead.
+pub fn normalize_member_phones() -> Result<PhoneNormalizationReport> {
+    let mut conn = get_db().lock();
+    let rows: Vec<(String, String, String, bool)> = {
+        let mut stmt = conn.prepare(
+            "SELECT id, name, phone, deleted_at IS NOT NULL FROM members
+             ORDER BY deleted_at IS NOT NULL, created_at",
+        )?;
+        let iter = stmt.query_map([], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })?;
+        iter.filter_map(|r| r.ok()).collect()
+    };
+
+    // Who holds each number as currently stored
+    let mut holders: std::collections::HashMap<String, String> = rows
+        .iter()
+        .map(|(id, _, phone, _)| (phone.clone(), id.clone()))
+        .collect();
+    let mut report = PhoneNormalizationReport {
+        checked_at: Some(Utc::now()),
+        ..Default::default()
+    };
+
+    let tx = conn.transaction()?;
+    let now = Utc::now().to_rfc3339();
+    for (id, name, current, deleted) in rows {
+        let issue = |conflicts_with: Option<String>| PhoneIssue {
+            member_id: id.clone(),
+            member_name: name.clone(),
+            phone: current.clone(),
+            conflicts_with,
+        };
+        let normalized = match phone::normalize(&current) {
+            Ok(normalized) if normalized == current => continue,
+            Ok(normalized) => normalized,
+            Err(_) => {
+                if !deleted {
+                    report.invalid.push(issue(None));
+                }
+                continue;
+            }
+        };
+
+        if let Some(holder) = holders.get(&normalized) {
+            if !deleted {
+                report.collisions.push(issue(Some(holder.clone())));
+            }
+            continue;
+        }
+
+        tx.execute(
+            "UPDATE members SET phone = ?1, updated_at = ?2 WHERE id = ?3",
+            rusqlite::params![normalized, now, id],
+        )?;
+        holders.remove(&current);
+        holders.insert(normalized, id);
+        report.normalized += 1;
+    }
+    tx.commit()?;
+    Ok(report)
+}
+
+pub fn phone_exists(phone: &str, exclude_id: Option<&str>) -> bool {
+    let conn = get_db().lock();
+    let count: i32 = if let Some(exclude) = exclude_id {
+        conn.query_row(
+            "SELECT COUNT(*) FROM members WHERE phone = ?1 AND id != ?2 AND deleted_at IS NULL",
+            [phone, exclude],
+            |row| row.get(0),
+        )
+        .unwrap_or(0)
+    } else {
+        conn.query_row(
+            "SELECT COUNT(*) FROM members WHERE phone = ?1 AND deleted_at IS NULL",
+            [phone],
+            |row| row.get(0),
+        )
+        .unwrap_or(0)
+    };
+    count > 0
+}
+
+// ==================== Staff Operations ====================
+pub fn get_staff_list() -> Vec<Staff> {
+    let conn = get_db().lock();
+    let mut stmt = conn
+        .prepare("SELECT id, name, created_at, updated_at FROM staff WHERE deleted_at IS NULL ORDER BY created_at ASC")
+        .unwrap();
+
+    let iter = stmt
+        .query_map([], |row| {
+            Ok(Staff {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                created_at: row.get::<_, String>(2)?.parse().unwrap_or_default(),
+                updated_at: row.get::<_, String>(3)?.parse().unwrap_or_default(),
+            })
+        })
+        .unwrap();
+
+    iter.filter_map(|r| r.ok()).collect()
+}
+
+pub fn get_staff_by_id(id: &str) -> Option<Staff> {
+    let conn = get_db().lock();
+    conn.query_row(
+        "SELECT id, name, created_at, updated_at FROM staff WHERE id = ?1 AND deleted_at IS NULL",
+        [id],
+        |row| {
+            Ok(Staff {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                created_at: row.get::<_, String>(2)?.parse().unwrap_or_default(),
+                updated_at: row.get::<_, String>(3)?.parse().unwrap_or_default(),
+            })
+        },
+    )
+    .ok()
+}
+
+pub fn create_staff(name: &str) -> Result<Staff> {
+    let conn = get_db().lock();
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now();
+    let now_str = now.to_rfc3339();
+
+    conn.execute(
+        "INSERT INTO staff (id, name, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
+        [&id, name, &now_str, &now_str],
+    )?;
+
+    Ok(Staff {
+        id,
+        name: name.to_string(),
+        created_at: now,
+        updated_at: now,
+    })
+}
+
+pub fn update_staff(id: &str, name: &str) -> Result<Staff> {
+    {
+        let conn = get_db().lock();
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE staff SET name = ?1, updated_at = ?2 WHERE id = ?3 AND deleted_at IS NULL",
+            [name, &now, id],
+        )?;
+    }
+    get_staff_by_id(id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
+
+pub fn delete_staff(id: &str) -> Result<()> {
+    let conn = get_db().lock();
+    let now = Utc::now().to_rfc3339();
+    conn.execute("UPDATE staff SET deleted_at = ?1 WHERE id = ?2", [&now, id])?;
+    Ok(())
+}
+
+// ==================== Seat Operations ====================
+pub fn get_seats() -> Vec<Seat> {
+    let seat_ids: Vec<(i32, String, String, String, String)> = {
+        let conn = get_db().lock();
+        let mut stmt = conn
+            .prepare("SELECT id, name, status, created_at, updated_at FROM seats WHERE deleted_at IS NULL ORDER BY id ASC")
+            .unwrap();
+
+        stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, i32>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
+    };
+
+    // Load current sessions for each seat
+    seat_ids
+        .into_iter()
+        .map(|(id, name, status, created_at, updated_at)| Seat {
+            id,
+            name,
+            status: SeatStatus::from_str(&status),
+            current_session: get_session_by_seat_id(id),
+            created_at: created_at.parse().unwrap_or_default(),
+            updated_at: updated_at.parse().unwrap_or_default(),
+        })
+        .collect()
+}
+
+pub fn get_seat_by_id(id: i32) -> Option<Seat> {
+    let seat_data: Option<(i32, String, String, String, String)> = {
+        let conn = get_db().lock();
+        conn.query_row(
+            "SELECT id, name, status, created_at, updated_at FROM seats WHERE id = ?1 AND deleted_at IS NULL",
+            [id],
+            |row| {
+                Ok((
+                    row.get::<_, i32>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                ))
+            },
+        ).ok()
+    };
+
+    seat_data.map(|(id, name, status, created_at, updated_at)| Seat {
+        id,
+        name,
+        status: SeatStatus::from_str(&status),
+        current_session: get_session_by_seat_id(id),
+        created_at: created_at.parse().unwrap_or_default(),
+        updated_at: updated_at.parse().unwrap_or_default(),
+    })
+}
+
+pub fn update_seat_status(id: i32, status: SeatStatus) -> Result<()> {
+    let conn = get_db().lock();
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE seats SET status = ?1, updated_at = ?2 WHERE id = ?3",
+        [status.as_str(), &now, &id.to_string()],
+    )?;
+    Ok(())
+}
+
+// ==================== Service Session Operations ====================
+// Outer None when the session does not exist (completed or cancelled)
+pub fn get_session_member_id(session_id: &str) -> Option<Option<String>> {
+    let conn = get_db().lock();
+    conn.query_row(
+        "SELECT member_id FROM service_sessions WHERE id = ?1",
+        [session_id],
+        |row| row.get(0),
+    )
+    .ok()
+}
+
+pub fn get_session_by_seat_id(seat_id: i32) -> Option<ServiceSession> {
+    let session_data: Option<(
+        String,
+        i32,
+        Option<String>,
+        String,
+        i32,
+        String,
+        String,
+        String,
+        Option<String>,
+        String,
+        String,
+    )> = {
+        let conn = get_db().lock();
+        conn.query_row(
+            "SELECT id, seat_id, member_id, member_name, total_price, staff_id, staff_name, start_time, reservation_id, created_at, updated_at
+             FROM service_sessions WHERE seat_id = ?1",
+            [seat_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i32>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, i32>(4)?,
+                    row.get::<_, String>(5)?,
+                    row.get::<_, String>(6)?,
+                    row.get::<_, String>(7)?,
+                    row.get::<_, Option<String>>(8)?,
+                    row.get::<_, String>(9)?,
+                    row.get::<_, String>(10)?,
+                ))
+            },
+        ).ok()
+    };
+
+    session_data.map(
+        |(
+            id,
+            seat_id,
+            member_id,
+            member_name,
+            total_price,
+            staff_id,
+            staff_name,
+            start_time,
+            reservation_id,
+            created_at,
+            updated_at,
+        )| {
+            let services = get_services_by_session_id(&id);
+            ServiceSession {
+                id,
+                seat_id,
+                member_id,
+                member_name,
+                services,
+                total_price,
+                staff_id,
+                staff_name,
+                start_time: start_time.parse().unwrap_or_default(),
+                reservation_id,
+                created_at: created_at.parse().unwrap_or_default(),
+                updated_at: updated_at.parse().unwrap_or_default(),
+            }
+        },
+    )
+}
+
+pub fn create_session(
+    seat_id: i32,
+    member_id: Option<&str>,
+    member_name: &str,
+    staff_id: &str,
+    staff_name: &str,
+    services: &[ServiceInput],
+    total_price: i32,
+    reservation_id: Option<&str>,
+) -> Result<ServiceSession> {
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now();
+    let now_str = now.to_rfc3339();
+
+    {
+        let conn = get_db().lock();
+        conn.execute(
+            "INSERT INTO service_sessions (id, seat_id, member_id, member_name, total_price, staff_id, staff_name, start_time, reservation_id, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            rusqlite::params![id, seat_id, member_id, member_name, total_price, staff_id, staff_name, now_str, reservation_id, now_str, now_str],
+        )?;
+
+        // Insert services
+        for service in services {
+            conn.execute(
+                "INSERT INTO selected_services (service_session_id, name, length, price, category) VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![id, service.name, service.length, service.price, service.category],
+            )?;
+            insert_service_staff(&conn, conn.last_insert_rowid(), &service.staff)?;
+        }
+    }
+
+    Ok(ServiceSession {
+        id: id.clone(),
+        seat_id,
+        member_id: member_id.map(|s| s.to_string()),
+        member_name: member_name.to_string(),
+        services: get_services_by_session_id(&id),
+        total_price,
+        staff_id: staff_id.to_string(),
+        staff_name: staff_name.to_string(),
+        start_time: now,
+        reservation_id: reservation_id.map(|s| s.to_string()),
+        created_at: now,
+        updated_at: now,
+    })
+}
+
+pub fn delete_session(seat_id: i32) -> Result<Option<ServiceSession>> {
+    let session = get_session_by_seat_id(seat_id);
+
+    if let Some(ref s) = session {
+        let conn = get_db().lock();
+        remove_session(&conn, &s.id)?;
+    }
+
+    Ok(session)
+}
+
+// Returns how many sessions were removed: 0 when someone else already
+// completed or cancelled it
+fn remove_session(conn: &Connection, session_id: &str) -> Result<usize> {
+    // Delete services
+    conn.execute(
+        "DELETE FROM service_staff WHERE selected_service_id IN (SELECT id FROM selected_services WHERE service_session_id = ?1)",
+        [session_id],
+    )?;
+    conn.execute(
+        "DELETE FROM selected_services WHERE service_session_id = ?1",
+        [session_id],
+    )?;
+    // Delete session
+    conn.execute("DELETE FROM service_sessions WHERE id = ?1", [session_id])
+}
+
+pub fn get_services_by_session_id(session_id: &str) -> Vec<SelectedService> {
+    let conn = get_db().lock();
+    let mut stmt = conn
+        .prepare("SELECT id, service_session_id, reservation_id, ledger_entry_id, name, length, price, category FROM selected_services WHERE service_session_id = ?1")
+        .unwrap();
+
+    let mut services: Vec<SelectedService> = stmt
+        .query_map([session_id], |row| {
+            Ok(SelectedService {
+                id: row.get(0)?,
+                service_session_id: row.get(1)?,
+                reservation_id: row.get(2)?,
+                ledger_entry_id: row.get(3)?,
+                name: row.get(4)?,
+                length: row.get(5)?,
+                price: row.get(6)?,
+                category: row.get(7)?,
+                staff: vec![],
+                member_package_id: None,
+            })
+        })
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect();
+
+    load_service_staff(&conn, &mut services);
+    services
+}
+
+fn insert_service_staff(conn: &Connection, service_id: i64, staff: &[StaffShare]) -> Result<()> {
+    for share in staff {
+        conn.execute(
+            "INSERT INTO service_staff (selected_service_id, staff_id, staff_name, share_percent) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![service_id, share.staff_id, share.staff_name, share.share_percent],
+        )?;
+    }
+    Ok(())
+}
+
+fn load_service_staff(conn: &Connection, services: &mut [SelectedService]) {
+    let mut stmt = conn
+        .prepare("SELECT staff_id, staff_name, share_percent FROM service_staff WHERE selected_service_id = ?1 ORDER BY id ASC")
+        .unwrap();
+
+    for service in services.iter_mut() {
+        service.staff = stmt
+            .query_map([service.id], |row| {
+                Ok(StaffShare {
+                    staff_id: row.get(0)?,
+                    staff_name: row.get(1)?,
+                    share_percent: row.get(2)?,
+                })
+            })
+            .unwrap()
+            .filter_map(|r| r.ok())
+            .collect();
+    }
+}
+
+// ==================== Reservation Operations ====================
+pub fn get_reservations(query: &ReservationQuery) -> Vec<Reservation> {
+    let reservation_ids: Vec<String> = {
+        let conn = get_db().lock();
+
+        let mut sql = String::from("SELECT id FROM reservations WHERE deleted_at IS NULL");
+
+        let mut params: Vec<String> = vec![];
+
+        if let Some(status) = &query.status {
+            sql.push_str(" AND status = ?");
+            params.push(status.clone());
+        }
+
+        if let Some(date) = &query.date {
+            sql.push_str(" AND DATE(reserved_at) = ?");
+            params.push(date.clone());
+        } else if query.all.as_deref() != Some("true") {
+            sql.push_str(" AND DATE(reserved_at) >= DATE('now')");
+        }
+
+        sql.push_str(" ORDER BY reserved_at ASC");
+
+        let mut stmt = conn.prepare(&sql).unwrap();
+
+        match params.len() {
+            0 => stmt
+                .query_map([], |row| row.get::<_, String>(0))
+                .unwrap()
+                .filter_map(|r| r.ok())
+                .collect(),
+            1 => stmt
+                .query_map([&params[0]], |row| row.get::<_, String>(0))
+                .unwrap()
+                .filter_map(|r| r.ok())
+                .collect(),
+            2 => stmt
+                .query_map([&params[0], &params[1]], |row| row.get::<_, String>(0))
+                .unwrap()
+                .filter_map(|r| r.ok())
+                .collect(),
+            _ => vec![],
+        }
+    };
+
+    // Load full reservations with services
+    reservation_ids
+        .into_iter()
+        .filter_map(|id| get_reservation_by_id(&id))
+        .collect()
+}
+
+fn map_reservation(row: &rusqlite::Row) -> rusqlite::Result<Reservation> {
+    Ok(Reservation {
+        id: row.get(0)?,
+        member_id: row.get(1)?,
+        member_name: row.get(2)?,
+        member_phone: row.get(3)?,
+        seat_id: row.get(4)?,
+        staff_id: row.get(5)?,
+        staff_name: row.get(6)?,
+        services: vec![],
+        total_price: row.get(7)?,
+        reserved_at: row.get::<_, String>(8)?.parse().unwrap_or_default(),
+        estimated_duration: row.get(9)?,
+        status: ReservationStatus::from_str(&row.get::<_, String>(10)?),
+        deposit_amount: row.get(11)?,
+        deposit_method: row
+            .get::<_, Option<String>>(12)?
+            .map(|m| PaymentMethod::from_str(&m)),
+        deposit_status: DepositStatus::from_str(&row.get::<_, String>(13)?),
+        deposit_paid_at: row
+            .get::<_, Option<String>>(14)?
+            .and_then(|t| t.parse().ok()),
+        created_at: row.get::<_, String>(15)?.parse().unwrap_or_default(),
+        updated_at: row.get::<_, String>(16)?.parse().unwrap_or_default(),
+    })
+}
+
+pub fn get_reservation_by_id(id: &str) -> Option<Reservation> {
+    let reservation_data: Option<Reservation> = {
+        let conn = get_db().lock();
+        conn.query_row(
+            "SELECT id, member_id, member_name, member_phone, seat_id, staff_id, staff_name, total_price, reserved_at, estimated_duration, status,
+                    deposit_amount, deposit_method, deposit_status, deposit_paid_at, created_at, updated_at
+             FROM reservations WHERE id = ?1 AND deleted_at IS NULL",
+            [id],
+            map_reservation,
+        ).ok()
+    };
+
+    reservation_data.map(|mut r| {
+        r.services = get_services_by_reservation_id(&r.id);
+        r
+    })
+}
+
+pub fn get_services_by_reservation_id(reservation_id: &str) -> Vec<SelectedService> {
+    let conn = get_db().lock();
+    let mut stmt = conn
+        .prepare("SELECT id, service_session_id, reservation_id, ledger_entry_id, name, length, price, category FROM selected_services WHERE reservation_id = ?1")
+        .unwrap();
+
+    let mut services: Vec<SelectedService> = stmt
+        .query_map([reservation_id], |row| {
+            Ok(SelectedService {
+                id: row.get(0)?,
+                service_session_id: row.get(1)?,
+                reservation_id: row.get(2)?,
+                ledger_entry_id: row.get(3)?,
+                name: row.get(4)?,
+                length: row.get(5)?,
+                price: row.get(6)?,
+                category: row.get(7)?,
+                staff: vec![],
+                member_package_id: None,
+            })
+        })
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect();
+
+    load_service_staff(&conn, &mut services);
+    services
+}
+
+pub fn create_reservation(req: &CreateReservationRequest) -> Result<Reservation> {
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now();
+    let now_str = now.to_rfc3339();
+
+    // A deposit with a method is already paid; otherwise it is awaiting payment
+    let deposit_status = match (req.deposit_amount > 0, req.deposit_method) {
+        (false, _) => DepositStatus::None,
+        (true, Some(_)) => DepositStatus::Paid,
+        (true, None) => DepositStatus::Pending,
+    };
+    let deposit_paid_at = (deposit_status == DepositStatus::Paid).then(|| now_str.clone());
+
+    {
+        let conn = get_db().lock();
+        conn.execute(
+            "INSERT INTO reservations (id, member_id, member_name, member_phone, seat_id, staff_id, staff_name, total_price, reserved_at, estimated_duration, status,
+                                       deposit_amount, deposit_method, deposit_status, deposit_paid_at, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+            rusqlite::params![
+                id,
+                req.member_id,
+                req.member_name,
+                req.member_phone,
+                req.seat_id,
+                req.staff_id,
+                req.staff_name,
+                req.total_price,
+                req.reserved_at.to_rfc3339(),
+                req.estimated_duration,
+                "scheduled",
+                req.deposit_amount,
+                req.deposit_method.map(|m| m.as_str()),
+                deposit_status.as_str(),
+                deposit_paid_at,
+                now_str,
+                now_str
+            ],
+        )?;
+
+        // Insert services
+        for service in &req.services {
+            conn.execute(
+                "INSERT INTO selected_services (reservation_id, name, length, price, category) VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![id, service.name, service.length, service.price, service.category],
+            )?;
+            insert_service_staff(&conn, conn.last_insert_rowid(), &service.staff)?;
+        }
+    }
+
+    get_reservation_by_id(&id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
+
+pub fn update_reservation(id: &str, req: &UpdateReservationRequest) -> Result<Reservation> {
+    {
+        let conn = get_db().lock();
+        let now = Utc::now().to_rfc3339();
+
+        conn.execute(
+            "UPDATE reservations SET member_id = ?1, member_name = ?2, member_phone = ?3, seat_id = ?4, staff_id = ?5, staff_name = ?6, total_price = ?7, reserved_at = ?8, estimated_duration = ?9, updated_at = ?10 WHERE id = ?11 AND deleted_at IS NULL",
+            rusqlite::params![
+                req.member_id,
+                req.member_name,
+                req.member_phone,
+                req.seat_id,
+                req.staff_id,
+                req.staff_name,
+                req.total_price,
+                req.reserved_at.to_rfc3339(),
+                req.estimated_duration,
+                now,
+                id
+            ],
+        )?;
+
+        // Delete old services and insert new ones
+        conn.execute(
+            "DELETE FROM service_staff WHERE selected_service_id IN (SELECT id FROM selected_services WHERE reservation_id = ?1)",
+            [id],
+        )?;
+        conn.execute(
+            "DELETE FROM selected_services WHERE reservation_id = ?1",
+            [id],
+        )?;
+
+        for service in &req.services {
+            conn.execute(
+                "INSERT INTO selected_services (reservation_id, name, length, price, category) VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![id, service.name, service.length, service.price, service.category],
+            )?;
+            insert_service_staff(&conn, conn.last_insert_rowid(), &service.staff)?;
+        }
+    }
+
+    get_reservation_by_id(id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
+
+pub fn update_reservation_status(id: &str, status: ReservationStatus) -> Result<Reservation> {
+    {
+        let conn = get_db().lock();
+        let now = Utc::now().to_rfc3339();
+
+        conn.execute(
+            "UPDATE reservations SET status = ?1, updated_at = ?2 WHERE id = ?3 AND deleted_at IS NULL",
+            [status.as_str(), &now, id],
+        )?;
+    }
+
+    get_reservation_by_id(id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
+
+pub fn record_deposit(id: &str, amount: i32, method: PaymentMethod) -> WriteResult<Reservation> {
+    {
+        let conn = get_db().lock();
+        let now = Utc::now().to_rfc3339();
+
+        let updated = conn.execute(
+            "UPDATE reservations SET deposit_amount = ?1, deposit_method = ?2, deposit_status = ?3, deposit_paid_at = ?4, updated_at = ?4
+             WHERE id = ?5 AND deleted_at IS NULL AND status = ?6 AND deposit_status IN (?7, ?8)",
+            rusqlite::params![
+                amount,
+                method.as_str(),
+                DepositStatus::Paid.as_str(),
+                now,
+                id,
+                ReservationStatus::Scheduled.as_str(),
+                DepositStatus::None.as_str(),
+                DepositStatus::Pending.as_str()
+            ],
+        )?;
+        if updated == 0 {
+            return Err(conflict("이미 예약금이 처리된 예약입니다"));
+        }
+    }
+
+    get_reservation_by_id(id).ok_or(rusqlite::Error::QueryReturnedNoRows.into())
+}
+
+// Moves a cancelled or missed booking to its new status together with what
+// happens to a paid deposit: `forfeit` of it is booked as income and the
+// deposit marked forfeited, or it is marked refunded when nothing is kept.
+pub fn settle_reservation(
+    reservation: &Reservation,
+    status: ReservationStatus,
+    forfeit: i32,
+) -> WriteResult<Reservation> {
+    {
+        let mut conn = get_db().lock();
+        let tx = conn.transaction()?;
+        let now = Utc::now().to_rfc3339();
+
+        tx.execute(
+            "UPDATE reservations SET status = ?1, updated_at = ?2 WHERE id = ?3 AND deleted_at IS NULL",
+            [status.as_str(), &now, &reservation.id],
+        )?;
+
+        if reservation.deposit_status == DepositStatus::Paid
+            && matches!(
+                status,
+                ReservationStatus::NoShow | ReservationStatus::Cancelled
+            )
+        {
+            let deposit_status = if forfeit > 0 {
+                insert_forfeit_ledger_entry(&tx, reservation, forfeit, &now)?;
+                DepositStatus::Forfeited
+            } else {
+                DepositStatus::Refunded
+            };
+            set_paid_deposit_status(&tx, &reservation.id, deposit_status, &now)?;
+        }
+        tx.commit()?;
+    }
+
+    get_reservation_by_id(&reservation.id).ok_or(rusqlite::Error::QueryReturnedNoRows.into())
+}
+
+pub fn refund_deposit(id: &str) -> WriteResult<Reservation> {
+    {
+        let conn = get_db().lock();
+        let now = Utc::now().to_rfc3339();
+        set_paid_deposit_status(&conn, id, DepositStatus::Refunded, &now)?;
+    }
+
+    get_reservation_by_id(id).ok_or(rusqlite::Error::QueryReturnedNoRows.into())
+}
+
+// Fails when another caller settled the deposit first
+fn set_paid_deposit_status(
+    conn: &Connection,
+    id: &str,
+    status: DepositStatus,
+    now: &str,
+) -> WriteResult<()> {
+    let updated = conn.execute(
+        "UPDATE reservations SET deposit_status = ?1, updated_at = ?2 WHERE id = ?3 AND deleted_at IS NULL AND deposit_status = ?4",
+        [status.as_str(), now, id, DepositStatus::Paid.as_str()],
+    )?;
+    if updated == 0 {
+        return Err(conflict("예약금이 이미 처리되었습니다"));
+    }
+    Ok(())
+}
+
+pub fn set_reservation_external_uid(id: &str, uid: &str) -> Result<()> {
+    let conn = get_db().lock();
+    conn.execute(
+        "UPDATE reservations SET external_uid = ?1 WHERE id = ?2",
+        [uid, id],
+    )?;
+    Ok(())
+}
+
+pub fn external_uid_exists(uid: &str) -> bool {
+    let conn = get_db().lock();
+    let count: i32 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM reservations WHERE external_uid = ?1 AND deleted_at IS NULL",
+            [uid],
+            |row| row.get(0),
+        )
+        .unwrap_or(0);
+    count > 0
+}
+
+pub fn get_deposit_policy() -> DepositPolicy {
+    get_setting("deposit_policy")
+}
+
+pub fn save_deposit_policy(policy: &DepositPolicy) -> Result<DepositPolicy> {
+    save_setting("deposit_policy", policy)?;
+    Ok(get_deposit_policy())
+}
+
+pub fn delete_reservation(id: &str) -> Result<()> {
+    let conn = get_db().lock();
+    let now = Utc::now().to_rfc3339();
+
+    // Delete services
+    conn.execute(
+        "DELETE FROM service_staff WHERE selected_service_id IN (SELECT id FROM selected_services WHERE reservation_id = ?1)",
+        [id],
+    )?;
+    conn.execute(
+        "DELETE FROM selected_services WHERE reservation_id = ?1",
+        [id],
+    )?;
+
+    // Soft delete reservation
+    conn.execute(
+        "UPDATE reservations SET deleted_at = ?1 WHERE id = ?2",
+        [&now, id],
+    )?;
+
+    Ok(())
+}
+
+// ==================== Discount Operations ====================
+const DISCOUNT_RULE_COLUMNS: &str = "id, name, kind, value, service_name, member_only, student_only, starts_at, ends_at, active, created_at, updated_at";
+
+fn map_discount_rule(row: &rusqlite::Row) -> rusqlite::Result<DiscountRule> {
+    Ok(DiscountRule {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        kind: DiscountKind::from_str(&row.get::<_, String>(2)?),
+        value: row.get(3)?,
+        service_name: row.get(4)?,
+        member_only: row.get(5)?,
+        student_only: row.get(6)?,
+        starts_at: row
+            .get::<_, Option<String>>(7)?
+            .and_then(|t| t.parse().ok()),
+        ends_at: row
+            .get::<_, Option<String>>(8)?
+            .and_then(|t| t.parse().ok()),
+        active: row.get(9)?,
+        created_at: row.get::<_, String>(10)?.parse().unwrap_or_default(),
+        updated_at: row.get::<_, String>(11)?.parse().unwrap_or_default(),
+    })
+}
+
+pub fn get_discount_rules() -> Vec<DiscountRule> {
+    let conn = get_db().lock();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM discount_rules WHERE deleted_at IS NULL ORDER BY created_at ASC",
+            DISCOUNT_RULE_COLUMNS
+        ))
+        .unwrap();
+
+    let iter = stmt.query_map([], map_discount_rule).unwrap();
+    iter.filter_map(|r| r.ok()).collect()
+}
+
+pub fn get_discount_rule_by_id(id: &str) -> Option<DiscountRule> {
+    let conn = get_db().lock();
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM discount_rules WHERE id = ?1 AND deleted_at IS NULL",
+            DISCOUNT_RULE_COLUMNS
+        ),
+        [id],
+        map_discount_rule,
+    )
+    .ok()
+}
+
+pub fn create_discount_rule(request: &DiscountRuleRequest) -> Result<DiscountRule> {
+    let id = Uuid::new_v4().to_string();
+    let now_str = Utc::now().to_rfc3339();
+
+    {
+        let conn = get_db().lock();
+        conn.execute(
+            "INSERT INTO discount_rules (id, name, kind, value, service_name, member_only, student_only, starts_at, ends_at, active, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?11)",
+            rusqlite::params![
+                id,
+                request.name,
+                request.kind.as_str(),
+                request.value,
+                request.service_name,
+                request.member_only,
+                request.student_only,
+                request.starts_at.map(|t| t.to_rfc3339()),
+                request.ends_at.map(|t| t.to_rfc3339()),
+                request.active,
+                now_str
+            ],
+        )?;
+    }
+
+    get_discount_rule_by_id(&id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
+
+pub fn update_discount_rule(id: &str, request: &DiscountRuleRequest) -> Result<DiscountRule> {
+    {
+        let conn = get_db().lock();
+        conn.execute(
+            "UPDATE discount_rules SET name = ?1, kind = ?2, value = ?3, service_name = ?4, member_only = ?5, student_only = ?6,
+                    starts_at = ?7, ends_at = ?8, active = ?9, updated_at = ?10
+             WHERE id = ?11 AND deleted_at IS NULL",
+            rusqlite::params![
+                request.name,
+                request.kind.as_str(),
+                request.value,
+                request.service_name,
+                request.member_only,
+                request.student_only,
+                request.starts_at.map(|t| t.to_rfc3339()),
+                request.ends_at.map(|t| t.to_rfc3339()),
+                request.active,
+                Utc::now().to_rfc3339(),
+                id
+            ],
+        )?;
+    }
+    get_discount_rule_by_id(id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
+
+pub fn delete_discount_rule(id: &str) -> Result<()> {
+    let conn = get_db().lock();
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE discount_rules SET deleted_at = ?1 WHERE id = ?2",
+        [&now, id],
+    )?;
+    Ok(())
+}
+
+const COUPON_COLUMNS: &str =
+    "id, code, name, kind, value, max_uses, used_count, expires_at, active, created_at, updated_at";
+
+fn map_coupon(row: &rusqlite::Row) -> rusqlite::Result<Coupon> {
+    Ok(Coupon {
+        id: row.get(0)?,
+        code: row.get(1)?,
+        name: row.get(2)?,
+        kind: DiscountKind::from_str(&row.get::<_, String>(3)?),
+        value: row.get(4)?,
+        max_uses: row.get(5)?,
+        used_count: row.get(6)?,
+        expires_at: row
+            .get::<_, Option<String>>(7)?
+            .and_then(|t| t.parse().ok()),
+        active: row.get(8)?,
+        created_at: row.get::<_, String>(9)?.parse().unwrap_or_default(),
+        updated_at: row.get::<_, String>(10)?.parse().unwrap_or_default(),
+    })
+}
+
+pub fn get_coupons() -> Vec<Coupon> {
+    let conn = get_db().lock();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM coupons WHERE deleted_at IS NULL ORDER BY created_at DESC",
+            COUPON_COLUMNS
+        ))
+        .unwrap();
+
+    let iter = stmt.query_map([], map_coupon).unwrap();
+    iter.filter_map(|r| r.ok()).collect()
+}
+
+pub fn get_coupon_by_id(id: &str) -> Option<Coupon> {
+    let conn = get_db().lock();
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM coupons WHERE id = ?1 AND deleted_at IS NULL",
+            COUPON_COLUMNS
+        ),
+        [id],
+        map_coupon,
+    )
+    .ok()
+}
+
+// Codes are matched case-insensitively
+pub fn get_coupon_by_code(code: &str) -> Option<Coupon> {
+    let conn = get_db().lock();
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM coupons WHERE code = ?1 COLLATE NOCASE AND deleted_at IS NULL",
+            COUPON_COLUMNS
+        ),
+        [code.trim()],
+        map_coupon,
+    )
+    .ok()
+}
+
+pub fn coupon_code_exists(code: &str, exclude_id: Option<&str>) -> bool {
+    get_coupon_by_code(code).is_some_and(|c| Some(c.id.as_str()) != exclude_id)
+}
+
+pub fn create_coupon(request: &CouponRequest) -> Result<Coupon> {
+    let id = Uuid::new_v4().to_string();
+    let now_str = Utc::now().to_rfc3339();
+
+    {
+        let conn = get_db().lock();
+        conn.execute(
+            "INSERT INTO coupons (id, code, name, kind, value, max_uses, used_count, expires_at, active, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 0, ?7, ?8, ?9, ?9)",
+            rusqlite::params![
+                id,
+                request.code.trim(),
+                request.name,
+                request.kind.as_str(),
+                request.value,
+                request.max_uses,
+                request.expires_at.map(|t| t.to_rfc3339()),
+                request.active,
+                now_str
+            ],
+        )?;
+    }
+
+    get_coupon_by_id(&id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
+
+pub fn update_coupon(id: &str, request: &CouponRequest) -> Result<Coupon> {
+    {
+        let conn = get_db().lock();
+        conn.execute(
+            "UPDATE coupons SET code = ?1, name = ?2, kind = ?3, value = ?4, max_uses = ?5, expires_at = ?6, active = ?7, updated_at = ?8
+             WHERE id = ?9 AND deleted_at IS NULL",
+            rusqlite::params![
+                request.code.trim(),
+                request.name,
+                request.kind.as_str(),
+                request.value,
+                request.max_uses,
+                request.expires_at.map(|t| t.to_rfc3339()),
+                request.active,
+                Utc::now().to_rfc3339(),
+                id
+            ],
+        )?;
+    }
+    get_coupon_by_id(id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
+
+pub fn delete_coupon(id: &str) -> Result<()> {
+    let conn = get_db().lock();
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE coupons SET deleted_at = ?1 WHERE id = ?2",
+        [&now, id],
+    )?;
+    Ok(())
+}
+
+// ==================== Ledger Operations ====================
+pub fn get_ledger_entries(query: &LedgerQuery) -> Vec<LedgerEntry> {
+    let entry_ids: Vec<String> = {
+        let conn = get_db().lock();
+
+        let mut sql = String::from("SELECT id FROM ledger_entries WHERE 1=1");
+
+        let mut params: Vec<String> = vec![];
+
+        if let Some(date) = &query.date {
+            sql.push_str(" AND DATE(completed_at, 'localtime') = ?");
+            params.push(date.clone());
+        } else if query.start_date.is_some() && query.end_date.is_some() {
+            sql.push_str(
+                " AND DATE(completed_at, 'localtime') >= ? AND DATE(completed_at, 'localtime') <= ?",
+            );
+            params.push(query.start_date.clone().unwrap());
+            params.push(query.end_date.clone().unwrap());
+        }
+
+        // A designer also sees entries where they hold a share of a service
+        if let Some(staff_id) = &query.staff_id {
+            sql.push_str(
+                " AND (staff_id = ? OR id IN (SELECT s.ledger_entry_id FROM selected_services s JOIN service_staff st ON st.selected_service_id = s.id WHERE st.staff_id = ?)
+                    OR refund_of IN (SELECT s.ledger_entry_id FROM selected_services s JOIN service_staff st ON st.selected_service_id = s.id WHERE st.staff_id = ?))",
+            );
+            params.extend(std::iter::repeat_n(staff_id.clone(), 3));
+        }
+
+        sql.push_str(" ORDER BY completed_at DESC");
+
+        let mut stmt = conn.prepare(&sql).unwrap();
+
+        stmt.query_map(rusqlite::params_from_iter(params.iter()), |row| {
+            row.get::<_, String>(0)
+        })
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
+    };
+
+    // Load full entries with services
+    entry_ids
+        .into_iter()
+        .filter_map(|id| get_ledger_entry_by_id(&id))
+        .collect()
+}
+
+pub fn get_ledger_entry_by_id(id: &str) -> Option<LedgerEntry> {
+    let conn = get_db().lock();
+    load_ledger_entry(&conn, id)
+}
+
+// Reads an entry through `conn` so it also works inside a write transaction
+fn load_ledger_entry(conn: &Connection, id: &str) -> Option<LedgerEntry> {
+    let entry_data: Option<LedgerEntry> = {
+        conn.query_row(
+            "SELECT id, entry_type, reservation_id, member_id, member_name, seat_id, staff_id, staff_name, total_price, deposit_applied, completed_at, created_at,
+                    COALESCE(gross_amount, total_price), discount_amount, refund_of, refund_reason, approved_by,
+                    (SELECT COALESCE(-SUM(r.total_price), 0) FROM ledger_entries r WHERE r.refund_of = ledger_entries.id),
+                    corrected_at,
+                    (SELECT COALESCE(SUM(t.amount), 0) FROM tips t WHERE t.ledger_entry_id = ledger_entries.id)
+             FROM ledger_entries WHERE id = ?1",
+            [id],
+            |row| {
+                Ok(LedgerEntry {
+                    id: row.get(0)?,
+                    entry_type: LedgerEntryType::from_str(&row.get::<_, String>(1)?),
+                    reservation_id: row.get(2)?,
+                    member_id: row.get(3)?,
+                    member_name: row.get(4)?,
+                    seat_id: row.get(5)?,
+                    staff_id: row.get(6)?,
+                    staff_name: row.get(7)?,
+                    services: vec![],
+                    gross_amount: row.get(12)?,
+                    discount_amount: row.get(13)?,
+                    discounts: vec![],
+                    total_price: row.get(8)?,
+                    deposit_applied: row.get(9)?,
+                    payments: vec![],
+                    refund_of: row.get(14)?,
+                    refund_reason: row.get(15)?,
+                    approved_by: row.get(16)?,
+                    refunded_amount: row.get(17)?,
+                    corrected_at: row
+                        .get::<_, Option<String>>(18)?
+                        .and_then(|t| t.parse().ok()),
+                    tip_amount: row.get(19)?,
+                    completed_at: row.get::<_, String>(10)?.parse().unwrap_or_default(),
+                    created_at: row.get::<_, String>(11)?.parse().unwrap_or_default(),
+                })
+            },
+        ).ok()
+    };
+
+    entry_data.map(|mut e| {
+        e.services = load_ledger_services(conn, &e.id);
+        e.payments = load_payments(conn, &e.id);
+        e.discounts = load_discounts(conn, &e.id);
+        e
+    })
+}
+
+fn load_discounts(conn: &Connection, ledger_id: &str) -> Vec<LedgerDiscount> {
+    let mut stmt = conn
+        .prepare("SELECT id, ledger_entry_id, source, reference_id, name, amount, kind, value, service_name, single_line FROM ledger_discounts WHERE ledger_entry_id = ?1")
+        .unwrap();
+
+    stmt.query_map([ledger_id], |row| {
+        let terms = match row.get::<_, Option<String>>(6)? {
+            Some(kind) => Some(DiscountTerms {
+                kind: DiscountKind::from_str(&kind),
+                value: row.get(7)?,
+                service_name: row.get(8)?,
+                single_line: row.get::<_, Option<bool>>(9)?.unwrap_or(false),
+            }),
+            None => None,
+        };
+        Ok(LedgerDiscount {
+            id: row.get(0)?,
+            ledger_entry_id: row.get(1)?,
+            source: DiscountSource::from_str(&row.get::<_, String>(2)?),
+            reference_id: row.get(3)?,
+            name: row.get(4)?,
+            amount: row.get(5)?,
+            terms,
+        })
+    })
+    .unwrap()
+    .filter_map(|r| r.ok())
+    .collect()
+}
+
+fn load_payments(conn: &Connection, ledger_id: &str) -> Vec<Payment> {
+    let mut stmt = conn
+        .prepare("SELECT id, ledger_entry_id, method, amount, approval_number, paid_at FROM payments WHERE ledger_entry_id = ?1 ORDER BY paid_at ASC")
+        .unwrap();
+
+    stmt.query_map([ledger_id], |row| {
+        Ok(Payment {
+            id: row.get(0)?,
+            ledger_entry_id: row.get(1)?,
+            method: PaymentMethod::from_str(&row.get::<_, String>(2)?),
+            amount: row.get(3)?,
+            approval_number: row.get(4)?,
+            paid_at: row.get::<_, String>(5)?.parse().unwrap_or_default(),
+        })
+    })
+    .unwrap()
+    .filter_map(|r| r.ok())
+    .collect()
+}
+
+fn insert_payment(
+    conn: &Connection,
+    ledger_id: &str,
+    payment: &PaymentInput,
+    paid_at: &str,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO payments (id, ledger_entry_id, method, amount, approval_number, paid_at, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        rusqlite::params![
+            Uuid::new_v4().to_string(),
+            ledger_id,
+            payment.method.as_str(),
+            payment.amount,
+            payment.approval_number,
+            paid_at,
+            paid_at
+        ],
+    )?;
+    Ok(())
+}
+
+fn load_ledger_services(conn: &Connection, ledger_id: &str) -> Vec<SelectedService> {
+    let mut stmt = conn
+        .prepare("SELECT id, service_session_id, reservation_id, ledger_entry_id, name, length, price, category, member_package_id FROM selected_services WHERE ledger_entry_id = ?1")
+        .unwrap();
+
+    let mut services: Vec<SelectedService> = stmt
+        .query_map([ledger_id], |row| {
+            Ok(SelectedService {
+                id: row.get(0)?,
+                service_session_id: row.get(1)?,
+                reservation_id: row.get(2)?,
+                ledger_entry_id: row.get(3)?,
+                name: row.get(4)?,
+                length: row.get(5)?,
+                price: row.get(6)?,
+                category: row.get(7)?,
+                staff: vec![],
+                member_package_id: row.get(8)?,
+            })
+        })
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect();
+
+    load_service_staff(conn, &mut services);
+    services
+}
+
+fn insert_ledger_entry(
+    conn: &Connection,
+    session: &ServiceSession,
+    checkout: &Checkout,
+    now_str: &str,
+) -> WriteResult<String> {
+    let id = Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO ledger_entries (id, entry_type, reservation_id, member_id, member_name, seat_id, staff_id, staff_name, gross_amount, discount_amount, total_price, deposit_applied, completed_at, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        rusqlite::params![
+            id,
+            LedgerEntryType::Service.as_str(),
+            session.reservation_id,
+            session.member_id,
+            session.member_name,
+            session.seat_id,
+            session.staff_id,
+            session.staff_name,
+            checkout.gross_amount,
+            checkout.discount_amount,
+            checkout.net_amount,
+            checkout.deposit_applied,
+            now_str,
+            now_str
+        ],
+    )?;
+
+    // Copy services to ledger, marking the ones a package ticket paid for
+    for (i, service) in session.services.iter().enumerate() {
+        let member_package_id = checkout
+            .redemptions
+            .iter()
+            .find(|r| r.service_index == i)
+            .map(|r| r.member_package_id.as_str());
+        conn.execute(
+            "INSERT INTO selected_services (ledger_entry_id, name, length, price, category, member_package_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![id, service.name, service.length, service.price, service.category, member_package_id],
+        )?;
+        insert_service_staff(conn, conn.last_insert_rowid(), &service.staff)?;
+    }
+
+    for discount in &checkout.discounts {
+        conn.execute(
+            "INSERT INTO ledger_discounts (id, ledger_entry_id, source, reference_id, name, amount, kind, value, service_name, single_line)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            rusqlite::params![
+                Uuid::new_v4().to_string(),
+                id,
+                discount.source.as_str(),
+                discount.reference_id,
+                discount.name,
+                discount.amount,
+                discount.terms.as_ref().map(|t| t.kind.as_str()),
+                discount.terms.as_ref().map(|t| t.value),
+                discount.terms.as_ref().and_then(|t| t.service_name.as_deref()),
+                discount.terms.as_ref().is_some_and(|t| t.single_line)
+            ],
+        )?;
+
+        if let (DiscountSource::Coupon, Some(coupon_id)) = (discount.source, &discount.reference_id)
+        {
+            // validate_coupon ran before the lock was taken, so the limit is
+            // checked again here
+            let updated = conn.execute(
+                "UPDATE coupons SET used_count = used_count + 1, updated_at = ?1
+                 WHERE id = ?2 AND (max_uses IS NULL OR used_count < max_uses)",
+                [now_str, coupon_id],
+            )?;
+            if updated == 0 {
+                return Err(conflict("사용 횟수가 모두 소진된 쿠폰입니다"));
+            }
+        }
+    }
+
+    for payment in &checkout.payments {
+        insert_payment(conn, &id, payment, now_str)?;
+    }
+
+    Ok(id)
+}
+
+// Writes a completed sale, every balance it moves and the freed seat in one
+// transaction. The session is removed first, so a second checkout of the same
+// session finds nothing left to complete.
+pub fn complete_checkout(
+    session: &ServiceSession,
+    checkout: &Checkout,
+    postings: &CheckoutPostings,
+) -> WriteResult<CompletedCheckout> {
+    let mut conn = get_db().lock();
+    let tx = conn.transaction()?;
+    let now_str = Utc::now().to_rfc3339();
+
+    if remove_session(&tx, &session.id)? == 0 {
+        return Err(conflict("이미 완료되었거나 취소된 서비스입니다"));
+    }
+    tx.execute(
+        "UPDATE seats SET status = ?1, updated_at = ?2 WHERE id = ?3",
+        rusqlite::params![SeatStatus::Available.as_str(), now_str, session.seat_id],
+    )?;
+
+    let id = insert_ledger_entry(&tx, session, checkout, &now_str)?;
+    if let Some(tip) = &postings.tip {
+        insert_tip(&tx, &id, &session.staff_id, &session.staff_name, tip)?;
+    }
+    let ledger_entry = load_ledger_entry(&tx, &id).ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+
+    // Take a visit off each package ticket the checkout used
+    let mut member_package_ids = vec![];
+    for redemption in &checkout.redemptions {
+        take_package_visit(&tx, &redemption.member_package_id, &id)?;
+        member_package_ids.push(redemption.member_package_id.clone());
+    }
+
+    let mut completed = CompletedCheckout {
+        ledger_entry,
+        member_package_ids,
+        prepaid: None,
+        points: vec![],
+        stamps_earned: None,
+    };
+
+    if let Some(member_id) = &session.member_id {
+        let actor = Some(session.staff_name.clone());
+        let paid_with = |method: PaymentMethod| -> i32 {
+            checkout
+                .payments
+                .iter()
+                .filter(|p| p.method == method)
+                .map(|p| p.amount)
+                .sum()
+        };
+
+        let prepaid_used = paid_with(PaymentMethod::Prepaid);
+        if prepaid_used > 0 {
+            let movement = PrepaidMovement {
+                amount: -prepaid_used,
+                ledger_entry_id: Some(id.clone()),
+                ..Default::default()
+            };
+            completed.prepaid = Some(insert_prepaid_transaction(
+                &tx,
+                member_id,
+                PrepaidTransactionKind::Use,
+                &movement,
+            )?);
+        }
+
+        // Spend the points paid with, then add what the rest of the bill earns
+        let movement = PointMovement {
+            ledger_entry_id: Some(id.clone()),
+            actor: actor.clone(),
+            ..Default::default()
+        };
+        let points_used = paid_with(PaymentMethod::Points);
+        if points_used > 0 {
+            completed.points.push(insert_point_transaction(
+                &tx,
+                member_id,
+                PointTransactionKind::Redeem,
+                -points_used,
+                &movement,
+            )?);
+        }
+        if postings.points_earned > 0 {
+            completed.points.push(insert_point_transaction(
+                &tx,
+                member_id,
+                PointTransactionKind::Earn,
+                postings.points_earned,
+                &movement,
+            )?);
+        }
+
+        // Spend the redeemed stamps, then add what this visit earns
+        for program in &postings.redeemed_programs {
+            let movement = StampMovement {
+                ledger_entry_id: Some(id.clone()),
+                program_id: Some(program.id.clone()),
+                actor: actor.clone(),
+                reason: Some(program.name.clone()),
+            };
+            insert_stamp_transaction(
+                &tx,
+                member_id,
+                StampTransactionKind::Redeem,
+                -program.stamps_required,
+                &movement,
+            )?;
+        }
+        let program = postings.stamp_program.as_ref();
+        let earned = crate::stamps::earned_for(&completed.ledger_entry, program);
+        if earned > 0 {
+            let movement = StampMovement {
+                ledger_entry_id: Some(id.clone()),
+                program_id: program.map(|p| p.id.clone()),
+                actor,
+                reason: None,
+            };
+            completed.stamps_earned = Some(insert_stamp_transaction(
+                &tx,
+                member_id,
+                StampTransactionKind::Earn,
+                earned,
+                &movement,
+            )?);
+        }
+    }
+
+    if let Some(reservation_id) = &session.reservation_id {
+        if checkout.deposit_applied > 0 {
+            let applied = tx.execute(
+                "UPDATE reservations SET deposit_status = ?1, updated_at = ?2 WHERE id = ?3 AND deposit_status = ?4",
+                [
+                    DepositStatus::Applied.as_str(),
+                    &now_str,
+                    reservation_id,
+                    DepositStatus::Paid.as_str(),
+                ],
+            )?;
+            if applied == 0 {
+                return Err(conflict("예약금이 이미 처리되었습니다"));
+            }
+        }
+        tx.execute(
+            "UPDATE reservations SET status = ?1, updated_at = ?2 WHERE id = ?3 AND deleted_at IS NULL",
+            [ReservationStatus::Completed.as_str(), &now_str, reservation_id],
+        )?;
+    }
+
+    tx.commit()?;
+    Ok(completed)
+}
+
+fn insert_forfeit_ledger_entry(
+    conn: &Connection,
+    reservation: &Reservation,
+    amount: i32,
+    now: &str,
+) -> Result<()> {
+    let id = Uuid::new_v4().to_string();
     conn.execute(
-        "INSERT INTO staff (id, name, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
-        [&id, name, &now_str, &now_str],
+        "INSERT INTO ledger_entries (id, entry_type, reservation_id, member_id, member_name, seat_id, staff_id, staff_name, gross_amount, discount_amount, total_price, deposit_applied, completed_at, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, 0, ?9, 0, ?10, ?10)",
+        rusqlite::params![
+            id,
+            LedgerEntryType::DepositForfeit.as_str(),
+            reservation.id,
+            reservation.member_id,
+            reservation.member_name,
+            reservation.seat_id.unwrap_or(0),
+            reservation.staff_id,
+            reservation.staff_name,
+            amount,
+            now
+        ],
+    )?;
+
+    conn.execute(
+        "INSERT INTO selected_services (ledger_entry_id, name, length, price) VALUES (?1, ?2, NULL, ?3)",
+        rusqlite::params![id, "예약금 위약금", amount],
+    )?;
+
+    let payment = PaymentInput {
+        method: PaymentMethod::Deposit,
+        amount,
+        approval_number: None,
+    };
+    insert_payment(conn, &id, &payment, now)?;
+    Ok(())
+}
+
+// Compensating entry: negative amounts that cancel all or part of `original`,
+// written together with the balances it gives back. Fails when another refund
+// or a correction changed `original` since it was read.
+pub fn complete_refund(
+    original: &LedgerEntry,
+    amount: i32,
+    reason: &str,
+    approved_by: &str,
+    payments: &[PaymentInput],
+    postings: &RefundPostings,
+) -> WriteResult<CompletedRefund> {
+    let mut conn = get_db().lock();
+    let tx = conn.transaction()?;
+
+    let (total_price, refunded, refunds): (i32, i32, i32) = tx.query_row(
+        "SELECT total_price,
+                (SELECT COALESCE(-SUM(r.total_price), 0) FROM ledger_entries r WHERE r.refund_of = ledger_entries.id),
+                (SELECT COUNT(*) FROM ledger_entries r WHERE r.refund_of = ledger_entries.id)
+         FROM ledger_entries WHERE id = ?1",
+        [&original.id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    if total_price != original.total_price
+        || refunded != original.refunded_amount
+        || (total_price == 0 && refunds > 0)
+    {
+        return Err(conflict(
+            "다른 곳에서 먼저 환불되거나 수정된 매출입니다. 다시 확인해주세요",
+        ));
+    }
+
+    let id = insert_refund_ledger_entry(&tx, original, amount, reason, approved_by, payments)?;
+    let mut member_package_ids = vec![];
+    let mut prepaid = None;
+    let mut points = vec![];
+
+    if let Some(member_id) = &original.member_id {
+        // Money paid from a prepaid balance goes back onto it
+        if postings.prepaid_restored > 0 {
+            let movement = PrepaidMovement {
+                amount: postings.prepaid_restored,
+                ledger_entry_id: Some(id.clone()),
+                memo: Some(reason.to_string()),
+                created_by: Some(approved_by.to_string()),
+                ..Default::default()
+            };
+            prepaid = Some(insert_prepaid_transaction(
+                &tx,
+                member_id,
+                PrepaidTransactionKind::Restore,
+                &movement,
+            )?);
+        }
+
+        let movement = PointMovement {
+            ledger_entry_id: Some(id.clone()),
+            source_entry_id: Some(original.id.clone()),
+            actor: Some(approved_by.to_string()),
+            reason: Some(reason.to_string()),
+        };
+        for (kind, amount) in [
+            (PointTransactionKind::Restore, postings.points_restored),
+            (PointTransactionKind::Reverse, -postings.points_reversed),
+        ] {
+            if amount != 0 {
+                points.push(insert_point_transaction(
+                    &tx, member_id, kind, amount, &movement,
+                )?);
+            }
+        }
+
+        let movement = StampMovement {
+            ledger_entry_id: Some(id.clone()),
+            program_id: None,
+            actor: Some(approved_by.to_string()),
+            reason: Some(reason.to_string()),
+        };
+        for (kind, amount) in [
+            (StampTransactionKind::Revoke, -postings.stamps_revoked),
+            (StampTransactionKind::Restore, postings.stamps_restored),
+        ] {
+            if amount != 0 {
+                insert_stamp_transaction(&tx, member_id, kind, amount, &movement)?;
+            }
+        }
+    }
+
+    if postings.restore_packages {
+        member_package_ids = restore_package_usages(&tx, &original.id)?;
+    }
+    if let Some(coupon_id) = &postings.release_coupon {
+        tx.execute(
+            "UPDATE coupons SET used_count = MAX(used_count - 1, 0), updated_at = ?1 WHERE id = ?2",
+            rusqlite::params![Utc::now().to_rfc3339(), coupon_id],
+        )?;
+    }
+    if let Some(member_package_id) = &postings.close_package {
+        let closed = tx.execute(
+            "UPDATE member_packages SET remaining_count = 0 WHERE id = ?1 AND remaining_count = total_count",
+            [member_package_id],
+        )?;
+        if closed == 0 {
+            return Err(conflict("이미 사용한 회수권은 환불할 수 없습니다"));
+        }
+        member_package_ids.push(member_package_id.clone());
+    }
+
+    let ledger_entry = load_ledger_entry(&tx, &id).ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+    tx.commit()?;
+
+    Ok(CompletedRefund {
+        ledger_entry,
+        member_package_ids,
+        prepaid,
+        points,
+    })
+}
+
+// `payments` carries positive amounts and is stored negated
+fn insert_refund_ledger_entry(
+    conn: &Connection,
+    original: &LedgerEntry,
+    amount: i32,
+    reason: &str,
+    approved_by: &str,
+    payments: &[PaymentInput],
+) -> Result<String> {
+    let id = Uuid::new_v4().to_string();
+    let now_str = Utc::now().to_rfc3339();
+
+    conn.execute(
+        "INSERT INTO ledger_entries (id, entry_type, reservation_id, member_id, member_name, seat_id, staff_id, staff_name, gross_amount, discount_amount, total_price, deposit_applied, refund_of, refund_reason, approved_by, completed_at, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, 0, ?9, 0, ?10, ?11, ?12, ?13, ?14)",
+        rusqlite::params![
+            id,
+            LedgerEntryType::Refund.as_str(),
+            original.reservation_id,
+            original.member_id,
+            original.member_name,
+            original.seat_id,
+            original.staff_id,
+            original.staff_name,
+            -amount,
+            original.id,
+            reason,
+            approved_by,
+            now_str,
+            now_str
+        ],
     )?;
 
-    Ok(Staff {
-        id,
-        name: name.to_string(),
-        created_at: now,
-        updated_at: now,
+    for payment in payments {
+        let refund = PaymentInput {
+            amount: -payment.amount,
+            ..payment.clone()
+        };
+        insert_payment(conn, &id, &refund, &now_str)?;
+    }
+
+    Ok(id)
+}
+
+pub fn get_refunds_by_ledger_id(ledger_id: &str) -> Vec<LedgerEntry> {
+    let ids: Vec<String> = {
+        let conn = get_db().lock();
+        let mut stmt = conn
+            .prepare("SELECT id FROM ledger_entries WHERE refund_of = ?1 ORDER BY completed_at ASC")
+            .unwrap();
+        stmt.query_map([ledger_id], |row| row.get::<_, String>(0))
+            .unwrap()
+            .filter_map(|r| r.ok())
+            .collect()
+    };
+
+    ids.into_iter()
+        .filter_map(|id| get_ledger_entry_by_id(&id))
+        .collect()
+}
+
+// Rewrites staff, services, discounts, amounts and payments from `corrected` and keeps
+// `original` in the correction history. Fails when the entry was refunded or
+// corrected again since `original` was read.
+pub fn correct_ledger_entry(
+    original: &LedgerEntry,
+    corrected: &LedgerEntry,
+    corrected_by: &str,
+    reason: &str,
+    changes: &[FieldChange],
+) -> WriteResult<LedgerEntry> {
+    let now_str = Utc::now().to_rfc3339();
+    let to_json = |e: serde_json::Error| rusqlite::Error::ToSqlConversionFailure(Box::new(e));
+
+    {
+        let mut conn = get_db().lock();
+        let tx = conn.transaction()?;
+
+        let (corrected_at, refunds): (Option<String>, i32) = tx.query_row(
+            "SELECT corrected_at, (SELECT COUNT(*) FROM ledger_entries r WHERE r.refund_of = ledger_entries.id)
+             FROM ledger_entries WHERE id = ?1",
+            [&original.id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let unchanged =
+            corrected_at.and_then(|t| t.parse::<DateTime<Utc>>().ok()) == original.corrected_at;
+        if !unchanged || refunds > 0 {
+            return Err(conflict(
+                "다른 곳에서 먼저 환불되거나 수정된 매출입니다. 다시 확인해주세요",
+            ));
+        }
+
+        tx.execute(
+            "INSERT INTO ledger_corrections (id, ledger_entry_id, corrected_by, reason, changes, original, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![
+                Uuid::new_v4().to_string(),
+                original.id,
+                corrected_by,
+                reason,
+                serde_json::to_string(changes).map_err(to_json)?,
+                serde_json::to_string(original).map_err(to_json)?,
+                now_str
+            ],
+        )?;
+
+        tx.execute(
+            "UPDATE ledger_entries SET staff_id = ?1, staff_name = ?2, gross_amount = ?3, discount_amount = ?4, total_price = ?5, corrected_at = ?6 WHERE id = ?7",
+            rusqlite::params![
+                corrected.staff_id,
+                corrected.staff_name,
+                corrected.gross_amount,
+                corrected.discount_amount,
+                corrected.total_price,
+                now_str,
+                original.id
+            ],
+        )?;
+
+        // Tips follow the designer only when the designer was changed, and only
+        // the ones that were theirs
+        if corrected.staff_id != original.staff_id {
+            tx.execute(
+                "UPDATE tips SET staff_id = ?1, staff_name = ?2 WHERE ledger_entry_id = ?3 AND staff_id = ?4",
+                [
+                    &corrected.staff_id,
+                    &corrected.staff_name,
+                    &original.id,
+                    &original.staff_id,
+                ],
+            )?;
+        }
+
+        tx.execute(
+            "DELETE FROM ledger_discounts WHERE ledger_entry_id = ?1",
+            [&original.id],
+        )?;
+        for discount in &corrected.discounts {
+            tx.execute(
+                "INSERT INTO ledger_discounts (id, ledger_entry_id, source, reference_id, name, amount, kind, value, service_name, single_line)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                rusqlite::params![
+                    discount.id,
+                    original.id,
+                    discount.source.as_str(),
+                    discount.reference_id,
+                    discount.name,
+                    discount.amount,
+                    discount.terms.as_ref().map(|t| t.kind.as_str()),
+                    discount.terms.as_ref().map(|t| t.value),
+                    discount.terms.as_ref().and_then(|t| t.service_name.as_deref()),
+                    discount.terms.as_ref().is_some_and(|t| t.single_line)
+                ],
+            )?;
+        }
+
+        tx.execute(
+            "DELETE FROM service_staff WHERE selected_service_id IN (SELECT id FROM selected_services WHERE ledger_entry_id = ?1)",
+            [&original.id],
+        )?;
+        tx.execute(
+            "DELETE FROM selected_services WHERE ledger_entry_id = ?1",
+            [&original.id],
+        )?;
+        for service in &corrected.services {
+            tx.execute(
+                "INSERT INTO selected_services (ledger_entry_id, name, length, price, category, member_package_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                rusqlite::params![original.id, service.name, service.length, service.price, service.category, service.member_package_id],
+            )?;
+            insert_service_staff(&tx, tx.last_insert_rowid(), &service.staff)?;
+        }
+
+        tx.execute(
+            "DELETE FROM payments WHERE ledger_entry_id = ?1",
+            [&original.id],
+        )?;
+        for payment in &corrected.payments {
+            let input = PaymentInput {
+                method: payment.method,
+                amount: payment.amount,
+                approval_number: payment.approval_number.clone(),
+            };
+            insert_payment(&tx, &original.id, &input, &payment.paid_at.to_rfc3339())?;
+        }
+        tx.commit()?;
+    }
+
+    get_ledger_entry_by_id(&original.id).ok_or(rusqlite::Error::QueryReturnedNoRows.into())
+}
+
+pub fn get_ledger_corrections(ledger_id: &str) -> Vec<LedgerCorrection> {
+    let conn = get_db().lock();
+    let mut stmt = conn
+        .prepare("SELECT id, ledger_entry_id, corrected_by, reason, changes, original, created_at FROM ledger_corrections WHERE ledger_entry_id = ?1 ORDER BY created_at ASC")
+        .unwrap();
+
+    stmt.query_map([ledger_id], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, String>(4)?,
+            row.get::<_, String>(5)?,
+            row.get::<_, String>(6)?,
+        ))
+    })
+    .unwrap()
+    .filter_map(|r| r.ok())
+    .filter_map(
+        |(id, ledger_entry_id, corrected_by, reason, changes, original, created_at)| {
+            Some(LedgerCorrection {
+                id,
+                ledger_entry_id,
+                corrected_by,
+                reason,
+                changes: serde_json::from_str(&changes).ok()?,
+                original: serde_json::from_str(&original).ok()?,
+                created_at: created_at.parse().unwrap_or_default(),
+            })
+        },
+    )
+    .collect()
+}
+
+// Spreads the discounted total over the charged service lines by list price.
+// Lines paid for with a package ticket earn nothing; the pass sale did.
+pub fn allocate_revenue(entry: &LedgerEntry) -> Vec<i32> {
+    let charged = |s: &SelectedService| s.member_package_id.is_none();
+    let gross: i64 = entry
+        .services
+        .iter()
+        .filter(|s| charged(s))
+        .map(|s| i64::from(s.price))
+        .sum();
+    if gross == 0 {
+        return vec![0; entry.services.len()];
+    }
+
+    let mut revenues: Vec<i32> = entry
+        .services
+        .iter()
+        .map(|s| {
+            if charged(s) {
+                (i64::from(s.price) * i64::from(entry.total_price) / gross) as i32
+            } else {
+                0
+            }
+        })
+        .collect();
+    let remainder = entry.total_price - revenues.iter().sum::<i32>();
+    if let Some(last) = (0..entry.services.len())
+        .rev()
+        .find(|&i| charged(&entry.services[i]))
+    {
+        revenues[last] += remainder;
+    }
+    revenues
+}
+
+// Divides one line's revenue between its designers by share percent.
+// Lines without shares belong to the entry's designer.
+pub fn split_line_revenue(
+    entry: &LedgerEntry,
+    service: &SelectedService,
+    revenue: i32,
+) -> Vec<(String, String, i32)> {
+    if service.staff.is_empty() {
+        return vec![(entry.staff_id.clone(), entry.staff_name.clone(), revenue)];
+    }
+
+    let mut split: Vec<(String, String, i32)> = service
+        .staff
+        .iter()
+        .map(|share| {
+            (
+                share.staff_id.clone(),
+                share.staff_name.clone(),
+                (i64::from(revenue) * i64::from(share.share_percent) / 100) as i32,
+            )
+        })
+        .collect();
+    let remainder = revenue - split.iter().map(|s| s.2).sum::<i32>();
+    if let Some(last) = split.last_mut() {
+        last.2 += remainder;
+    }
+    split
+}
+
+// Net revenue per designer for one entry. Refunds follow the original sale's
+// split, and package ticket visits are credited at the pass's price per visit.
+pub fn staff_revenue_split(entry: &LedgerEntry) -> Vec<(String, String, i32)> {
+    let whole = || {
+        vec![(
+            entry.staff_id.clone(),
+            entry.staff_name.clone(),
+            entry.total_price,
+        )]
+    };
+
+    match entry.entry_type {
+        LedgerEntryType::Service => {
+            if entry.services.is_empty() {
+                return whole();
+            }
+            split_by_staff(entry, &credited_revenue(entry))
+        }
+        LedgerEntryType::Refund => {
+            let Some(original) = entry.refund_of.as_deref().and_then(get_ledger_entry_by_id) else {
+                return whole();
+            };
+
+            // The money taken back follows the sale's split
+            let mut split: Vec<(String, String, i32)> = vec![];
+            if original.total_price != 0 {
+                let money = if original.services.is_empty() {
+                    vec![(
+                        original.staff_id.clone(),
+                        original.staff_name.clone(),
+                        original.total_price,
+                    )]
+                } else {
+                    split_by_staff(&original, &allocate_revenue(&original))
+                };
+                split = money
+                    .into_iter()
+                    .map(|(staff_id, staff_name, amount)| {
+                        let scaled = i64::from(amount) * i64::from(entry.total_price)
+                            / i64::from(original.total_price);
+                        (staff_id, staff_name, scaled as i32)
+                    })
+                    .collect();
+                let remainder = entry.total_price - split.iter().map(|s| s.2).sum::<i32>();
+                if let Some(last) = split.last_mut() {
+                    last.2 += remainder;
+                }
+            }
+
+            // Ticket visits are taken back by the refund that gave them back
+            if refund_gives_back_visits(entry, &original) {
+                let visits: Vec<i32> = original
+                    .services
+                    .iter()
+                    .zip(credited_revenue(&original))
+                    .map(|(s, credit)| match s.member_package_id {
+                        Some(_) => -credit,
+                        None => 0,
+                    })
+                    .collect();
+                for (staff_id, staff_name, amount) in split_by_staff(&original, &visits) {
+                    add_staff_revenue(&mut split, staff_id, staff_name, amount);
+                }
+            }
+            split
+        }
+        LedgerEntryType::DepositForfeit => whole(),
+        // Pass sales are made at the counter, not by a designer; the visits
+        // are credited when they are used
+        LedgerEntryType::PackageSale => vec![],
+    }
+}
+
+// What each line credits its designers: its share of the money taken, or the
+// pass's price per visit for a line paid with a ticket
+pub fn credited_revenue(entry: &LedgerEntry) -> Vec<i32> {
+    entry
+        .services
+        .iter()
+        .zip(allocate_revenue(entry))
+        .map(|(s, revenue)| match &s.member_package_id {
+            Some(member_package_id) => get_member_package_by_id(member_package_id)
+                .map_or(0, |p| p.price / p.total_count.max(1)),
+            None => revenue,
+        })
+        .collect()
+}
+
+// Whether `refund` refunded what was left of `original`. That is the refund
+// that gave back the sale's package ticket visits.
+pub fn refund_gives_back_visits(refund: &LedgerEntry, original: &LedgerEntry) -> bool {
+    let mut refunded = 0;
+    for r in get_refunds_by_ledger_id(&original.id) {
+        refunded -= r.total_price;
+        if r.id == refund.id {
+            return refunded >= original.total_price;
+        }
+    }
+    false
+}
+
+fn split_by_staff(entry: &LedgerEntry, revenues: &[i32]) -> Vec<(String, String, i32)> {
+    let mut split: Vec<(String, String, i32)> = vec![];
+    for (service, revenue) in entry.services.iter().zip(revenues) {
+        for (staff_id, staff_name, amount) in split_line_revenue(entry, service, *revenue) {
+            add_staff_revenue(&mut split, staff_id, staff_name, amount);
+        }
+    }
+    split
+}
+
+fn add_staff_revenue(
+    split: &mut Vec<(String, String, i32)>,
+    staff_id: String,
+    staff_name: String,
+    amount: i32,
+) {
+    match split.iter_mut().find(|s| s.0 == staff_id) {
+        Some(s) => s.2 += amount,
+        None => split.push((staff_id, staff_name, amount)),
+    }
+}
+
+pub fn get_ledger_summary(query: &LedgerQuery) -> LedgerSummary {
+    let entries = get_ledger_entries(query);
+
+    let (refunds, sales): (Vec<&LedgerEntry>, Vec<&LedgerEntry>) = entries
+        .iter()
+        .partition(|e| e.entry_type == LedgerEntryType::Refund);
+
+    let gross_revenue: i32 = sales.iter().map(|e| e.gross_amount).sum();
+    let total_discount: i32 = sales.iter().map(|e| e.discount_amount).sum();
+    let total_refunds: i32 = refunds.iter().map(|e| -e.total_price).sum();
+    let total_revenue: i32 = entries.iter().map(|e| e.total_price).sum();
+    let total_count = sales.len() as i32;
+    let refund_count = refunds.len() as i32;
+    let total_tips: i32 = entries.iter().map(|e| e.tip_amount).sum();
+
+    // Group by staff, following each line's revenue shares
+    let mut staff_map: std::collections::HashMap<String, (String, i32, i32)> =
+        std::collections::HashMap::new();
+    for entry in &entries {
+        for (staff_id, staff_name, revenue) in staff_revenue_split(entry) {
+            let e = staff_map.entry(staff_id).or_insert((staff_name, 0, 0));
+            e.1 += revenue;
+            if entry.entry_type != LedgerEntryType::Refund {
+                e.2 += 1;
+            }
+        }
+    }
+    let by_staff: Vec<StaffRevenue> = staff_map
+        .into_iter()
+        .map(|(id, (name, revenue, count))| StaffRevenue {
+            staff_id: id,
+            staff_name: name,
+            revenue,
+            count,
+        })
+        .collect();
+
+    // Group by service
+    let mut service_map: std::collections::HashMap<String, (i32, i32)> =
+        std::collections::HashMap::new();
+    for entry in &entries {
+        for service in &entry.services {
+            let e = service_map.entry(service.name.clone()).or_insert((0, 0));
+            e.0 += 1;
+            if service.member_package_id.is_none() {
+                e.1 += service.price;
+            }
+        }
+    }
+    let by_service: Vec<ServiceCount> = service_map
+        .into_iter()
+        .map(|(name, (count, revenue))| ServiceCount {
+            service_name: name,
+            count,
+            revenue,
+        })
+        .collect();
+
+    // Group by payment method
+    let mut method_map: std::collections::HashMap<&'static str, (PaymentMethod, i32, i32)> =
+        std::collections::HashMap::new();
+    for entry in &entries {
+        for payment in &entry.payments {
+            let e = method_map
+                .entry(payment.method.as_str())
+                .or_insert((payment.method, 0, 0));
+            e.1 += payment.amount;
+            e.2 += 1;
+        }
+    }
+    let by_payment_method: Vec<PaymentMethodTotal> = method_map
+        .into_values()
+        .map(|(method, amount, count)| PaymentMethodTotal {
+            method,
+            amount,
+            count,
+        })
+        .collect();
+
+    // Group by discount
+    let mut discount_map: std::collections::HashMap<String, (i32, i32)> =
+        std::collections::HashMap::new();
+    for entry in &entries {
+        for discount in &entry.discounts {
+            let e = discount_map.entry(discount.name.clone()).or_insert((0, 0));
+            e.0 += discount.amount;
+            e.1 += 1;
+        }
+    }
+    let by_discount: Vec<DiscountTotal> = discount_map
+        .into_iter()
+        .map(|(name, (amount, count))| DiscountTotal {
+            name,
+            amount,
+            count,
+        })
+        .collect();
+
+    LedgerSummary {
+        gross_revenue,
+        total_discount,
+        total_refunds,
+        total_revenue,
+        total_count,
+        refund_count,
+        total_tips,
+        by_staff,
+        by_service,
+        by_payment_method,
+        by_discount,
+    }
+}
+
+pub fn get_daily_summary(year: i32, month: u32) -> Vec<DailySummary> {
+    let conn = get_db().lock();
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT DATE(completed_at, 'localtime') as date,
+                    SUM(CASE WHEN entry_type = 'refund' THEN 0 ELSE COALESCE(gross_amount, total_price) END),
+                    SUM(CASE WHEN entry_type = 'refund' THEN 0 ELSE discount_amount END),
+                    SUM(CASE WHEN entry_type = 'refund' THEN -total_price ELSE 0 END),
+                    SUM(total_price) as revenue,
+                    SUM(CASE WHEN entry_type = 'refund' THEN 0 ELSE 1 END) as count
+             FROM ledger_entries
+             WHERE strftime('%Y', completed_at, 'localtime') = ? AND strftime('%m', completed_at, 'localtime') = ?
+             GROUP BY DATE(completed_at, 'localtime')
+             ORDER BY date ASC",
+        )
+        .unwrap();
+
+    let year_str = format!("{:04}", year);
+    let month_str = format!("{:02}", month);
+
+    stmt.query_map([&year_str, &month_str], |row| {
+        Ok(DailySummary {
+            date: row.get(0)?,
+            gross_revenue: row.get(1)?,
+            total_discount: row.get(2)?,
+            total_refunds: row.get(3)?,
+            revenue: row.get(4)?,
+            count: row.get(5)?,
+        })
     })
+    .unwrap()
+    .filter_map(|r| r.ok())
+    .collect()
 }
 
-pub fn update_staff(id: &str, name: &str) -> Result<Staff> {
-    {
+// ==================== Member Profile ====================
+pub fn get_ledger_entries_by_member(member_id: &str) -> Vec<LedgerEntry> {
+    let entry_ids: Vec<String> = {
         let conn = get_db().lock();
-        let now = Utc::now().to_rfc3339();
-        conn.execute(
-            "UPDATE staff SET name = ?1, updated_at = ?2 WHERE id = ?3 AND deleted_at IS NULL",
-            [name, &now, id],
-        )?;
-    }
-    get_staff_by_id(id).ok_or(rusqlite::Error::QueryReturnedNoRows)
-}
+        let mut stmt = conn
+            .prepare(
+                "SELECT id FROM ledger_entries WHERE member_id = ?1 ORDER BY completed_at DESC",
+            )
+            .unwrap();
+        stmt.query_map([member_id], |row| row.get::<_, String>(0))
+            .unwrap()
+            .filter_map(|r| r.ok())
+            .collect()
+    };
 
-pub fn delete_staff(id: &str) -> Result<()> {
-    let conn = get_db().lock();
-    let now = Utc::now().to_rfc3339();
-    conn.execute("UPDATE staff SET deleted_at = ?1 WHERE id = ?2", [&now, id])?;
-    Ok(())
+    entry_ids
+        .into_iter()
+        .filter_map(|id| get_ledger_entry_by_id(&id))
+        .collect()
 }
 
-// ==================== Seat Operations ====================
-pub fn get_seats() -> Vec<Seat> {
-    let seat_ids: Vec<(i32, String, String, String, String)> = {
+pub fn get_upcoming_reservations_by_member(member_id: &str) -> Vec<Reservation> {
+    let reservation_ids: Vec<String> = {
         let conn = get_db().lock();
         let mut stmt = conn
-            .prepare("SELECT id, name, status, created_at, updated_at FROM seats WHERE deleted_at IS NULL ORDER BY id ASC")
+            .prepare(
+                "SELECT id FROM reservations
+                 WHERE member_id = ?1 AND deleted_at IS NULL AND status = ?2 AND reserved_at >= ?3
+                 ORDER BY reserved_at ASC",
+            )
             .unwrap();
-
-        stmt.query_map([], |row| {
-            Ok((
-                row.get::<_, i32>(0)?,
-                row.get::<_, String>(1)?,
-                row.get::<_, String>(2)?,
-                row.get::<_, String>(3)?,
-                row.get::<_, String>(4)?,
-            ))
-        })
+        stmt.query_map(
+            [
+                member_id,
+                ReservationStatus::Scheduled.as_str(),
+                &Utc::now().to_rfc3339(),
+            ],
+            |row| row.get::<_, String>(0),
+        )
         .unwrap()
         .filter_map(|r| r.ok())
         .collect()
     };
 
-    // Load current sessions for each seat
-    seat_ids
+    reservation_ids
         .into_iter()
-        .map(|(id, name, status, created_at, updated_at)| Seat {
-            id,
-            name,
-            status: SeatStatus::from_str(&status),
-            current_session: get_session_by_seat_id(id),
-            created_at: created_at.parse().unwrap_or_default(),
-            updated_at: updated_at.parse().unwrap_or_default(),
-        })
+        .filter_map(|id| get_reservation_by_id(&id))
         .collect()
 }
 
-pub fn get_seat_by_id(id: i32) -> Option<Seat> {
-    let seat_data: Option<(i32, String, String, String, String)> = {
-        let conn = get_db().lock();
-        conn.query_row(
-            "SELECT id, name, status, created_at, updated_at FROM seats WHERE id = ?1 AND deleted_at IS NULL",
-            [id],
-            |row| {
-                Ok((
-                    row.get::<_, i32>(0)?,
-                    row.get::<_, String>(1)?,
-                    row.get::<_, String>(2)?,
-                    row.get::<_, String>(3)?,
-                    row.get::<_, String>(4)?,
-                ))
-            },
-        ).ok()
+pub fn get_member_profile(member: Member) -> MemberProfile {
+    let history = get_ledger_entries_by_member(&member.id);
+
+    // A sale counts as a visit unless it was refunded in full
+    let visits: Vec<&LedgerEntry> = history
+        .iter()
+        .filter(|e| e.entry_type == LedgerEntryType::Service)
+        .filter(|e| {
+            let voided = history
+                .iter()
+                .any(|r| r.refund_of.as_deref() == Some(e.id.as_str()));
+            e.refunded_amount < e.total_price || (e.total_price == 0 && !voided)
+        })
+        .collect();
+    let visit_count = visits.len() as i32;
+    let total_spend: i32 = history
+        .iter()
+        .filter(|e| e.entry_type != LedgerEntryType::DepositForfeit)
+        .map(|e| e.total_price)
+        .sum();
+    let average_spend = if visit_count > 0 {
+        total_spend / visit_count
+    } else {
+        0
     };
 
-    seat_data.map(|(id, name, status, created_at, updated_at)| Seat {
-        id,
-        name,
-        status: SeatStatus::from_str(&status),
-        current_session: get_session_by_seat_id(id),
-        created_at: created_at.parse().unwrap_or_default(),
-        updated_at: updated_at.parse().unwrap_or_default(),
+    let mut service_map: std::collections::HashMap<String, (i32, i32)> =
+        std::collections::HashMap::new();
+    let mut staff_map: std::collections::HashMap<String, (String, i32, i32)> =
+        std::collections::HashMap::new();
+    for entry in &visits {
+        for (service, revenue) in entry.services.iter().zip(allocate_revenue(entry)) {
+            let e = service_map.entry(service.name.clone()).or_insert((0, 0));
+            e.0 += 1;
+            e.1 += revenue;
+        }
+        let e = staff_map
+            .entry(entry.staff_id.clone())
+            .or_insert((entry.staff_name.clone(), 0, 0));
+        e.1 += entry.total_price - entry.refunded_amount;
+        e.2 += 1;
+    }
+    let mut favorite_services: Vec<ServiceCount> = service_map
+        .into_iter()
+        .map(|(name, (count, revenue))| ServiceCount {
+            service_name: name,
+            count,
+            revenue,
+        })
+        .collect();
+    favorite_services.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then(b.revenue.cmp(&a.revenue))
+            .then(a.service_name.cmp(&b.service_name))
+    });
+    let favorite_staff = staff_map
+        .into_iter()
+        .map(|(id, (name, revenue, count))| StaffRevenue {
+            staff_id: id,
+            staff_name: name,
+            revenue,
+            count,
+        })
+        .max_by(|a, b| a.count.cmp(&b.count).then(a.revenue.cmp(&b.revenue)));
+
+    // Visits are newest first; several on one day count as one
+    let mut days: Vec<chrono::NaiveDate> = visits
+        .iter()
+        .map(|e| e.completed_at.with_timezone(&chrono::Local).date_naive())
+        .collect();
+    days.dedup();
+    let mut gaps: Vec<i64> = days.windows(2).map(|w| (w[0] - w[1]).num_days()).collect();
+    gaps.sort_unstable();
+    let typical_return_days = match gaps.len() {
+        0 => None,
+        n if n % 2 == 1 => Some(gaps[n / 2]),
+        n => Some((gaps[n / 2 - 1] + gaps[n / 2]) / 2),
+    };
+
+    MemberProfile {
+        visit_count,
+        first_visit_at: visits.last().map(|e| e.completed_at),
+        last_visit_at: visits.first().map(|e| e.completed_at),
+        total_spend,
+        average_spend,
+        favorite_services,
+        favorite_staff,
+        typical_return_days,
+        upcoming_reservations: get_upcoming_reservations_by_member(&member.id),
+        history,
+        member,
+    }
+}
+
+// ==================== Treatment Note Operations ====================
+const TREATMENT_NOTE_COLUMNS: &str = "id, member_id, ledger_entry_id, session_id, staff_id, staff_name, color_formula, perm_rod_size, scalp_condition, hair_condition, allergies, memo, created_at, updated_at";
+
+fn map_treatment_note(row: &rusqlite::Row) -> rusqlite::Result<TreatmentNote> {
+    Ok(TreatmentNote {
+        id: row.get(0)?,
+        member_id: row.get(1)?,
+        ledger_entry_id: row.get(2)?,
+        session_id: row.get(3)?,
+        staff_id: row.get(4)?,
+        staff_name: row.get(5)?,
+        color_formula: row
+            .get::<_, Option<String>>(6)?
+            .and_then(|s| serde_json::from_str(&s).ok()),
+        perm_rod_size: row.get(7)?,
+        scalp_condition: row.get(8)?,
+        hair_condition: row.get(9)?,
+        allergies: row.get(10)?,
+        memo: row.get(11)?,
+        created_at: row.get::<_, String>(12)?.parse().unwrap_or_default(),
+        updated_at: row.get::<_, String>(13)?.parse().unwrap_or_default(),
     })
 }
 
-pub fn update_seat_status(id: i32, status: SeatStatus) -> Result<()> {
+pub fn get_treatment_notes(member_id: &str) -> Vec<TreatmentNote> {
     let conn = get_db().lock();
-    let now = Utc::now().to_rfc3339();
-    conn.execute(
-        "UPDATE seats SET status = ?1, updated_at = ?2 WHERE id = ?3",
-        [status.as_str(), &now, &id.to_string()],
-    )?;
-    Ok(())
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM treatment_notes WHERE member_id = ?1 AND deleted_at IS NULL ORDER BY created_at DESC, rowid DESC",
+            TREATMENT_NOTE_COLUMNS
+        ))
+        .unwrap();
+    stmt.query_map([member_id], map_treatment_note)
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
 }
 
-// ==================== Service Session Operations ====================
-pub fn get_session_by_seat_id(seat_id: i32) -> Option<ServiceSession> {
-    let session_data: Option<(
-        String,
-        i32,
-        Option<String>,
-        String,
-        i32,
-        String,
-        String,
-        String,
-        Option<String>,
-        String,
-        String,
-    )> = {
-        let conn = get_db().lock();
-        conn.query_row(
-            "SELECT id, seat_id, member_id, member_name, total_price, staff_id, staff_name, start_time, reservation_id, created_at, updated_at
-             FROM service_sessions WHERE seat_id = ?1",
-            [seat_id],
-            |row| {
-                Ok((
-                    row.get::<_, String>(0)?,
-                    row.get::<_, i32>(1)?,
-                    row.get::<_, Option<String>>(2)?,
-                    row.get::<_, String>(3)?,
-                    row.get::<_, i32>(4)?,
-                    row.get::<_, String>(5)?,
-                    row.get::<_, String>(6)?,
-                    row.get::<_, String>(7)?,
-                    row.get::<_, Option<String>>(8)?,
-                    row.get::<_, String>(9)?,
-                    row.get::<_, String>(10)?,
-                ))
-            },
-        ).ok()
-    };
-
-    session_data.map(
-        |(
-            id,
-            seat_id,
-            member_id,
-            member_name,
-            total_price,
-            staff_id,
-            staff_name,
-            start_time,
-            reservation_id,
-            created_at,
-            updated_at,
-        )| {
-            let services = get_services_by_session_id(&id);
-            ServiceSession {
-                id,
-                seat_id,
-                member_id,
-                member_name,
-                services,
-                total_price,
-                staff_id,
-                staff_name,
-                start_time: start_time.parse().unwrap_or_default(),
-                reservation_id,
-                created_at: created_at.parse().unwrap_or_default(),
-                updated_at: updated_at.parse().unwrap_or_default(),
-            }
-        },
+pub fn get_treatment_note_by_id(id: &str) -> Option<TreatmentNote> {
+    let conn = get_db().lock();
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM treatment_notes WHERE id = ?1 AND deleted_at IS NULL",
+            TREATMENT_NOTE_COLUMNS
+        ),
+        [id],
+        map_treatment_note,
     )
+    .ok()
 }
 
-pub fn create_session(
-    seat_id: i32,
-    member_id: Option<&str>,
-    member_name: &str,
-    staff_id: &str,
-    staff_name: &str,
-    services: &[ServiceInput],
-    total_price: i32,
-    reservation_id: Option<&str>,
-) -> Result<ServiceSession> {
-    let id = Uuid::new_v4().to_string();
-    let now = Utc::now();
-    let now_str = now.to_rfc3339();
-
-    {
-        let conn = get_db().lock();
-        conn.execute(
-            "INSERT INTO service_sessions (id, seat_id, member_id, member_name, total_price, staff_id, staff_name, start_time, reservation_id, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
-            rusqlite::params![id, seat_id, member_id, member_name, total_price, staff_id, staff_name, now_str, reservation_id, now_str, now_str],
-        )?;
-
-        // Insert services
-        for service in services {
-            conn.execute(
-                "INSERT INTO selected_services (service_session_id, name, length, price) VALUES (?1, ?2, ?3, ?4)",
-                rusqlite::params![id, service.name, service.length, service.price],
-            )?;
-        }
-    }
-
-    Ok(ServiceSession {
-        id: id.clone(),
-        seat_id,
-        member_id: member_id.map(|s| s.to_string()),
-        member_name: member_name.to_string(),
-        services: get_services_by_session_id(&id),
-        total_price,
-        staff_id: staff_id.to_string(),
-        staff_name: staff_name.to_string(),
-        start_time: now,
-        reservation_id: reservation_id.map(|s| s.to_string()),
-        created_at: now,
-        updated_at: now,
-    })
+// The newest note that recorded a color formula or perm rods
+pub fn get_latest_formula(member_id: &str) -> Option<TreatmentNote> {
+    let conn = get_db().lock();
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM treatment_notes
+             WHERE member_id = ?1 AND deleted_at IS NULL AND (color_formula IS NOT NULL OR perm_rod_size IS NOT NULL)
+             ORDER BY created_at DESC, rowid DESC LIMIT 1",
+            TREATMENT_NOTE_COLUMNS
+        ),
+        [member_id],
+        map_treatment_note,
+    )
+    .ok()
 }
 
-pub fn delete_session(seat_id: i32) -> Result<Option<ServiceSession>> {
-    let session = get_session_by_seat_id(seat_id);
+pub fn create_treatment_note(
+    request: &TreatmentNoteRequest,
+    staff: Option<&Staff>,
+) -> Result<TreatmentNote> {
+    let id = Uuid::new_v4().to_string();
+    let now_str = Utc::now().to_rfc3339();
+    let color_formula = request
+        .color_formula
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
 
-    if let Some(ref s) = session {
+    {
         let conn = get_db().lock();
-        // Delete services
         conn.execute(
-            "DELETE FROM selected_services WHERE service_session_id = ?1",
-            [&s.id],
+            &format!(
+                "INSERT INTO treatment_notes ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?13)",
+                TREATMENT_NOTE_COLUMNS
+            ),
+            rusqlite::params![
+                id,
+                request.member_id,
+                request.ledger_entry_id,
+                request.session_id,
+                staff.map(|s| &s.id),
+                staff.map(|s| &s.name),
+                color_formula,
+                request.perm_rod_size,
+                request.scalp_condition,
+                request.hair_condition,
+                request.allergies,
+                request.memo,
+                now_str
+            ],
         )?;
-        // Delete session
-        conn.execute("DELETE FROM service_sessions WHERE id = ?1", [&s.id])?;
     }
 
-    Ok(session)
+    get_treatment_note_by_id(&id).ok_or(rusqlite::Error::QueryReturnedNoRows)
 }
 
-pub fn get_services_by_session_id(session_id: &str) -> Vec<SelectedService> {
-    let conn = get_db().lock();
-    let mut stmt = conn
-        .prepare("SELECT id, service_session_id, reservation_id, ledger_entry_id, name, length, price FROM selected_services WHERE service_session_id = ?1")
-        .unwrap();
+pub fn update_treatment_note(
+    id: &str,
+    request: &TreatmentNoteRequest,
+    staff: Option<&Staff>,
+) -> Result<TreatmentNote> {
+    let color_formula = request
+        .color_formula
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
 
-    stmt.query_map([session_id], |row| {
-        Ok(SelectedService {
-            id: row.get(0)?,
-            service_session_id: row.get(1)?,
-            reservation_id: row.get(2)?,
-            ledger_entry_id: row.get(3)?,
-            name: row.get(4)?,
-            length: row.get(5)?,
-            price: row.get(6)?,
-        })
-    })
-    .unwrap()
-    .filter_map(|r| r.ok())
-    .collect()
+    {
+        let conn = get_db().lock();
+        let updated = conn.execute(
+            "UPDATE treatment_notes SET ledger_entry_id = ?1, session_id = ?2, staff_id = ?3, staff_name = ?4, color_formula = ?5,
+                    perm_rod_size = ?6, scalp_condition = ?7, hair_condition = ?8, allergies = ?9, memo = ?10, updated_at = ?11
+             WHERE id = ?12 AND deleted_at IS NULL",
+            rusqlite::params![
+                request.ledger_entry_id,
+                request.session_id,
+                staff.map(|s| &s.id),
+                staff.map(|s| &s.name),
+                color_formula,
+                request.perm_rod_size,
+                request.scalp_condition,
+                request.hair_condition,
+                request.allergies,
+                request.memo,
+                Utc::now().to_rfc3339(),
+                id
+            ],
+        )?;
+        if updated == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+    }
+    get_treatment_note_by_id(id).ok_or(rusqlite::Error::QueryReturnedNoRows)
 }
 
-// ==================== Reservation Operations ====================
-pub fn get_reservations(query: &ReservationQuery) -> Vec<Reservation> {
-    let reservation_ids: Vec<String> = {
+pub fn delete_treatment_note(id: &str) -> Result<()> {
+    let conn = get_db().lock();
+    conn.execute(
+        "UPDATE treatment_notes SET deleted_at = ?1 WHERE id = ?2",
+        [&Utc::now().to_rfc3339(), id],
+    )?;
+    Ok(())
+}
+
+// Moves notes taken during a session onto the entry it was completed as
+pub fn link_treatment_notes(session_id: &str, ledger_entry_id: &str) -> Result<Vec<TreatmentNote>> {
+    let ids: Vec<String> = {
         let conn = get_db().lock();
+        conn.execute(
+            "UPDATE treatment_notes SET ledger_entry_id = ?1, updated_at = ?2 WHERE session_id = ?3 AND ledger_entry_id IS NULL",
+            [ledger_entry_id, &Utc::now().to_rfc3339(), session_id],
+        )?;
+        let mut stmt = conn.prepare(
+            "SELECT id FROM treatment_notes WHERE session_id = ?1 AND deleted_at IS NULL",
+        )?;
+        let ids = stmt
+            .query_map([session_id], |row| row.get::<_, String>(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+        ids
+    };
+    Ok(ids
+        .iter()
+        .filter_map(|id| get_treatment_note_by_id(id))
+        .collect())
+}
 
-        let mut sql = String::from("SELECT id FROM reservations WHERE deleted_at IS NULL");
+// ==================== Member Photo Operations ====================
+const MEMBER_PHOTO_COLUMNS: &str = "id, member_id, ledger_entry_id, kind, content_hash, extension, width, height, file_size, memo, taken_at, created_at";
 
-        let mut params: Vec<String> = vec![];
+fn map_member_photo(row: &rusqlite::Row) -> rusqlite::Result<MemberPhoto> {
+    let content_hash: String = row.get(4)?;
+    let extension: String = row.get(5)?;
+    Ok(MemberPhoto {
+        id: row.get(0)?,
+        member_id: row.get(1)?,
+        ledger_entry_id: row.get(2)?,
+        kind: PhotoKind::from_str(&row.get::<_, String>(3)?),
+        path: photos::original_path(&content_hash, &extension)
+            .to_string_lossy()
+            .to_string(),
+        thumbnail_path: photos::thumbnail_path(&content_hash)
+            .to_string_lossy()
+            .to_string(),
+        content_hash,
+        extension,
+        width: row.get(6)?,
+        height: row.get(7)?,
+        file_size: row.get(8)?,
+        memo: row.get(9)?,
+        taken_at: row.get::<_, String>(10)?.parse().unwrap_or_default(),
+        created_at: row.get::<_, String>(11)?.parse().unwrap_or_default(),
+    })
+}
 
-        if let Some(status) = &query.status {
-            sql.push_str(" AND status = ?");
-            params.push(status.clone());
-        }
+// Oldest first, so a member's styles read as a timeline
+pub fn get_member_photos(member_id: &str) -> Vec<MemberPhoto> {
+    let conn = get_db().lock();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM member_photos WHERE member_id = ?1 AND deleted_at IS NULL ORDER BY taken_at ASC, created_at ASC",
+            MEMBER_PHOTO_COLUMNS
+        ))
+        .unwrap();
+    stmt.query_map([member_id], map_member_photo)
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
+}
 
-        if let Some(date) = &query.date {
-            sql.push_str(" AND DATE(reserved_at) = ?");
-            params.push(date.clone());
-        } else if query.all.as_deref() != Some("true") {
-            sql.push_str(" AND DATE(reserved_at) >= DATE('now')");
-        }
+pub fn get_member_photo_by_id(id: &str) -> Option<MemberPhoto> {
+    let conn = get_db().lock();
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM member_photos WHERE id = ?1 AND deleted_at IS NULL",
+            MEMBER_PHOTO_COLUMNS
+        ),
+        [id],
+        map_member_photo,
+    )
+    .ok()
+}
 
-        sql.push_str(" ORDER BY reserved_at ASC");
+pub fn create_member_photo(
+    request: &AttachPhotoRequest,
+    stored: &photos::StoredPhoto,
+    taken_at: DateTime<Utc>,
+) -> Result<MemberPhoto> {
+    let id = Uuid::new_v4().to_string();
 
-        let mut stmt = conn.prepare(&sql).unwrap();
+    {
+        let conn = get_db().lock();
+        conn.execute(
+            &format!(
+                "INSERT INTO member_photos ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                MEMBER_PHOTO_COLUMNS
+            ),
+            rusqlite::params![
+                id,
+                request.member_id,
+                request.ledger_entry_id,
+                request.kind.as_str(),
+                stored.content_hash,
+                stored.extension,
+                stored.width,
+                stored.height,
+                stored.file_size,
+                request.memo,
+                taken_at.to_rfc3339(),
+                Utc::now().to_rfc3339()
+            ],
+        )?;
+    }
 
-        match params.len() {
-            0 => stmt
-                .query_map([], |row| row.get::<_, String>(0))
-                .unwrap()
-                .filter_map(|r| r.ok())
-                .collect(),
-            1 => stmt
-                .query_map([&params[0]], |row| row.get::<_, String>(0))
-                .unwrap()
-                .filter_map(|r| r.ok())
-                .collect(),
-            2 => stmt
-                .query_map([&params[0], &params[1]], |row| row.get::<_, String>(0))
-                .unwrap()
-                .filter_map(|r| r.ok())
-                .collect(),
-            _ => vec![],
-        }
-    };
+    get_member_photo_by_id(&id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
 
-    // Load full reservations with services
-    reservation_ids
-        .into_iter()
-        .filter_map(|id| get_reservation_by_id(&id))
-        .collect()
+// The file stays; another photo or a backup may share it
+pub fn delete_member_photo(id: &str) -> Result<()> {
+    let conn = get_db().lock();
+    conn.execute(
+        "UPDATE member_photos SET deleted_at = ?1 WHERE id = ?2",
+        [&Utc::now().to_rfc3339(), id],
+    )?;
+    Ok(())
 }
 
-fn map_reservation(row: &rusqlite::Row) -> rusqlite::Result<Reservation> {
-    Ok(Reservation {
+// ==================== Daily Closing Operations ====================
+const DAILY_CLOSING_COLUMNS: &str = "id, business_date, opening_float, cash_sales, cash_refunds, expected_cash, counted_cash, difference, gross_revenue, total_discount, total_refunds, total_revenue, total_count, closed_by, memo, closed_at, cash_tips, prepaid_cash";
+
+fn map_daily_closing(row: &rusqlite::Row) -> rusqlite::Result<DailyClosing> {
+    Ok(DailyClosing {
         id: row.get(0)?,
-        member_id: row.get(1)?,
-        member_name: row.get(2)?,
-        member_phone: row.get(3)?,
-        seat_id: row.get(4)?,
-        staff_id: row.get(5)?,
-        staff_name: row.get(6)?,
-        services: vec![],
-        total_price: row.get(7)?,
-        reserved_at: row.get::<_, String>(8)?.parse().unwrap_or_default(),
-        estimated_duration: row.get(9)?,
-        status: ReservationStatus::from_str(&row.get::<_, String>(10)?),
-        created_at: row.get::<_, String>(11)?.parse().unwrap_or_default(),
-        updated_at: row.get::<_, String>(12)?.parse().unwrap_or_default(),
+        business_date: row.get(1)?,
+        opening_float: row.get(2)?,
+        cash_sales: row.get(3)?,
+        cash_refunds: row.get(4)?,
+        expected_cash: row.get(5)?,
+        counted_cash: row.get(6)?,
+        difference: row.get(7)?,
+        gross_revenue: row.get(8)?,
+        total_discount: row.get(9)?,
+        total_refunds: row.get(10)?,
+        total_revenue: row.get(11)?,
+        total_count: row.get(12)?,
+        closed_by: row.get(13)?,
+        memo: row.get(14)?,
+        closed_at: row.get::<_, String>(15)?.parse().unwrap_or_default(),
+        cash_tips: row.get(16)?,
+        prepaid_cash: row.get(17)?,
     })
 }
 
-pub fn get_reservation_by_id(id: &str) -> Option<Reservation> {
-    let reservation_data: Option<Reservation> = {
+// Expected cash = opening float + cash taken + cash tips + net prepaid cash - cash paid back
+pub fn get_cash_drawer_summary(business_date: &str, opening_float: i32) -> CashDrawerSummary {
+    let query = LedgerQuery {
+        date: Some(business_date.to_string()),
+        ..Default::default()
+    };
+
+    let (mut cash_sales, mut cash_refunds) = (0, 0);
+    for entry in get_ledger_entries(&query) {
+        for payment in entry.payments {
+            if payment.method != PaymentMethod::Cash {
+                continue;
+            }
+            if payment.amount >= 0 {
+                cash_sales += payment.amount;
+            } else {
+                cash_refunds -= payment.amount;
+            }
+        }
+    }
+
+    let cash_tips: i32 = {
         let conn = get_db().lock();
         conn.query_row(
-            "SELECT id, member_id, member_name, member_phone, seat_id, staff_id, staff_name, total_price, reserved_at, estimated_duration, status, created_at, updated_at
-             FROM reservations WHERE id = ?1 AND deleted_at IS NULL",
-            [id],
-            map_reservation,
-        ).ok()
+            "SELECT COALESCE(SUM(amount), 0) FROM tips WHERE DATE(created_at, 'localtime') = ?1 AND method = ?2",
+            [business_date, PaymentMethod::Cash.as_str()],
+            |row| row.get(0),
+        )
+        .unwrap_or(0)
     };
 
-    reservation_data.map(|mut r| {
-        r.services = get_services_by_reservation_id(&r.id);
-        r
-    })
+    // Prepaid top-ups paid in cash, less unused balance paid back in cash
+    let prepaid_cash: i32 = {
+        let conn = get_db().lock();
+        conn.query_row(
+            "SELECT COALESCE(SUM(paid_amount), 0) FROM prepaid_transactions WHERE DATE(created_at, 'localtime') = ?1 AND method = ?2",
+            [business_date, PaymentMethod::Cash.as_str()],
+            |row| row.get(0),
+        )
+        .unwrap_or(0)
+    };
+
+    CashDrawerSummary {
+        business_date: business_date.to_string(),
+        opening_float,
+        cash_sales,
+        cash_refunds,
+        cash_tips,
+        prepaid_cash,
+        expected_cash: opening_float + cash_sales + cash_tips + prepaid_cash - cash_refunds,
+    }
 }
 
-pub fn get_services_by_reservation_id(reservation_id: &str) -> Vec<SelectedService> {
+pub fn get_daily_closing(business_date: &str) -> Option<DailyClosing> {
+    let conn = get_db().lock();
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM daily_closings WHERE business_date = ?1",
+            DAILY_CLOSING_COLUMNS
+        ),
+        [business_date],
+        map_daily_closing,
+    )
+    .ok()
+}
+
+pub fn get_daily_closings(start_date: Option<&str>, end_date: Option<&str>) -> Vec<DailyClosing> {
     let conn = get_db().lock();
     let mut stmt = conn
-        .prepare("SELECT id, service_session_id, reservation_id, ledger_entry_id, name, length, price FROM selected_services WHERE reservation_id = ?1")
+        .prepare(&format!(
+            "SELECT {} FROM daily_closings
+             WHERE (?1 IS NULL OR business_date >= ?1) AND (?2 IS NULL OR business_date <= ?2)
+             ORDER BY business_date DESC",
+            DAILY_CLOSING_COLUMNS
+        ))
         .unwrap();
 
-    stmt.query_map([reservation_id], |row| {
-        Ok(SelectedService {
-            id: row.get(0)?,
-            service_session_id: row.get(1)?,
-            reservation_id: row.get(2)?,
-            ledger_entry_id: row.get(3)?,
-            name: row.get(4)?,
-            length: row.get(5)?,
-            price: row.get(6)?,
-        })
-    })
-    .unwrap()
-    .filter_map(|r| r.ok())
-    .collect()
+    let iter = stmt
+        .query_map(rusqlite::params![start_date, end_date], map_daily_closing)
+        .unwrap();
+    iter.filter_map(|r| r.ok()).collect()
 }
 
-pub fn create_reservation(req: &CreateReservationRequest) -> Result<Reservation> {
-    let id = Uuid::new_v4().to_string();
-    let now = Utc::now();
-    let now_str = now.to_rfc3339();
+pub fn is_day_closed(business_date: &str) -> bool {
+    let conn = get_db().lock();
+    conn.query_row(
+        "SELECT COUNT(*) FROM daily_closings WHERE business_date = ?1",
+        [business_date],
+        |row| row.get::<_, i32>(0),
+    )
+    .unwrap_or(0)
+        > 0
+}
+
+pub fn create_daily_closing(request: &CloseDayRequest, closed_by: &str) -> Result<DailyClosing> {
+    let drawer = get_cash_drawer_summary(&request.business_date, request.opening_float);
+    let summary = get_ledger_summary(&LedgerQuery {
+        date: Some(request.business_date.clone()),
+        ..Default::default()
+    });
 
     {
         let conn = get_db().lock();
         conn.execute(
-            "INSERT INTO reservations (id, member_id, member_name, member_phone, seat_id, staff_id, staff_name, total_price, reserved_at, estimated_duration, status, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            "INSERT INTO daily_closings (id, business_date, opening_float, cash_sales, cash_refunds, expected_cash, counted_cash, difference,
+                                         gross_revenue, total_discount, total_refunds, total_revenue, total_count, closed_by, memo, closed_at, cash_tips, prepaid_cash)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
             rusqlite::params![
-                id,
-                req.member_id,
-                req.member_name,
-                req.member_phone,
-                req.seat_id,
-                req.staff_id,
-                req.staff_name,
-                req.total_price,
-                req.reserved_at.to_rfc3339(),
-                req.estimated_duration,
-                "scheduled",
-                now_str,
-                now_str
+                Uuid::new_v4().to_string(),
+                request.business_date,
+                request.opening_float,
+                drawer.cash_sales,
+                drawer.cash_refunds,
+                drawer.expected_cash,
+                request.counted_cash,
+                request.counted_cash - drawer.expected_cash,
+                summary.gross_revenue,
+                summary.total_discount,
+                summary.total_refunds,
+                summary.total_revenue,
+                summary.total_count,
+                closed_by,
+                request.memo,
+                Utc::now().to_rfc3339(),
+                drawer.cash_tips,
+                drawer.prepaid_cash
             ],
         )?;
-
-        // Insert services
-        for service in &req.services {
-            conn.execute(
-                "INSERT INTO selected_services (reservation_id, name, length, price) VALUES (?1, ?2, ?3, ?4)",
-                rusqlite::params![id, service.name, service.length, service.price],
-            )?;
-        }
     }
 
-    get_reservation_by_id(&id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+    get_daily_closing(&request.business_date).ok_or(rusqlite::Error::QueryReturnedNoRows)
 }
 
-pub fn update_reservation(id: &str, req: &UpdateReservationRequest) -> Result<Reservation> {
+// ==================== Commission Operations ====================
+const COMMISSION_RULE_COLUMNS: &str =
+    "id, staff_id, service_category, kind, value, tiers, active, created_at, updated_at";
+
+fn map_commission_rule(row: &rusqlite::Row) -> rusqlite::Result<CommissionRule> {
+    Ok(CommissionRule {
+        id: row.get(0)?,
+        staff_id: row.get(1)?,
+        service_category: row.get(2)?,
+        kind: CommissionKind::from_str(&row.get::<_, String>(3)?),
+        value: row.get(4)?,
+        tiers: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_default(),
+        active: row.get(6)?,
+        created_at: row.get::<_, String>(7)?.parse().unwrap_or_default(),
+        updated_at: row.get::<_, String>(8)?.parse().unwrap_or_default(),
+    })
+}
+
+pub fn get_commission_rules() -> Vec<CommissionRule> {
+    let conn = get_db().lock();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM commission_rules WHERE deleted_at IS NULL ORDER BY created_at ASC",
+            COMMISSION_RULE_COLUMNS
+        ))
+        .unwrap();
+
+    let iter = stmt.query_map([], map_commission_rule).unwrap();
+    iter.filter_map(|r| r.ok()).collect()
+}
+
+pub fn get_commission_rule_by_id(id: &str) -> Option<CommissionRule> {
+    let conn = get_db().lock();
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM commission_rules WHERE id = ?1 AND deleted_at IS NULL",
+            COMMISSION_RULE_COLUMNS
+        ),
+        [id],
+        map_commission_rule,
+    )
+    .ok()
+}
+
+pub fn create_commission_rule(request: &CommissionRuleRequest) -> Result<CommissionRule> {
+    let id = Uuid::new_v4().to_string();
+    let now_str = Utc::now().to_rfc3339();
+    let tiers = serde_json::to_string(&request.tiers)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+
     {
         let conn = get_db().lock();
-        let now = Utc::now().to_rfc3339();
-
         conn.execute(
-            "UPDATE reservations SET member_id = ?1, member_name = ?2, member_phone = ?3, seat_id = ?4, staff_id = ?5, staff_name = ?6, total_price = ?7, reserved_at = ?8, estimated_duration = ?9, updated_at = ?10 WHERE id = ?11 AND deleted_at IS NULL",
+            "INSERT INTO commission_rules (id, staff_id, service_category, kind, value, tiers, active, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)",
             rusqlite::params![
-                req.member_id,
-                req.member_name,
-                req.member_phone,
-                req.seat_id,
-                req.staff_id,
-                req.staff_name,
-                req.total_price,
-                req.reserved_at.to_rfc3339(),
-                req.estimated_duration,
-                now,
-                id
+                id,
+                request.staff_id,
+                request.service_category,
+                request.kind.as_str(),
+                request.value,
+                tiers,
+                request.active,
+                now_str
             ],
         )?;
-
-        // Delete old services and insert new ones
-        conn.execute(
-            "DELETE FROM selected_services WHERE reservation_id = ?1",
-            [id],
-        )?;
-
-        for service in &req.services {
-            conn.execute(
-                "INSERT INTO selected_services (reservation_id, name, length, price) VALUES (?1, ?2, ?3, ?4)",
-                rusqlite::params![id, service.name, service.length, service.price],
-            )?;
-        }
     }
 
-    get_reservation_by_id(id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+    get_commission_rule_by_id(&id).ok_or(rusqlite::Error::QueryReturnedNoRows)
 }
 
-pub fn update_reservation_status(id: &str, status: ReservationStatus) -> Result<Reservation> {
+pub fn update_commission_rule(id: &str, request: &CommissionRuleRequest) -> Result<CommissionRule> {
+    let tiers = serde_json::to_string(&request.tiers)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+
     {
         let conn = get_db().lock();
-        let now = Utc::now().to_rfc3339();
-
         conn.execute(
-            "UPDATE reservations SET status = ?1, updated_at = ?2 WHERE id = ?3 AND deleted_at IS NULL",
-            [status.as_str(), &now, id],
+            "UPDATE commission_rules SET staff_id = ?1, service_category = ?2, kind = ?3, value = ?4, tiers = ?5, active = ?6, updated_at = ?7
+             WHERE id = ?8 AND deleted_at IS NULL",
+            rusqlite::params![
+                request.staff_id,
+                request.service_category,
+                request.kind.as_str(),
+                request.value,
+                tiers,
+                request.active,
+                Utc::now().to_rfc3339(),
+                id
+            ],
         )?;
     }
-
-    get_reservation_by_id(id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+    get_commission_rule_by_id(id).ok_or(rusqlite::Error::QueryReturnedNoRows)
 }
 
-pub fn delete_reservation(id: &str) -> Result<()> {
+pub fn delete_commission_rule(id: &str) -> Result<()> {
     let conn = get_db().lock();
     let now = Utc::now().to_rfc3339();
-
-    // Delete services
     conn.execute(
-        "DELETE FROM selected_services WHERE reservation_id = ?1",
-        [id],
+        "UPDATE commission_rules SET deleted_at = ?1 WHERE id = ?2",
+        [&now, id],
     )?;
+    Ok(())
+}
+
+// ==================== Tip Operations ====================
+fn map_tip(row: &rusqlite::Row) -> rusqlite::Result<Tip> {
+    Ok(Tip {
+        id: row.get(0)?,
+        ledger_entry_id: row.get(1)?,
+        staff_id: row.get(2)?,
+        staff_name: row.get(3)?,
+        amount: row.get(4)?,
+        method: PaymentMethod::from_str(&row.get::<_, String>(5)?),
+        created_at: row.get::<_, String>(6)?.parse().unwrap_or_default(),
+    })
+}
+
+pub fn create_tip(entry: &LedgerEntry, tip: &TipInput) -> Result<Tip> {
+    let conn = get_db().lock();
+    insert_tip(&conn, &entry.id, &entry.staff_id, &entry.staff_name, tip)
+}
+
+fn insert_tip(
+    conn: &Connection,
+    ledger_entry_id: &str,
+    staff_id: &str,
+    staff_name: &str,
+    tip: &TipInput,
+) -> Result<Tip> {
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now();
 
-    // Soft delete reservation
     conn.execute(
-        "UPDATE reservations SET deleted_at = ?1 WHERE id = ?2",
-        [&now, id],
+        "INSERT INTO tips (id, ledger_entry_id, staff_id, staff_name, amount, method, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        rusqlite::params![
+            id,
+            ledger_entry_id,
+            staff_id,
+            staff_name,
+            tip.amount,
+            tip.method.as_str(),
+            now.to_rfc3339()
+        ],
     )?;
 
-    Ok(())
+    Ok(Tip {
+        id,
+        ledger_entry_id: ledger_entry_id.to_string(),
+        staff_id: staff_id.to_string(),
+        staff_name: staff_name.to_string(),
+        amount: tip.amount,
+        method: tip.method,
+        created_at: now,
+    })
 }
 
-// ==================== Ledger Operations ====================
-pub fn get_ledger_entries(query: &LedgerQuery) -> Vec<LedgerEntry> {
-    let entry_ids: Vec<String> = {
-        let conn = get_db().lock();
+pub fn get_tips_by_ledger_id(ledger_id: &str) -> Vec<Tip> {
+    let conn = get_db().lock();
+    let mut stmt = conn
+        .prepare("SELECT id, ledger_entry_id, staff_id, staff_name, amount, method, created_at FROM tips WHERE ledger_entry_id = ?1 ORDER BY created_at ASC")
+        .unwrap();
 
-        let mut sql = String::from("SELECT id FROM ledger_entries WHERE 1=1");
+    let iter = stmt.query_map([ledger_id], map_tip).unwrap();
+    iter.filter_map(|r| r.ok()).collect()
+}
 
-        let mut params: Vec<String> = vec![];
+// ==================== Notification Operations ====================
+const NOTIFICATION_COLUMNS: &str = "id, kind, channel, member_id, reservation_id, recipient, body, status, attempts, last_error, provider_message_id, scheduled_at, sent_at, created_at, updated_at";
 
-        if let Some(date) = &query.date {
-            sql.push_str(" AND DATE(completed_at) = ?");
-            params.push(date.clone());
-        } else if query.start_date.is_some() && query.end_date.is_some() {
-            sql.push_str(" AND DATE(completed_at) >= ? AND DATE(completed_at) <= ?");
-            params.push(query.start_date.clone().unwrap());
-            params.push(query.end_date.clone().unwrap());
-        }
+fn map_notification(row: &rusqlite::Row) -> rusqlite::Result<Notification> {
+    Ok(Notification {
+        id: row.get(0)?,
+        kind: NotificationKind::from_str(&row.get::<_, String>(1)?),
+        channel: NotificationChannel::from_str(&row.get::<_, String>(2)?),
+        member_id: row.get(3)?,
+        reservation_id: row.get(4)?,
+        recipient: row.get(5)?,
+        body: row.get(6)?,
+        status: NotificationStatus::from_str(&row.get::<_, String>(7)?),
+        attempts: row.get(8)?,
+        last_error: row.get(9)?,
+        provider_message_id: row.get(10)?,
+        scheduled_at: row.get::<_, String>(11)?.parse().unwrap_or_default(),
+        sent_at: row
+            .get::<_, Option<String>>(12)?
+            .and_then(|t| t.parse().ok()),
+        created_at: row.get::<_, String>(13)?.parse().unwrap_or_default(),
+        updated_at: row.get::<_, String>(14)?.parse().unwrap_or_default(),
+    })
+}
 
-        if let Some(staff_id) = &query.staff_id {
-            sql.push_str(" AND staff_id = ?");
-            params.push(staff_id.clone());
-        }
+pub fn get_notifications(query: &NotificationQuery) -> Vec<Notification> {
+    let conn = get_db().lock();
 
-        sql.push_str(" ORDER BY completed_at DESC");
+    let mut sql = format!(
+        "SELECT {} FROM notifications WHERE 1=1",
+        NOTIFICATION_COLUMNS
+    );
+    let mut params: Vec<String> = vec![];
 
-        let mut stmt = conn.prepare(&sql).unwrap();
+    if let Some(status) = &query.status {
+        sql.push_str(" AND status = ?");
+        params.push(status.clone());
+    }
 
-        match params.len() {
-            0 => stmt
-                .query_map([], |row| row.get::<_, String>(0))
-                .unwrap()
-                .filter_map(|r| r.ok())
-                .collect(),
-            1 => stmt
-                .query_map([&params[0]], |row| row.get::<_, String>(0))
-                .unwrap()
-                .filter_map(|r| r.ok())
-                .collect(),
-            2 => stmt
-                .query_map([&params[0], &params[1]], |row| row.get::<_, String>(0))
-                .unwrap()
-                .filter_map(|r| r.ok())
-                .collect(),
-            3 => stmt
-                .query_map([&params[0], &params[1], &params[2]], |row| {
-                    row.get::<_, String>(0)
-                })
-                .unwrap()
-                .filter_map(|r| r.ok())
-                .collect(),
-            _ => vec![],
-        }
-    };
+    if let Some(member_id) = &query.member_id {
+        sql.push_str(" AND member_id = ?");
+        params.push(member_id.clone());
+    }
 
-    // Load full entries with services
-    entry_ids
-        .into_iter()
-        .filter_map(|id| get_ledger_entry_by_id(&id))
+    if let Some(reservation_id) = &query.reservation_id {
+        sql.push_str(" AND reservation_id = ?");
+        params.push(reservation_id.clone());
+    }
+
+    sql.push_str(" ORDER BY scheduled_at DESC");
+
+    let mut stmt = conn.prepare(&sql).unwrap();
+    stmt.query_map(rusqlite::params_from_iter(params.iter()), map_notification)
+        .unwrap()
+        .filter_map(|r| r.ok())
         .collect()
 }
 
-fn get_ledger_entry_by_id(id: &str) -> Option<LedgerEntry> {
-    let entry_data: Option<LedgerEntry> = {
-        let conn = get_db().lock();
-        conn.query_row(
-            "SELECT id, reservation_id, member_id, member_name, seat_id, staff_id, staff_name, total_price, completed_at, created_at
-             FROM ledger_entries WHERE id = ?1",
-            [id],
-            |row| {
-                Ok(LedgerEntry {
-                    id: row.get(0)?,
-                    reservation_id: row.get(1)?,
-                    member_id: row.get(2)?,
-                    member_name: row.get(3)?,
-                    seat_id: row.get(4)?,
-                    staff_id: row.get(5)?,
-                    staff_name: row.get(6)?,
-                    services: vec![],
-                    total_price: row.get(7)?,
-                    completed_at: row.get::<_, String>(8)?.parse().unwrap_or_default(),
-                    created_at: row.get::<_, String>(9)?.parse().unwrap_or_default(),
-                })
-            },
-        ).ok()
-    };
+pub fn get_notification_settings() -> NotificationSettings {
+    get_setting("notification_settings")
+}
 
-    entry_data.map(|mut e| {
-        e.services = get_services_by_ledger_id(&e.id);
-        e
-    })
+pub fn save_notification_settings(settings: &NotificationSettings) -> Result<NotificationSettings> {
+    save_setting("notification_settings", settings)?;
+    Ok(get_notification_settings())
+}
+
+pub fn get_notification_templates() -> NotificationTemplates {
+    get_setting("notification_templates")
+}
+
+pub fn save_notification_templates(
+    templates: &NotificationTemplates,
+) -> Result<NotificationTemplates> {
+    save_setting("notification_templates", templates)?;
+    Ok(get_notification_templates())
+}
+
+pub fn get_notification_by_id(id: &str) -> Option<Notification> {
+    let conn = get_db().lock();
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM notifications WHERE id = ?1",
+            NOTIFICATION_COLUMNS
+        ),
+        [id],
+        map_notification,
+    )
+    .ok()
 }
 
-pub fn get_services_by_ledger_id(ledger_id: &str) -> Vec<SelectedService> {
+pub fn get_due_notifications(now: DateTime<Utc>) -> Vec<Notification> {
     let conn = get_db().lock();
     let mut stmt = conn
-        .prepare("SELECT id, service_session_id, reservation_id, ledger_entry_id, name, length, price FROM selected_services WHERE ledger_entry_id = ?1")
+        .prepare(&format!(
+            "SELECT {} FROM notifications WHERE status = 'pending' AND scheduled_at <= ?1 ORDER BY scheduled_at ASC",
+            NOTIFICATION_COLUMNS
+        ))
         .unwrap();
 
-    stmt.query_map([ledger_id], |row| {
-        Ok(SelectedService {
-            id: row.get(0)?,
-            service_session_id: row.get(1)?,
-            reservation_id: row.get(2)?,
-            ledger_entry_id: row.get(3)?,
-            name: row.get(4)?,
-            length: row.get(5)?,
-            price: row.get(6)?,
-        })
-    })
-    .unwrap()
-    .filter_map(|r| r.ok())
-    .collect()
+    stmt.query_map([now.to_rfc3339()], map_notification)
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
 }
 
-pub fn create_ledger_entry(session: &ServiceSession) -> Result<LedgerEntry> {
+pub fn create_notification(
+    kind: NotificationKind,
+    channel: NotificationChannel,
+    member_id: Option<&str>,
+    reservation_id: Option<&str>,
+    recipient: &str,
+    body: &str,
+    scheduled_at: DateTime<Utc>,
+) -> Result<Notification> {
     let id = Uuid::new_v4().to_string();
-    let now = Utc::now();
-    let now_str = now.to_rfc3339();
+    let now_str = Utc::now().to_rfc3339();
 
     {
         let conn = get_db().lock();
         conn.execute(
-            "INSERT INTO ledger_entries (id, reservation_id, member_id, member_name, seat_id, staff_id, staff_name, total_price, completed_at, created_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            "INSERT INTO notifications (id, kind, channel, member_id, reservation_id, recipient, body, status, attempts, scheduled_at, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 'pending', 0, ?8, ?9, ?10)",
             rusqlite::params![
                 id,
-                session.reservation_id,
-                session.member_id,
-                session.member_name,
-                session.seat_id,
-                session.staff_id,
-                session.staff_name,
-                session.total_price,
+                kind.as_str(),
+                channel.as_str(),
+                member_id,
+                reservation_id,
+                recipient,
+                body,
+                scheduled_at.to_rfc3339(),
                 now_str,
                 now_str
             ],
         )?;
-
-        // Copy services to ledger
-        for service in &session.services {
-            conn.execute(
-                "INSERT INTO selected_services (ledger_entry_id, name, length, price) VALUES (?1, ?2, ?3, ?4)",
-                rusqlite::params![id, service.name, service.length, service.price],
-            )?;
-        }
     }
 
-    Ok(LedgerEntry {
-        id: id.clone(),
-        reservation_id: session.reservation_id.clone(),
-        member_id: session.member_id.clone(),
-        member_name: session.member_name.clone(),
-        seat_id: session.seat_id,
-        staff_id: session.staff_id.clone(),
-        staff_name: session.staff_name.clone(),
-        services: get_services_by_ledger_id(&id),
-        total_price: session.total_price,
-        completed_at: now,
-        created_at: now,
-    })
+    get_notification_by_id(&id).ok_or(rusqlite::Error::QueryReturnedNoRows)
 }
 
-pub fn get_ledger_summary(query: &LedgerQuery) -> LedgerSummary {
-    let entries = get_ledger_entries(query);
+// Moves a pending message to sending; false when someone else claimed it first
+pub fn claim_notification(id: &str) -> Result<bool> {
+    let conn = get_db().lock();
+    let now = Utc::now().to_rfc3339();
+    let claimed = conn.execute(
+        "UPDATE notifications SET status = ?1, updated_at = ?2 WHERE id = ?3 AND status = ?4",
+        [
+            NotificationStatus::Sending.as_str(),
+            &now,
+            id,
+            NotificationStatus::Pending.as_str(),
+        ],
+    )?;
+    Ok(claimed == 1)
+}
 
-    let total_revenue: i32 = entries.iter().map(|e| e.total_price).sum();
-    let total_count = entries.len() as i32;
+// Messages left sending by a shutdown mid-delivery go back to the queue
+pub fn release_sending_notifications() -> Result<()> {
+    let conn = get_db().lock();
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE notifications SET status = ?1, updated_at = ?2 WHERE status = ?3",
+        [
+            NotificationStatus::Pending.as_str(),
+            &now,
+            NotificationStatus::Sending.as_str(),
+        ],
+    )?;
+    Ok(())
+}
 
-    // Group by staff
-    let mut staff_map: std::collections::HashMap<String, (String, i32, i32)> =
-        std::collections::HashMap::new();
-    for entry in &entries {
-        let e = staff_map
-            .entry(entry.staff_id.clone())
-            .or_insert((entry.staff_name.clone(), 0, 0));
-        e.1 += entry.total_price;
-        e.2 += 1;
-    }
-    let by_staff: Vec<StaffRevenue> = staff_map
-        .into_iter()
-        .map(|(id, (name, revenue, count))| StaffRevenue {
-            staff_id: id,
-            staff_name: name,
-            revenue,
-            count,
-        })
-        .collect();
+pub fn mark_notification_sent(id: &str, provider_message_id: &str) -> Result<()> {
+    let conn = get_db().lock();
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE notifications SET status = ?1, attempts = attempts + 1, last_error = NULL, provider_message_id = ?2, sent_at = ?3, updated_at = ?3 WHERE id = ?4",
+        [NotificationStatus::Sent.as_str(), provider_message_id, &now, id],
+    )?;
+    Ok(())
+}
 
-    // Group by service
-    let mut service_map: std::collections::HashMap<String, (i32, i32)> =
-        std::collections::HashMap::new();
-    for entry in &entries {
-        for service in &entry.services {
-            let e = service_map.entry(service.name.clone()).or_insert((0, 0));
-            e.0 += 1;
-            e.1 += service.price;
-        }
+// Records a failed attempt; the message stays pending until `retry_at` or is marked failed
+pub fn mark_notification_failed(
+    id: &str,
+    error: &str,
+    retry_at: Option<DateTime<Utc>>,
+) -> Result<()> {
+    let conn = get_db().lock();
+    let now = Utc::now().to_rfc3339();
+    match retry_at {
+        Some(retry_at) => conn.execute(
+            "UPDATE notifications SET status = ?1, attempts = attempts + 1, last_error = ?2, scheduled_at = ?3, updated_at = ?4 WHERE id = ?5",
+            [
+                NotificationStatus::Pending.as_str(),
+                error,
+                &retry_at.to_rfc3339(),
+                &now,
+                id,
+            ],
+        )?,
+        None => conn.execute(
+            "UPDATE notifications SET status = ?1, attempts = attempts + 1, last_error = ?2, updated_at = ?3 WHERE id = ?4",
+            [NotificationStatus::Failed.as_str(), error, &now, id],
+        )?,
+    };
+    Ok(())
+}
+
+pub fn requeue_notification(id: &str) -> Result<Notification> {
+    {
+        let conn = get_db().lock();
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE notifications SET status = 'pending', attempts = 0, last_error = NULL, scheduled_at = ?1, updated_at = ?1 WHERE id = ?2 AND status NOT IN ('sending', 'sent')",
+            [&now, id],
+        )?;
     }
-    let by_service: Vec<ServiceCount> = service_map
-        .into_iter()
-        .map(|(name, (count, revenue))| ServiceCount {
-            service_name: name,
-            count,
-            revenue,
-        })
-        .collect();
+    get_notification_by_id(id).ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
 
-    LedgerSummary {
-        total_revenue,
-        total_count,
-        by_staff,
-        by_service,
+pub fn cancel_notification(id: &str) -> Result<Notification> {
+    {
+        let conn = get_db().lock();
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE notifications SET status = 'cancelled', updated_at = ?1 WHERE id = ?2 AND status = 'pending'",
+            [&now, id],
+        )?;
     }
+    get_notification_by_id(id).ok_or(rusqlite::Error::QueryReturnedNoRows)
 }
 
-pub fn get_daily_summary(year: i32, month: u32) -> Vec<DailySummary> {
+pub fn cancel_reservation_notifications(reservation_id: &str) -> Result<()> {
     let conn = get_db().lock();
-
-    let mut stmt = conn
-        .prepare(
-            "SELECT DATE(completed_at) as date, SUM(total_price) as revenue, COUNT(*) as count
-             FROM ledger_entries
-             WHERE strftime('%Y', completed_at) = ? AND strftime('%m', completed_at) = ?
-             GROUP BY DATE(completed_at)
-             ORDER BY date ASC",
-        )
-        .unwrap();
-
-    let year_str = format!("{:04}", year);
-    let month_str = format!("{:02}", month);
-
-    stmt.query_map([&year_str, &month_str], |row| {
-        Ok(DailySummary {
-            date: row.get(0)?,
-            revenue: row.get(1)?,
-            count: row.get(2)?,
-        })
-    })
-    .unwrap()
-    .filter_map(|r| r.ok())
-    .collect()
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE notifications SET status = 'cancelled', updated_at = ?1 WHERE reservation_id = ?2 AND status = 'pending'",
+        [&now, reservation_id],
+    )?;
+    Ok(())
 }
@@ -0,0 +1,376 @@
+use chrono::{DateTime, Utc};
+
+use crate::database;
+use crate::models::*;
+use crate::stamps;
+use crate::tiers;
+
+// ==================== Checkout Discounts ====================
+// Resolves a stamp reward, the member's tier discount, the requested rules
+// and coupon into discount lines for the services the package tickets did not
+// cover. The total never exceeds what is charged.
+pub fn compute(
+    session: &ServiceSession,
+    request: &CompleteServiceRequest,
+    redemptions: &[PackageRedemption],
+) -> Result<Vec<DiscountLine>, String> {
+    let now = Utc::now();
+    let covered: Vec<usize> = redemptions.iter().map(|r| r.service_index).collect();
+    let mut lines = vec![];
+
+    // Rules and coupons only apply to what the tickets did not cover
+    let uncovered = |service_name: &str| -> i32 {
+        session
+            .services
+            .iter()
+            .enumerate()
+            .filter(|(i, s)| !covered.contains(i) && s.name == service_name)
+            .map(|(_, s)| s.price)
+            .sum()
+    };
+    let covered_total: i32 = redemptions.iter().map(|r| r.amount).sum();
+    let chargeable = session.total_price - covered_total;
+
+    if request.redeem_stamps {
+        lines.push(stamp_line(session, &covered, chargeable)?);
+    }
+    if !request.skip_tier_discount {
+        lines.extend(tiers::discount_line(session, chargeable));
+    }
+
+    for (i, rule_id) in request.discount_rule_ids.iter().enumerate() {
+        if request.discount_rule_ids[..i].contains(rule_id) {
+            return Err("같은 할인 규칙을 두 번 적용할 수 없습니다".to_string());
+        }
+        let rule =
+            database::get_discount_rule_by_id(rule_id).ok_or("할인 규칙을 찾을 수 없습니다")?;
+        check_rule(&rule, session, request, now)?;
+
+        let base = match &rule.service_name {
+            Some(service_name) => uncovered(service_name),
+            None => chargeable,
+        };
+        if base <= 0 {
+            return Err(format!("'{}' 할인 대상 시술이 없습니다", rule.name));
+        }
+
+        lines.push(DiscountLine {
+            source: DiscountSource::Rule,
+            reference_id: Some(rule.id.clone()),
+            name: rule.name.clone(),
+            amount: amount_for(rule.kind, rule.value, base),
+            terms: Some(DiscountTerms {
+                kind: rule.kind,
+                value: rule.value,
+                service_name: rule.service_name.clone(),
+                single_line: false,
+            }),
+        });
+    }
+
+    if let Some(code) = request
+        .coupon_code
+        .as_deref()
+        .filter(|c| !c.trim().is_empty())
+    {
+        let coupon = validate_coupon(code, now)?;
+        lines.push(DiscountLine {
+            source: DiscountSource::Coupon,
+            reference_id: Some(coupon.id.clone()),
+            name: coupon.name.clone(),
+            amount: amount_for(coupon.kind, coupon.value, chargeable),
+            terms: Some(DiscountTerms {
+                kind: coupon.kind,
+                value: coupon.value,
+                service_name: None,
+                single_line: false,
+            }),
+        });
+    }
+
+    trim_to(&mut lines, chargeable, |l| &mut l.amount);
+
+    Ok(lines)
+}
+
+fn stamp_line(
+    session: &ServiceSession,
+    covered: &[usize],
+    chargeable: i32,
+) -> Result<DiscountLine, String> {
+    let member_id = session
+        .member_id
+        .as_deref()
+        .ok_or("스탬프 혜택은 회원만 사용할 수 있습니다")?;
+    let program =
+        database::get_active_stamp_program().ok_or("진행 중인 스탬프 프로그램이 없습니다")?;
+
+    let member = database::get_member_by_id(member_id).ok_or("회원을 찾을 수 없습니다")?;
+    let balance = stamps::usable(&member, &program, Utc::now());
+    if balance < program.stamps_required {
+        return Err(format!(
+            "스탬프가 부족합니다 ({}/{}개)",
+            balance, program.stamps_required
+        ));
+    }
+
+    Ok(DiscountLine {
+        source: DiscountSource::Stamp,
+        reference_id: Some(program.id.clone()),
+        amount: stamps::reward_amount(&program, &session.services, covered, chargeable)?,
+        terms: Some(stamps::reward_terms(&program)),
+        name: program.name,
+    })
+}
+
+// The services the member's tickets cover, one visit each from the pass that
+// expires first
+pub fn package_redemptions(
+    session: &ServiceSession,
+    request: &CompleteServiceRequest,
+) -> Vec<PackageRedemption> {
+    let mut redemptions = vec![];
+    let Some(member_id) = session
+        .member_id
+        .as_deref()
+        .filter(|_| !request.skip_packages)
+    else {
+        return redemptions;
+    };
+
+    let mut packages = database::get_member_packages(member_id, true);
+    for (i, service) in session.services.iter().enumerate() {
+        let Some(package) = packages
+            .iter_mut()
+            .find(|p| p.service_name == service.name && p.remaining_count > 0)
+        else {
+            continue;
+        };
+        package.remaining_count -= 1;
+        redemptions.push(PackageRedemption {
+            service_index: i,
+            member_package_id: package.id.clone(),
+            name: package.name.clone(),
+            amount: service.price,
+        });
+    }
+
+    redemptions
+}
+
+// Moves the ticket visits of the original lines onto the corrected lines of
+// the same service. A visit cannot be given back by a correction.
+pub fn carry_redemptions(
+    original: &[SelectedService],
+    corrected: &mut [SelectedService],
+) -> Result<(), String> {
+    for service in original.iter().filter(|s| s.member_package_id.is_some()) {
+        let line = corrected
+            .iter_mut()
+            .find(|s| s.member_package_id.is_none() && s.name == service.name)
+            .ok_or_else(|| {
+                format!(
+                    "'{}' 회수권으로 받은 시술은 뺄 수 없습니다. 환불 후 다시 결제해주세요",
+                    service.name
+                )
+            })?;
+        line.member_package_id = service.member_package_id.clone();
+    }
+    Ok(())
+}
+
+// Works the recorded discounts out again for corrected service lines, from
+// the terms kept with the sale rather than the rules as they are now. Nothing
+// is added or re-validated, since stamps and coupons were spent when the
+// sale was made.
+pub fn recompute(entry: &LedgerEntry) -> Vec<LedgerDiscount> {
+    let mut discounts = entry.discounts.clone();
+    let chargeable: i32 = entry
+        .services
+        .iter()
+        .filter(|s| s.member_package_id.is_none())
+        .map(|s| s.price)
+        .sum();
+
+    // Lines from before terms were kept only get trimmed to the new bill
+    for discount in discounts.iter_mut() {
+        if let Some(terms) = &discount.terms {
+            discount.amount = terms_amount(terms, &entry.services, chargeable);
+        }
+    }
+    trim_to(&mut discounts, chargeable, |d| &mut d.amount);
+
+    discounts
+}
+
+// What `terms` take off the service lines the tickets did not cover
+fn terms_amount(terms: &DiscountTerms, services: &[SelectedService], chargeable: i32) -> i32 {
+    let mut lines = services.iter().filter(|s| {
+        s.member_package_id.is_none()
+            && terms
+                .service_name
+                .as_deref()
+                .is_none_or(|name| s.name == name)
+    });
+    let base = if terms.single_line {
+        lines.next().map_or(0, |s| s.price)
+    } else if terms.service_name.is_some() {
+        lines.map(|s| s.price).sum()
+    } else {
+        chargeable
+    };
+    amount_for(terms.kind, terms.value, base.max(0))
+}
+
+// Trims the last lines so the discounts never exceed the bill, dropping
+// lines left at zero
+fn trim_to<T>(lines: &mut Vec<T>, charge: i32, amount: impl Fn(&mut T) -> &mut i32) {
+    let mut remaining = charge.max(0);
+    for line in lines.iter_mut() {
+        let amount = amount(line);
+        *amount = (*amount).min(remaining);
+        remaining -= *amount;
+    }
+    lines.retain_mut(|line| *amount(line) > 0);
+}
+
+pub fn validate_coupon(code: &str, now: DateTime<Utc>) -> Result<Coupon, String> {
+    let coupon = database::get_coupon_by_code(code).ok_or("존재하지 않는 쿠폰입니다")?;
+
+    if !coupon.active {
+        return Err("사용할 수 없는 쿠폰입니다".to_string());
+    }
+    if coupon.expires_at.is_some_and(|t| t < now) {
+        return Err("만료된 쿠폰입니다".to_string());
+    }
+    if coupon.max_uses.is_some_and(|max| coupon.used_count >= max) {
+        return Err("사용 횟수가 모두 소진된 쿠폰입니다".to_string());
+    }
+
+    Ok(coupon)
+}
+
+fn check_rule(
+    rule: &DiscountRule,
+    session: &ServiceSession,
+    request: &CompleteServiceRequest,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    if !rule.active {
+        return Err(format!("'{}' 할인은 비활성 상태입니다", rule.name));
+    }
+    if rule.starts_at.is_some_and(|t| now < t) || rule.ends_at.is_some_and(|t| now > t) {
+        return Err(format!("'{}' 할인 기간이 아닙니다", rule.name));
+    }
+    if rule.member_only && session.member_id.is_none() {
+        return Err(format!("'{}' 할인은 회원 전용입니다", rule.name));
+    }
+    if rule.student_only && !request.is_student {
+        return Err(format!("'{}' 할인은 학생 전용입니다", rule.name));
+    }
+    Ok(())
+}
+
+fn amount_for(kind: DiscountKind, value: i32, base: i32) -> i32 {
+    match kind {
+        DiscountKind::Percentage => base * value.clamp(0, 100) / 100,
+        DiscountKind::FixedAmount => value.clamp(0, base),
+    }
+}
+
+pub fn validate_value(kind: DiscountKind, value: i32) -> Result<(), String> {
+    match kind {
+        DiscountKind::Percentage if !(1..=100).contains(&value) => {
+            Err("할인율은 1~100 사이여야 합니다".to_string())
+        }
+        DiscountKind::FixedAmount if value <= 0 => {
+            Err("할인 금액은 0원보다 커야 합니다".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(name: &str, price: i32, ticket: bool) -> SelectedService {
+        serde_json::from_value(serde_json::json!({
+            "id": 0,
+            "name": name,
+            "price": price,
+            "member_package_id": ticket.then_some("pass"),
+        }))
+        .unwrap()
+    }
+
+    fn terms(kind: DiscountKind, value: i32, service_name: Option<&str>) -> DiscountTerms {
+        DiscountTerms {
+            kind,
+            value,
+            service_name: service_name.map(str::to_string),
+            single_line: false,
+        }
+    }
+
+    #[test]
+    fn amount_for_rounds_down_and_caps_at_the_base() {
+        assert_eq!(amount_for(DiscountKind::Percentage, 10, 25000), 2500);
+        assert_eq!(amount_for(DiscountKind::Percentage, 15, 9990), 1498);
+        assert_eq!(amount_for(DiscountKind::Percentage, 150, 20000), 20000);
+        assert_eq!(amount_for(DiscountKind::Percentage, -5, 20000), 0);
+        assert_eq!(amount_for(DiscountKind::FixedAmount, 5000, 30000), 5000);
+        assert_eq!(amount_for(DiscountKind::FixedAmount, 5000, 3000), 3000);
+        assert_eq!(amount_for(DiscountKind::FixedAmount, 5000, 0), 0);
+    }
+
+    #[test]
+    fn validate_value_checks_the_range_for_each_kind() {
+        assert!(validate_value(DiscountKind::Percentage, 1).is_ok());
+        assert!(validate_value(DiscountKind::Percentage, 100).is_ok());
+        assert!(validate_value(DiscountKind::Percentage, 0).is_err());
+        assert!(validate_value(DiscountKind::Percentage, 101).is_err());
+        assert!(validate_value(DiscountKind::FixedAmount, 1).is_ok());
+        assert!(validate_value(DiscountKind::FixedAmount, 0).is_err());
+        assert!(validate_value(DiscountKind::FixedAmount, -1000).is_err());
+    }
+
+    #[test]
+    fn trim_to_cuts_the_last_lines_to_the_bill() {
+        let mut lines = vec![5000, 3000, 2000];
+        trim_to(&mut lines, 6000, |l| l);
+        assert_eq!(lines, vec![5000, 1000]);
+
+        let mut lines = vec![5000, 3000];
+        trim_to(&mut lines, 10000, |l| l);
+        assert_eq!(lines, vec![5000, 3000]);
+
+        let mut lines = vec![5000, 0, 3000];
+        trim_to(&mut lines, -1000, |l| l);
+        assert!(lines.is_empty());
+    }
+
+    #[test]
+    fn terms_amount_uses_only_the_charged_lines_it_applies_to() {
+        let services = vec![
+            service("커트", 20000, false),
+            service("염색", 50000, false),
+            service("커트", 20000, true),
+        ];
+
+        let whole = terms(DiscountKind::Percentage, 10, None);
+        assert_eq!(terms_amount(&whole, &services, 70000), 7000);
+
+        let cut = terms(DiscountKind::Percentage, 10, Some("커트"));
+        assert_eq!(terms_amount(&cut, &services, 70000), 2000);
+
+        let free_service = DiscountTerms {
+            single_line: true,
+            ..terms(DiscountKind::Percentage, 100, Some("염색"))
+        };
+        assert_eq!(terms_amount(&free_service, &services, 70000), 50000);
+
+        let missing = terms(DiscountKind::FixedAmount, 5000, Some("펌"));
+        assert_eq!(terms_amount(&missing, &services, 70000), 0);
+    }
+}
@@ -0,0 +1,216 @@
+use crate::models::*;
+use crate::phone;
+
+// Names at least this similar count as the same person when something else
+// (phone ending, birthday) also matches
+const SIMILAR_NAME: f64 = 0.8;
+
+// ==================== Duplicate Detection ====================
+// The E.164 form, or just the digits for numbers that do not normalize
+pub fn phone_key(phone: &str) -> String {
+    phone::normalize(phone)
+        .unwrap_or_else(|_| phone.chars().filter(|c| c.is_ascii_digit()).collect())
+}
+
+// 1.0 for the same name ignoring spaces and case, down to 0.0
+pub fn name_similarity(a: &str, b: &str) -> f64 {
+    let clean = |s: &str| -> Vec<char> {
+        s.chars()
+            .filter(|c| !c.is_whitespace())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+    let (a, b) = (clean(a), clean(b));
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 0.0;
+    }
+    1.0 - edit_distance(&a, &b) as f64 / longest as f64
+}
+
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = previous[j] + usize::from(ca != cb);
+            current.push(substitute.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// Pairs of members that look like the same person, most likely first. With
+// `member_id`, only pairs that include that member.
+pub fn candidates(members: &[Member], member_id: Option<&str>) -> Vec<DuplicateCandidate> {
+    let mut found = vec![];
+    for (i, a) in members.iter().enumerate() {
+        for b in &members[i + 1..] {
+            if member_id.is_some_and(|id| a.id != id && b.id != id) {
+                continue;
+            }
+            if let Some(candidate) = compare(a, b) {
+                found.push(candidate);
+            }
+        }
+    }
+    found.sort_by(|a, b| b.score.total_cmp(&a.score));
+    found
+}
+
+fn compare(a: &Member, b: &Member) -> Option<DuplicateCandidate> {
+    let (phone_a, phone_b) = (phone_key(&a.phone), phone_key(&b.phone));
+    let same_phone = !phone_a.is_empty() && phone_a == phone_b;
+    let same_suffix = phone_a.len() >= 4
+        && phone_b.len() >= 4
+        && phone_a[phone_a.len() - 4..] == phone_b[phone_b.len() - 4..];
+    let same_birthday = a.birthday.is_some() && a.birthday == b.birthday;
+    let similarity = name_similarity(&a.name, &b.name);
+
+    let likely = same_phone
+        || similarity >= 1.0
+        || (similarity >= SIMILAR_NAME && (same_suffix || same_birthday));
+    if !likely {
+        return None;
+    }
+
+    let mut reasons = vec![];
+    if same_phone {
+        reasons.push(DuplicateReason::SamePhone);
+    } else if same_suffix {
+        reasons.push(DuplicateReason::SamePhoneSuffix);
+    }
+    if similarity >= SIMILAR_NAME {
+        reasons.push(DuplicateReason::SimilarName);
+    }
+    if same_birthday {
+        reasons.push(DuplicateReason::SameBirthday);
+    }
+
+    let score = if same_phone {
+        1.0
+    } else {
+        0.6 * similarity
+            + if same_suffix { 0.2 } else { 0.0 }
+            + if same_birthday { 0.2 } else { 0.0 }
+    };
+
+    Some(DuplicateCandidate {
+        member: a.clone(),
+        duplicate: b.clone(),
+        reasons,
+        score,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, Utc};
+
+    fn member(id: &str, name: &str, phone: &str, birthday: Option<&str>) -> Member {
+        Member {
+            id: id.to_string(),
+            name: name.to_string(),
+            phone: phone.to_string(),
+            phone_display: phone::format(phone),
+            stamps: 0,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            prepaid_balance: 0,
+            prepaid_expires_at: None,
+            points: 0,
+            tier_id: None,
+            tier_name: None,
+            birthday: birthday.map(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap()),
+            gender: None,
+            memo: None,
+            marketing_consent: false,
+            marketing_consent_at: None,
+            tags: vec![],
+            custom_fields: Default::default(),
+            packages: vec![],
+        }
+    }
+
+    fn reasons(a: &Member, b: &Member) -> Option<Vec<DuplicateReason>> {
+        compare(a, b).map(|c| c.reasons)
+    }
+
+    #[test]
+    fn name_similarity_ignores_spaces_and_case() {
+        assert_eq!(name_similarity("김 민지", "김민지"), 1.0);
+        assert_eq!(name_similarity("Kim Minji", "kimminji"), 1.0);
+        assert_eq!(name_similarity("김민지", "김민주"), 1.0 - 1.0 / 3.0);
+        assert_eq!(name_similarity("", ""), 0.0);
+    }
+
+    #[test]
+    fn phone_key_matches_formatting_variants() {
+        assert_eq!(phone_key("010-1234-5678"), phone_key("+82 10 1234 5678"));
+        assert_eq!(phone_key("1234"), "1234");
+    }
+
+    #[test]
+    fn same_phone_is_a_duplicate_whatever_the_name() {
+        let a = member("a", "김민지", "+821012345678", None);
+        let b = member("b", "박서준", "010-1234-5678", None);
+
+        let candidate = compare(&a, &b).unwrap();
+        assert_eq!(candidate.reasons, vec![DuplicateReason::SamePhone]);
+        assert_eq!(candidate.score, 1.0);
+    }
+
+    #[test]
+    fn similar_names_need_a_second_match() {
+        let a = member("a", "Lee Seoyeon", "+821012345678", Some("1990-05-01"));
+        let suffix = member("b", "Lee Seoyoun", "+821099995678", None);
+        let birthday = member("c", "Lee Seoyoun", "+821011112222", Some("1990-05-01"));
+        let neither = member("d", "Lee Seoyoun", "+821011112222", None);
+
+        assert_eq!(
+            reasons(&a, &suffix),
+            Some(vec![
+                DuplicateReason::SamePhoneSuffix,
+                DuplicateReason::SimilarName
+            ])
+        );
+        assert_eq!(
+            reasons(&a, &birthday),
+            Some(vec![
+                DuplicateReason::SimilarName,
+                DuplicateReason::SameBirthday
+            ])
+        );
+        assert_eq!(reasons(&a, &neither), None);
+        // One syllable off in a three-syllable name is not similar enough
+        let short = member("e", "김민지", "+821012345678", None);
+        assert_eq!(
+            reasons(&short, &member("f", "김민주", "+821099995678", None)),
+            None
+        );
+    }
+
+    #[test]
+    fn candidates_are_sorted_and_filtered_by_member() {
+        let members = vec![
+            member("a", "김민지", "+821012345678", None),
+            member("b", "김민지", "+821098765432", None),
+            member("c", "박서준", "+821012345678", None),
+            member("d", "이하늘", "+821055554444", None),
+        ];
+
+        let all = candidates(&members, None);
+        let pairs: Vec<(&str, &str)> = all
+            .iter()
+            .map(|c| (c.member.id.as_str(), c.duplicate.id.as_str()))
+            .collect();
+        assert_eq!(pairs, vec![("a", "c"), ("a", "b")]);
+
+        let for_b = candidates(&members, Some("b"));
+        assert_eq!(for_b.len(), 1);
+        assert_eq!(for_b[0].duplicate.id, "b");
+        assert!(candidates(&members, Some("d")).is_empty());
+    }
+}
@@ -0,0 +1,43 @@
+use once_cell::sync::{Lazy, OnceCell};
+use parking_lot::Mutex;
+use std::sync::mpsc::{self, Receiver, Sender};
+use tauri::{AppHandle, Emitter};
+
+use crate::models::ChangeEvent;
+
+static APP_HANDLE: OnceCell<AppHandle> = OnceCell::new();
+
+// Open SSE connections on the HTTP API server
+static SUBSCRIBERS: Lazy<Mutex<Vec<Sender<String>>>> = Lazy::new(|| Mutex::new(vec![]));
+
+pub fn init(handle: AppHandle) {
+    APP_HANDLE.set(handle).ok();
+}
+
+pub fn subscribe() -> Receiver<String> {
+    let (tx, rx) = mpsc::channel();
+    SUBSCRIBERS.lock().push(tx);
+    rx
+}
+
+// Ends every open SSE stream, e.g. when the API server stops
+pub fn disconnect_all() {
+    SUBSCRIBERS.lock().clear();
+}
+
+// Sends the event to the webview and every connected SSE client
+pub fn emit(event: ChangeEvent) {
+    if let Some(app) = APP_HANDLE.get() {
+        let _ = app.emit(event.name(), &event);
+    }
+
+    let Ok(data) = serde_json::to_string(&event) else {
+        return;
+    };
+    let message = format!("event: {}\ndata: {}\n\n", event.name(), data);
+
+    // Disconnected clients drop their receiver and are removed here
+    SUBSCRIBERS
+        .lock()
+        .retain(|tx| tx.send(message.clone()).is_ok());
+}
@@ -0,0 +1,433 @@
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDateTime, TimeZone, Utc};
+
+use crate::models::{Reservation, ReservationStatus};
+
+const PRODID: &str = "-//TimeHair//Reservations//KO";
+const UID_DOMAIN: &str = "timehair";
+
+// ==================== Export ====================
+pub fn reservation_uid(reservation_id: &str) -> String {
+    format!("{}@{}", reservation_id, UID_DOMAIN)
+}
+
+// Returns the reservation id when the UID was produced by `reservation_uid`
+pub fn reservation_id_from_uid(uid: &str) -> Option<&str> {
+    uid.strip_suffix(&format!("@{}", UID_DOMAIN))
+}
+
+pub fn export_reservations(reservations: &[Reservation], calendar_name: &str) -> String {
+    let now = format_utc(Utc::now());
+    let mut lines: Vec<String> = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODID),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", escape_text(calendar_name)),
+    ];
+
+    for r in reservations {
+        let end = r.reserved_at + Duration::minutes(i64::from(r.estimated_duration.max(0)));
+        let services = r
+            .services
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let summary = if services.is_empty() {
+            r.member_name.clone()
+        } else {
+            format!("{} - {}", r.member_name, services)
+        };
+
+        let mut description = format!("담당: {}\n금액: {}원", r.staff_name, r.total_price);
+        if let Some(phone) = &r.member_phone {
+            description.push_str(&format!("\n연락처: {}", crate::phone::format(phone)));
+        }
+
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", reservation_uid(&r.id)));
+        lines.push(format!("DTSTAMP:{}", now));
+        lines.push(format!("DTSTART:{}", format_utc(r.reserved_at)));
+        lines.push(format!("DTEND:{}", format_utc(end)));
+        lines.push(format!("SUMMARY:{}", escape_text(&summary)));
+        lines.push(format!("DESCRIPTION:{}", escape_text(&description)));
+        lines.push(format!(
+            "STATUS:{}",
+            match r.status {
+                ReservationStatus::Cancelled | ReservationStatus::NoShow => "CANCELLED",
+                _ => "CONFIRMED",
+            }
+        ));
+        lines.push(format!("LAST-MODIFIED:{}", format_utc(r.updated_at)));
+        lines.push("END:VEVENT".to_string());
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    lines
+        .iter()
+        .map(|line| fold_line(line))
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+fn format_utc(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+// Content lines are limited to 75 octets; continuation lines start with a space
+fn fold_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+
+    for c in line.chars() {
+        let len = c.len_utf8();
+        if width + len > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += len;
+    }
+
+    folded.push_str("\r\n");
+    folded
+}
+
+// ==================== Import ====================
+#[derive(Debug, Clone)]
+pub struct IcsEvent {
+    pub uid: Option<String>,
+    pub summary: String,
+    pub start: DateTime<Utc>,
+    pub duration_minutes: i32,
+    pub cancelled: bool,
+}
+
+// An event that could not be read; the rest of the file is still imported
+#[derive(Debug, Clone)]
+pub struct IcsEventError {
+    pub uid: Option<String>,
+    pub summary: String,
+    pub reason: String,
+}
+
+pub fn parse_events(content: &str) -> Result<Vec<Result<IcsEvent, IcsEventError>>, String> {
+    let lines = unfold_lines(content);
+
+    if !lines
+        .iter()
+        .any(|l| l.eq_ignore_ascii_case("BEGIN:VCALENDAR"))
+    {
+        return Err("유효한 iCalendar 파일이 아닙니다".to_string());
+    }
+
+    let mut events = vec![];
+    let mut current: Option<Vec<(String, String, String)>> = None;
+
+    for line in lines {
+        let Some((name, params, value)) = split_property(&line) else {
+            continue;
+        };
+
+        match (name.as_str(), value.to_ascii_uppercase().as_str()) {
+            ("BEGIN", "VEVENT") => current = Some(vec![]),
+            ("END", "VEVENT") => {
+                if let Some(props) = current.take() {
+                    events.push(build_event(&props));
+                }
+            }
+            _ => {
+                if let Some(props) = current.as_mut() {
+                    props.push((name, params, value));
+                }
+            }
+        }
+    }
+
+    Ok(events)
+}
+
+fn unfold_lines(content: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+
+    for raw in content.split('\n') {
+        let raw = raw.strip_suffix('\r').unwrap_or(raw);
+        if let Some(rest) = raw.strip_prefix([' ', '\t']) {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(rest);
+                continue;
+            }
+        }
+        if !raw.is_empty() {
+            lines.push(raw.to_string());
+        }
+    }
+
+    lines
+}
+
+// Splits `NAME;PARAM=X:VALUE` into upper-cased name, raw params and value
+fn split_property(line: &str) -> Option<(String, String, String)> {
+    let colon = line.find(':')?;
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let (name, params) = match head.find(';') {
+        Some(i) => (&head[..i], &head[i + 1..]),
+        None => (head, ""),
+    };
+    Some((
+        name.to_ascii_uppercase(),
+        params.to_ascii_uppercase(),
+        value.to_string(),
+    ))
+}
+
+fn build_event(props: &[(String, String, String)]) -> Result<IcsEvent, IcsEventError> {
+    let find = |key: &str| props.iter().find(|(name, _, _)| name == key);
+    let uid = find("UID").map(|(_, _, v)| v.trim().to_string());
+    let summary = find("SUMMARY")
+        .map(|(_, _, v)| unescape_text(v))
+        .unwrap_or_default();
+    let error = |reason: String| IcsEventError {
+        uid: uid.clone(),
+        summary: summary.clone(),
+        reason,
+    };
+
+    let (_, start_params, start_value) =
+        find("DTSTART").ok_or_else(|| error("시작 시각(DTSTART)이 없습니다".to_string()))?;
+    if is_all_day(start_value, start_params) {
+        return Err(error(
+            "종일 일정은 예약 시각이 없어 가져올 수 없습니다".to_string(),
+        ));
+    }
+    let start = parse_datetime(start_value, start_params).map_err(&error)?;
+
+    let duration_minutes = match find("DTEND") {
+        Some((_, params, value)) => {
+            let end = parse_datetime(value, params).map_err(&error)?;
+            Some((end - start).num_minutes() as i32)
+        }
+        None => find("DURATION").and_then(|(_, _, value)| parse_duration_minutes(value)),
+    }
+    .filter(|m| *m > 0)
+    .unwrap_or(60);
+
+    Ok(IcsEvent {
+        uid,
+        summary,
+        start,
+        duration_minutes,
+        cancelled: find("STATUS").is_some_and(|(_, _, v)| v.eq_ignore_ascii_case("CANCELLED")),
+    })
+}
+
+// `VALUE=DATE`, or a bare date some calendars write without the parameter
+fn is_all_day(value: &str, params: &str) -> bool {
+    let value = value.trim();
+    (params.contains("VALUE=DATE") && !params.contains("VALUE=DATE-TIME"))
+        || (value.len() == 8 && value.bytes().all(|b| b.is_ascii_digit()))
+}
+
+// UTC (`Z`) times are absolute, floating times are taken as shop local time and
+// TZID times are converted when the zone is one we know the offset of
+fn parse_datetime(value: &str, params: &str) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    let unreadable = || format!("시각을 해석할 수 없습니다: {}", value);
+
+    if let Some(utc) = value.strip_suffix('Z') {
+        let naive =
+            NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").map_err(|_| unreadable())?;
+        return Ok(Utc.from_utc_datetime(&naive));
+    }
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").map_err(|_| unreadable())?;
+
+    let tzid = params
+        .split(';')
+        .find_map(|param| param.strip_prefix("TZID="))
+        .map(|tzid| tzid.trim_matches('"'));
+    let local = match tzid {
+        Some(tzid) => fixed_zone(tzid)
+            .ok_or_else(|| format!("지원하지 않는 시간대입니다: {}", tzid))?
+            .from_local_datetime(&naive)
+            .single()
+            .map(|t| t.with_timezone(&Utc)),
+        None => Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|t| t.with_timezone(&Utc)),
+    };
+    local.ok_or_else(unreadable)
+}
+
+// Zones without daylight saving, so a fixed offset converts them exactly;
+// names arrive upper-cased from split_property
+fn fixed_zone(tzid: &str) -> Option<FixedOffset> {
+    match tzid {
+        "ASIA/SEOUL" | "KOREA STANDARD TIME" => FixedOffset::east_opt(9 * 3600),
+        "UTC" | "ETC/UTC" | "GMT" | "ETC/GMT" => FixedOffset::east_opt(0),
+        _ => None,
+    }
+}
+
+// Supports the `PnW`, `PnDTnHnMnS` forms used for event durations
+fn parse_duration_minutes(value: &str) -> Option<i32> {
+    let value = value.trim().strip_prefix('P')?;
+    let mut minutes = 0;
+    let mut number = String::new();
+    let mut in_time = false;
+
+    for c in value.chars() {
+        match c {
+            'T' => in_time = true,
+            '0'..='9' => number.push(c),
+            _ => {
+                let n: i32 = number.parse().ok()?;
+                number.clear();
+                minutes += match (c, in_time) {
+                    ('W', false) => n * 7 * 24 * 60,
+                    ('D', false) => n * 24 * 60,
+                    ('H', true) => n * 60,
+                    ('M', true) => n,
+                    ('S', true) => n / 60,
+                    _ => return None,
+                };
+            }
+        }
+    }
+
+    Some(minutes)
+}
+
+fn unescape_text(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') | Some('N') => result.push('\n'),
+                Some(other) => result.push(other),
+                None => {}
+            }
+        } else {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calendar(events: &[&str]) -> String {
+        let mut lines = vec!["BEGIN:VCALENDAR", "VERSION:2.0"];
+        for event in events {
+            lines.push("BEGIN:VEVENT");
+            lines.extend(event.lines());
+            lines.push("END:VEVENT");
+        }
+        lines.push("END:VCALENDAR");
+        lines.join("\r\n")
+    }
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        Utc.from_utc_datetime(&NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap())
+    }
+
+    #[test]
+    fn folded_lines_are_joined() {
+        let content = calendar(&["UID:a@x\nSUMMARY:김민지 - 커트\\, \n 염색\nDTSTART:20261020T0\n\t10000Z\nDURATION:PT1H30M"]);
+        let events = parse_events(&content).unwrap();
+
+        let event = events[0].as_ref().unwrap();
+        assert_eq!(event.summary, "김민지 - 커트, 염색");
+        assert_eq!(event.start, utc("2026-10-20 01:00"));
+        assert_eq!(event.duration_minutes, 90);
+    }
+
+    #[test]
+    fn exported_lines_fold_and_read_back() {
+        let long = "가".repeat(40);
+        let folded = fold_line(&format!("SUMMARY:{}", long));
+
+        assert!(folded.split("\r\n").all(|line| line.len() <= 75));
+        assert_eq!(unfold_lines(&folded), vec![format!("SUMMARY:{}", long)]);
+    }
+
+    #[test]
+    fn tzid_times_are_converted() {
+        let content = calendar(&[
+            "UID:seoul\nDTSTART;TZID=Asia/Seoul:20261020T100000\nDTEND;TZID=Asia/Seoul:20261020T110000",
+            "UID:quoted\nDTSTART;TZID=\"Korea Standard Time\":20261020T100000",
+            "UID:utc\nDTSTART;TZID=UTC:20261020T100000",
+        ]);
+        let events = parse_events(&content).unwrap();
+
+        let seoul = events[0].as_ref().unwrap();
+        assert_eq!(seoul.start, utc("2026-10-20 01:00"));
+        assert_eq!(seoul.duration_minutes, 60);
+        assert_eq!(events[1].as_ref().unwrap().start, utc("2026-10-20 01:00"));
+        assert_eq!(events[2].as_ref().unwrap().start, utc("2026-10-20 10:00"));
+    }
+
+    #[test]
+    fn unknown_tzid_is_reported() {
+        let content = calendar(&["UID:ny\nDTSTART;TZID=America/New_York:20261020T100000"]);
+        let events = parse_events(&content).unwrap();
+
+        let error = events[0].as_ref().unwrap_err();
+        assert_eq!(error.uid.as_deref(), Some("ny"));
+        assert!(error.reason.contains("AMERICA/NEW_YORK"));
+    }
+
+    #[test]
+    fn all_day_events_are_reported_not_imported() {
+        let content = calendar(&[
+            "UID:holiday\nSUMMARY:휴무\nDTSTART;VALUE=DATE:20261020\nDTEND;VALUE=DATE:20261021",
+            "UID:bare\nDTSTART:20261020",
+            "UID:ok\nDTSTART;VALUE=DATE-TIME:20261020T010000Z",
+        ]);
+        let events = parse_events(&content).unwrap();
+
+        let holiday = events[0].as_ref().unwrap_err();
+        assert_eq!(holiday.summary, "휴무");
+        assert!(holiday.reason.contains("종일"));
+        assert!(events[1].as_ref().unwrap_err().reason.contains("종일"));
+        assert_eq!(events[2].as_ref().unwrap().start, utc("2026-10-20 01:00"));
+    }
+
+    #[test]
+    fn malformed_events_do_not_stop_the_import() {
+        let content = calendar(&[
+            "UID:no-start\nSUMMARY:시작 없음",
+            "UID:bad-time\nDTSTART:2026-10-20 10:00",
+            "UID:bad-end\nDTSTART:20261020T010000Z\nDTEND:tomorrow",
+            "UID:ok\nDTSTART:20261020T010000Z\nSTATUS:CANCELLED",
+        ]);
+        let events = parse_events(&content).unwrap();
+
+        assert_eq!(events.len(), 4);
+        assert!(events[0].as_ref().unwrap_err().reason.contains("DTSTART"));
+        assert!(events[1].as_ref().unwrap_err().reason.contains("해석"));
+        assert!(events[2].as_ref().unwrap_err().reason.contains("해석"));
+        let ok = events[3].as_ref().unwrap();
+        assert!(ok.cancelled);
+        assert_eq!(ok.duration_minutes, 60);
+    }
+
+    #[test]
+    fn non_calendar_content_is_rejected() {
+        assert!(parse_events("hello").is_err());
+    }
+}
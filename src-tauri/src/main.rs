@@ -2,7 +2,19 @@
 
 mod auth;
 mod database;
+mod discounts;
+mod duplicates;
+mod events;
+mod ical;
 mod models;
+mod notifications;
+mod payroll;
+mod phone;
+mod photos;
+mod receipt;
+mod server;
+mod stamps;
+mod tiers;
 
 use models::*;
 use tauri::Manager;
@@ -18,10 +30,40 @@ fn get_current_user(token: String) -> Result<UserInfo, String> {
     auth::get_current_user(&token)
 }
 
+#[tauri::command]
+fn get_users() -> Vec<UserInfo> {
+    auth::get_users()
+}
+
+#[tauri::command]
+fn create_user(request: CreateUserRequest) -> Result<UserInfo, String> {
+    auth::create_user(request)
+}
+
 // ==================== Member Commands ====================
 #[tauri::command]
-fn get_members(search: Option<String>) -> Vec<Member> {
-    database::get_members(search.as_deref())
+fn get_members(
+    search: Option<String>,
+    tag: Option<String>,
+    gender: Option<Gender>,
+    marketing_consent: Option<bool>,
+) -> Vec<Member> {
+    database::get_members(&MemberQuery {
+        search,
+        tag,
+        gender,
+        marketing_consent,
+    })
+}
+
+// For greeting messages; pass `consented_only` to leave out members who
+// have not agreed to marketing
+#[tauri::command]
+fn get_birthday_members(range: BirthdayRange, consented_only: Option<bool>) -> Vec<Member> {
+    database::get_birthday_members(range)
+        .into_iter()
+        .filter(|m| m.marketing_consent || consented_only != Some(true))
+        .collect()
 }
 
 #[tauri::command]
@@ -29,282 +71,2545 @@ fn get_member(id: String) -> Result<Member, String> {
     database::get_member_by_id(&id).ok_or("회원을 찾을 수 없습니다".to_string())
 }
 
+// Visit history and lifetime value for the member screen
+#[tauri::command]
+fn get_member_profile(id: String) -> Result<MemberProfile, String> {
+    let member = database::get_member_by_id(&id).ok_or("회원을 찾을 수 없습니다")?;
+    Ok(database::get_member_profile(member))
+}
+
 #[tauri::command]
 fn search_member_by_phone(phone: String) -> Result<Member, String> {
-    database::get_member_by_phone(&phone).ok_or("회원을 찾을 수 없습니다".to_string())
+    phone::normalize(&phone)
+        .ok()
+        .and_then(|phone| database::get_member_by_phone(&phone))
+        .ok_or("회원을 찾을 수 없습니다".to_string())
 }
 
 #[tauri::command]
 fn create_member(request: CreateMemberRequest) -> Result<Member, String> {
+    let phone = phone::normalize(&request.phone)?;
     // Check if phone already exists
-    if database::phone_exists(&request.phone, None) {
+    if database::phone_exists(&phone, None) {
         return Err("이미 등록된 전화번호입니다".to_string());
     }
 
-    database::create_member(&request.name, &request.phone)
+    let details = resolve_member_details(None, request.details)?;
+    database::create_member(&request.name, &phone, &details)
         .map_err(|e| format!("회원 생성 실패: {}", e))
 }
 
 #[tauri::command]
 fn update_member(id: String, request: UpdateMemberRequest) -> Result<Member, String> {
+    let phone = phone::normalize(&request.phone)?;
     // Check if phone already exists (excluding current member)
-    if database::phone_exists(&request.phone, Some(&id)) {
+    if database::phone_exists(&phone, Some(&id)) {
         return Err("이미 등록된 전화번호입니다".to_string());
     }
 
-    database::update_member(&id, &request.name, &request.phone)
+    let member = database::get_member_by_id(&id).ok_or("회원을 찾을 수 없습니다")?;
+    let details = resolve_member_details(Some(&member), request.details)?;
+    database::update_member(&id, &request.name, &phone, &details)
         .map_err(|e| format!("회원 수정 실패: {}", e))
 }
 
+// Rewrites stored member phones into E.164 and reports what could not be
 #[tauri::command]
-fn delete_member(id: String) -> Result<(), String> {
-    database::delete_member(&id).map_err(|e| format!("회원 삭제 실패: {}", e))
+fn normalize_member_phones() -> Result<PhoneNormalizationReport, String> {
+    let report =
+        database::normalize_member_phones().map_err(|e| format!("전화번호 정리 실패: {}", e))?;
+    database::save_phone_normalization_report(&report)
+        .map_err(|e| format!("전화번호 정리 결과 저장 실패: {}", e))?;
+    Ok(report)
 }
 
+// The last normalization run, including the one at startup
 #[tauri::command]
-fn add_stamp(id: String) -> Result<Member, String> {
-    database::add_stamp(&id).map_err(|e| format!("스탬프 추가 실패: {}", e))
+fn get_phone_normalization_report() -> PhoneNormalizationReport {
+    database::get_phone_normalization_report()
+}
+
+// Fills in what the request left out from the current member and checks
+// custom fields against the shop's definitions
+fn resolve_member_details(
+    member: Option<&Member>,
+    input: MemberDetailsInput,
+) -> Result<MemberDetails, String> {
+    let mut tags: Vec<String> = vec![];
+    for tag in input
+        .tags
+        .unwrap_or_else(|| member.map(|m| m.tags.clone()).unwrap_or_default())
+    {
+        let tag = tag.trim().to_string();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+
+    let custom_fields = match input.custom_fields {
+        Some(values) => {
+            let definitions = database::get_member_fields();
+            let mut checked = std::collections::BTreeMap::new();
+            for (key, value) in values {
+                let value = value.trim().to_string();
+                if value.is_empty() {
+                    continue;
+                }
+                let definition = definitions
+                    .iter()
+                    .find(|d| d.key == key)
+                    .ok_or(format!("'{}' 항목이 정의되어 있지 않습니다", key))?;
+                let valid = match definition.field_type {
+                    MemberFieldType::Text => true,
+                    MemberFieldType::Number => value.parse::<f64>().is_ok(),
+                    MemberFieldType::Date => value.parse::<chrono::NaiveDate>().is_ok(),
+                    MemberFieldType::Choice => definition.options.contains(&value),
+                };
+                if !valid {
+                    return Err(format!("'{}' 값이 올바르지 않습니다", definition.label));
+                }
+                checked.insert(key, value);
+            }
+            checked
+        }
+        None => member.map(|m| m.custom_fields.clone()).unwrap_or_default(),
+    };
+
+    Ok(MemberDetails {
+        birthday: input
+            .birthday
+            .unwrap_or_else(|| member.and_then(|m| m.birthday)),
+        gender: input
+            .gender
+            .unwrap_or_else(|| member.and_then(|m| m.gender)),
+        memo: input
+            .memo
+            .unwrap_or_else(|| member.and_then(|m| m.memo.clone()))
+            .filter(|m| !m.trim().is_empty()),
+        marketing_consent: input
+            .marketing_consent
+            .unwrap_or_else(|| member.is_some_and(|m| m.marketing_consent)),
+        tags,
+        custom_fields,
+    })
 }
 
+// Members that look like the same person; with `member_id`, only that
+// member's matches
 #[tauri::command]
-fn reset_stamps(id: String) -> Result<Member, String> {
-    database::reset_stamps(&id).map_err(|e| format!("스탬프 초기화 실패: {}", e))
+fn find_duplicate_members(member_id: Option<String>) -> Vec<DuplicateCandidate> {
+    let members = database::get_members(&MemberQuery::default());
+    duplicates::candidates(&members, member_id.as_deref())
 }
 
-// ==================== Staff Commands ====================
 #[tauri::command]
-fn get_staff_list() -> Vec<Staff> {
-    database::get_staff_list()
+fn merge_members(request: MergeMembersRequest) -> Result<MemberMerge, String> {
+    if request.survivor_id == request.duplicate_id {
+        return Err("같은 회원끼리는 병합할 수 없습니다".to_string());
+    }
+    let merger = auth::verify_approver(&request.merged_by)?;
+    let survivor =
+        database::get_member_by_id(&request.survivor_id).ok_or("회원을 찾을 수 없습니다")?;
+    let duplicate = database::get_member_by_id(&request.duplicate_id)
+        .ok_or("병합할 회원을 찾을 수 없습니다")?;
+
+    // The survivor's own details win; the duplicate fills the gaps
+    let mut tags = survivor.tags.clone();
+    for tag in &duplicate.tags {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+    let mut custom_fields = duplicate.custom_fields.clone();
+    custom_fields.extend(survivor.custom_fields.clone());
+    let memo = match (&survivor.memo, &duplicate.memo) {
+        (Some(a), Some(b)) if a != b => Some(format!("{}\n{}", a, b)),
+        (a, b) => a.clone().or(b.clone()),
+    };
+    let details = MemberDetails {
+        birthday: survivor.birthday.or(duplicate.birthday),
+        gender: survivor.gender.or(duplicate.gender),
+        memo,
+        marketing_consent: survivor.marketing_consent,
+        tags,
+        custom_fields,
+    };
+
+    let merge = database::merge_members(
+        &survivor,
+        &duplicate,
+        &details,
+        &merger.username,
+        request
+            .reason
+            .as_deref()
+            .map(str::trim)
+            .filter(|r| !r.is_empty()),
+    )
+    .map_err(write_error("회원 병합 실패"))?;
+
+    recalculate_tier(&survivor.id);
+    events::emit(ChangeEvent::MembersMerged {
+        merge: merge.clone(),
+    });
+    Ok(merge)
 }
 
 #[tauri::command]
-fn get_staff(id: String) -> Result<Staff, String> {
-    database::get_staff_by_id(&id).ok_or("직원을 찾을 수 없습니다".to_string())
+fn get_member_merges(member_id: String) -> Vec<MemberMerge> {
+    database::get_member_merges(&member_id)
 }
 
 #[tauri::command]
-fn create_staff(request: CreateStaffRequest) -> Result<Staff, String> {
-    database::create_staff(&request.name).map_err(|e| format!("직원 생성 실패: {}", e))
+fn get_member_fields() -> Vec<MemberFieldDefinition> {
+    database::get_member_fields()
 }
 
 #[tauri::command]
-fn update_staff(id: String, request: UpdateStaffRequest) -> Result<Staff, String> {
-    database::update_staff(&id, &request.name).map_err(|e| format!("직원 수정 실패: {}", e))
+fn update_member_fields(
+    fields: Vec<MemberFieldDefinition>,
+) -> Result<Vec<MemberFieldDefinition>, String> {
+    let mut keys = std::collections::HashSet::new();
+    for field in &fields {
+        if field.key.trim().is_empty() || field.label.trim().is_empty() {
+            return Err("항목 키와 이름을 입력해주세요".to_string());
+        }
+        if !keys.insert(field.key.as_str()) {
+            return Err(format!("'{}' 항목 키가 중복되었습니다", field.key));
+        }
+        if field.field_type == MemberFieldType::Choice && field.options.is_empty() {
+            return Err(format!("'{}' 항목의 선택지를 입력해주세요", field.label));
+        }
+    }
+    database::save_member_fields(&fields).map_err(|e| format!("회원 항목 저장 실패: {}", e))?;
+    Ok(fields)
 }
 
 #[tauri::command]
-fn delete_staff(id: String) -> Result<(), String> {
-    database::delete_staff(&id).map_err(|e| format!("직원 삭제 실패: {}", e))
+fn delete_member(id: String) -> Result<(), String> {
+    database::delete_member(&id).map_err(|e| format!("회원 삭제 실패: {}", e))
 }
 
-// ==================== Seat Commands ====================
 #[tauri::command]
-fn get_seats() -> Vec<Seat> {
-    database::get_seats()
+fn add_stamp(id: String, request: StampAdjustRequest) -> Result<Member, String> {
+    let count = request.count.unwrap_or(1);
+    if count <= 0 {
+        return Err("추가할 스탬프 수는 1개 이상이어야 합니다".to_string());
+    }
+    let actor = auth::verify_credentials(&request.actor)?;
+
+    let movement = StampMovement {
+        actor: Some(actor.username),
+        reason: request.reason,
+        ..Default::default()
+    };
+    database::record_stamp_transaction(&id, StampTransactionKind::ManualAdd, count, &movement)
+        .map_err(write_error("스탬프 추가 실패"))?;
+    database::get_member_by_id(&id).ok_or("회원을 찾을 수 없습니다".to_string())
 }
 
 #[tauri::command]
-fn get_seat(id: i32) -> Result<Seat, String> {
-    database::get_seat_by_id(id).ok_or("좌석을 찾을 수 없습니다".to_string())
+fn reset_stamps(id: String, request: StampAdjustRequest) -> Result<Member, String> {
+    let member = database::get_member_by_id(&id).ok_or("회원을 찾을 수 없습니다")?;
+    let actor = auth::verify_credentials(&request.actor)?;
+
+    let movement = StampMovement {
+        actor: Some(actor.username),
+        reason: request.reason,
+        ..Default::default()
+    };
+    database::record_stamp_transaction(&id, StampTransactionKind::Reset, -member.stamps, &movement)
+        .map_err(write_error("스탬프 초기화 실패"))?;
+    database::get_member_by_id(&id).ok_or("회원을 찾을 수 없습니다".to_string())
 }
 
 #[tauri::command]
-fn start_service(id: i32, request: StartServiceRequest) -> Result<Seat, String> {
-    // Check if seat is available
-    let seat = database::get_seat_by_id(id).ok_or("좌석을 찾을 수 없습니다")?;
+fn get_stamp_transactions(member_id: String) -> Result<Vec<StampTransaction>, String> {
+    database::get_member_by_id(&member_id).ok_or("회원을 찾을 수 없습니다")?;
+    stamps::expire();
+    Ok(database::get_stamp_transactions(&member_id))
+}
 
-    if seat.status != SeatStatus::Available && seat.status != SeatStatus::Reserved {
-        return Err("이미 사용 중인 좌석입니다".to_string());
+// Brings members.stamps back in line with the history and lists the fixes
+#[tauri::command]
+fn reconcile_stamps() -> Result<Vec<StampDiscrepancy>, String> {
+    database::reconcile_stamps().map_err(|e| format!("스탬프 대조 실패: {}", e))
+}
+
+// ==================== Prepaid Commands ====================
+#[tauri::command]
+fn get_prepaid_transactions(member_id: String) -> Result<Vec<PrepaidTransaction>, String> {
+    database::get_member_by_id(&member_id).ok_or("회원을 찾을 수 없습니다")?;
+    expire_prepaid_balances();
+    Ok(database::get_prepaid_transactions(&member_id))
+}
+
+#[tauri::command]
+fn top_up_prepaid(
+    member_id: String,
+    request: TopUpPrepaidRequest,
+) -> Result<PrepaidTransaction, String> {
+    database::get_member_by_id(&member_id).ok_or("회원을 찾을 수 없습니다")?;
+    if request.amount <= 0 {
+        return Err("충전 금액은 0원보다 커야 합니다".to_string());
     }
+    if matches!(
+        request.method,
+        PaymentMethod::Deposit | PaymentMethod::Prepaid | PaymentMethod::Points
+    ) {
+        return Err("충전 결제 수단을 선택해주세요".to_string());
+    }
+    let cashier = auth::verify_credentials(&request.created_by)?;
+    ensure_day_open(chrono::Utc::now(), request.owner_override.as_ref())?;
+    expire_prepaid_balances();
 
-    // Create service session
-    database::create_session(
-        id,
-        request.member_id.as_deref(),
-        &request.member_name,
-        &request.staff_id,
-        &request.staff_name,
-        &request.services,
-        request.total_price,
-        request.reservation_id.as_deref(),
-    )
-    .map_err(|e| format!("세션 생성 실패: {}", e))?;
+    let policy = database::get_prepaid_policy();
+    let bonus_amount = match request.bonus_amount {
+        Some(bonus) if bonus < 0 => return Err("보너스 금액은 0원 이상이어야 합니다".to_string()),
+        Some(bonus) => bonus,
+        None => policy
+            .bonus_tiers
+            .iter()
+            .filter(|t| t.min_amount <= request.amount)
+            .map(|t| t.bonus_amount)
+            .max()
+            .unwrap_or(0),
+    };
+    let expires_at = (policy.validity_days > 0)
+        .then(|| chrono::Utc::now() + chrono::Duration::days(i64::from(policy.validity_days)));
 
-    // Update seat status
-    database::update_seat_status(id, SeatStatus::InUse)
-        .map_err(|e| format!("좌석 상태 업데이트 실패: {}", e))?;
+    let movement = PrepaidMovement {
+        amount: request.amount + bonus_amount,
+        bonus_amount,
+        paid_amount: request.amount,
+        method: Some(request.method),
+        expires_at,
+        memo: request.memo.filter(|m| !m.trim().is_empty()),
+        created_by: Some(cashier.username),
+        ..Default::default()
+    };
+    let transaction =
+        database::record_prepaid_transaction(&member_id, PrepaidTransactionKind::TopUp, &movement)
+            .map_err(write_error("선불권 충전 실패"))?;
 
-    // Update reservation status if exists
-    if let Some(reservation_id) = &request.reservation_id {
-        let _ = database::update_reservation_status(reservation_id, ReservationStatus::InProgress);
+    events::emit(ChangeEvent::PrepaidBalanceChanged {
+        member_id,
+        transaction: transaction.clone(),
+    });
+    Ok(transaction)
+}
+
+// Pays back unused balance. Bonus credit is not paid out, so by default the
+// refund is the balance taken off less its proportional share of bonus.
+#[tauri::command]
+fn refund_prepaid(
+    member_id: String,
+    request: RefundPrepaidRequest,
+) -> Result<PrepaidTransaction, String> {
+    if request.reason.trim().is_empty() {
+        return Err("환불 사유를 입력해주세요".to_string());
+    }
+    let approver = auth::verify_approver(&request.approved_by)?;
+    if matches!(
+        request.method,
+        PaymentMethod::Deposit | PaymentMethod::Prepaid | PaymentMethod::Points
+    ) {
+        return Err("환불 수단을 선택해주세요".to_string());
     }
+    ensure_day_open(chrono::Utc::now(), request.owner_override.as_ref())?;
+    expire_prepaid_balances();
 
-    database::get_seat_by_id(id).ok_or("좌석을 찾을 수 없습니다".to_string())
+    let member = database::get_member_by_id(&member_id).ok_or("회원을 찾을 수 없습니다")?;
+    if member.prepaid_balance <= 0 {
+        return Err("환불할 선불권 잔액이 없습니다".to_string());
+    }
+    let amount = request.amount.unwrap_or(member.prepaid_balance);
+    if amount <= 0 || amount > member.prepaid_balance {
+        return Err(format!(
+            "환불 금액은 1원 이상 {}원 이하여야 합니다",
+            member.prepaid_balance
+        ));
+    }
+
+    let refund_amount = match request.refund_amount {
+        Some(refund) if refund < 0 || refund > amount => {
+            return Err(format!("반환 금액은 0원 이상 {}원 이하여야 합니다", amount))
+        }
+        Some(refund) => refund,
+        None => {
+            let top_ups: Vec<PrepaidTransaction> = database::get_prepaid_transactions(&member_id)
+                .into_iter()
+                .filter(|t| t.kind == PrepaidTransactionKind::TopUp)
+                .collect();
+            let paid: i64 = top_ups.iter().map(|t| i64::from(t.paid_amount)).sum();
+            let credited: i64 = top_ups.iter().map(|t| i64::from(t.amount)).sum();
+            if credited == 0 {
+                amount
+            } else {
+                (i64::from(amount) * paid / credited) as i32
+            }
+        }
+    };
+
+    let movement = PrepaidMovement {
+        amount: -amount,
+        paid_amount: -refund_amount,
+        method: Some(request.method),
+        memo: Some(request.reason.trim().to_string()),
+        created_by: Some(approver.username),
+        ..Default::default()
+    };
+    let transaction =
+        database::record_prepaid_transaction(&member_id, PrepaidTransactionKind::Refund, &movement)
+            .map_err(write_error("선불권 환불 실패"))?;
+
+    events::emit(ChangeEvent::PrepaidBalanceChanged {
+        member_id,
+        transaction: transaction.clone(),
+    });
+    Ok(transaction)
 }
 
 #[tauri::command]
-fn complete_service(id: i32) -> Result<LedgerEntry, String> {
-    // Get current session
-    let session = database::get_session_by_seat_id(id).ok_or("진행 중인 서비스가 없습니다")?;
-
-    // Create ledger entry
-    let ledger_entry =
-        database::create_ledger_entry(&session).map_err(|e| format!("매출 기록 실패: {}", e))?;
+fn get_prepaid_policy() -> PrepaidPolicy {
+    database::get_prepaid_policy()
+}
 
-    // Add stamp if member exists
-    if let Some(member_id) = &session.member_id {
-        let _ = database::add_stamp(member_id);
+#[tauri::command]
+fn update_prepaid_policy(policy: PrepaidPolicy) -> Result<PrepaidPolicy, String> {
+    if policy.validity_days < 0 {
+        return Err("유효기간은 0일 이상이어야 합니다".to_string());
+    }
+    if policy
+        .bonus_tiers
+        .iter()
+        .any(|t| t.min_amount <= 0 || t.bonus_amount < 0)
+    {
+        return Err("보너스 기준 금액과 보너스 금액을 확인해주세요".to_string());
     }
+    database::save_prepaid_policy(&policy).map_err(|e| format!("선불권 설정 저장 실패: {}", e))?;
+    Ok(policy)
+}
 
-    // Update reservation status if exists
-    if let Some(reservation_id) = &session.reservation_id {
-        let _ = database::update_reservation_status(reservation_id, ReservationStatus::Completed);
+fn expire_prepaid_balances() {
+    for transaction in database::expire_prepaid_balances() {
+        events::emit(ChangeEvent::PrepaidBalanceChanged {
+            member_id: transaction.member_id.clone(),
+            transaction,
+        });
     }
+}
 
-    // Delete session and update seat status
-    database::delete_session(id).map_err(|e| format!("세션 삭제 실패: {}", e))?;
-    database::update_seat_status(id, SeatStatus::Available)
-        .map_err(|e| format!("좌석 상태 업데이트 실패: {}", e))?;
+// ==================== Point Commands ====================
+#[tauri::command]
+fn get_point_transactions(member_id: String) -> Result<Vec<PointTransaction>, String> {
+    database::get_member_by_id(&member_id).ok_or("회원을 찾을 수 없습니다")?;
+    Ok(database::get_point_transactions(&member_id))
+}
 
-    Ok(ledger_entry)
+#[tauri::command]
+fn adjust_points(
+    member_id: String,
+    request: AdjustPointsRequest,
+) -> Result<PointTransaction, String> {
+    database::get_member_by_id(&member_id).ok_or("회원을 찾을 수 없습니다")?;
+    if request.amount == 0 {
+        return Err("조정할 포인트를 입력해주세요".to_string());
+    }
+    if request.reason.trim().is_empty() {
+        return Err("조정 사유를 입력해주세요".to_string());
+    }
+    let actor = auth::verify_approver(&request.actor)?;
+
+    let movement = PointMovement {
+        actor: Some(actor.username),
+        reason: Some(request.reason.trim().to_string()),
+        ..Default::default()
+    };
+    let transaction = database::record_point_transaction(
+        &member_id,
+        PointTransactionKind::Adjust,
+        request.amount,
+        &movement,
+    )
+    .map_err(write_error("포인트 조정 실패"))?;
+    events::emit(ChangeEvent::PointsChanged {
+        member_id,
+        transaction: transaction.clone(),
+    });
+    Ok(transaction)
 }
 
 #[tauri::command]
-fn cancel_service(id: i32) -> Result<(), String> {
-    // Get current session
-    let session = database::get_session_by_seat_id(id).ok_or("진행 중인 서비스가 없습니다")?;
+fn get_point_policy() -> PointPolicy {
+    database::get_point_policy()
+}
 
-    // Update reservation status back to scheduled if exists
-    if let Some(reservation_id) = &session.reservation_id {
-        let _ = database::update_reservation_status(reservation_id, ReservationStatus::Scheduled);
+#[tauri::command]
+fn update_point_policy(policy: PointPolicy) -> Result<PointPolicy, String> {
+    if !(0.0..=100.0).contains(&policy.earn_percent) {
+        return Err("적립률은 0%에서 100% 사이여야 합니다".to_string());
     }
+    if policy.min_redeem < 0 {
+        return Err("최소 사용 포인트는 0 이상이어야 합니다".to_string());
+    }
+    database::save_point_policy(&policy).map_err(|e| format!("포인트 설정 저장 실패: {}", e))?;
+    Ok(policy)
+}
 
-    // Delete session and update seat status
-    database::delete_session(id).map_err(|e| format!("세션 삭제 실패: {}", e))?;
-    database::update_seat_status(id, SeatStatus::Available)
-        .map_err(|e| format!("좌석 상태 업데이트 실패: {}", e))?;
+// ==================== Treatment Note Commands ====================
+#[tauri::command]
+fn get_treatment_notes(member_id: String) -> Result<Vec<TreatmentNote>, String> {
+    database::get_member_by_id(&member_id).ok_or("회원을 찾을 수 없습니다")?;
+    Ok(database::get_treatment_notes(&member_id))
+}
 
-    Ok(())
+// What to reuse when starting the member's next color or perm
+#[tauri::command]
+fn get_latest_formula(member_id: String) -> Result<Option<TreatmentNote>, String> {
+    database::get_member_by_id(&member_id).ok_or("회원을 찾을 수 없습니다")?;
+    Ok(database::get_latest_formula(&member_id))
 }
 
-// ==================== Reservation Commands ====================
 #[tauri::command]
-fn get_reservations(
-    status: Option<String>,
-    date: Option<String>,
-    all: Option<String>,
-) -> Vec<Reservation> {
-    let query = ReservationQuery { status, date, all };
-    database::get_reservations(&query)
+fn create_treatment_note(request: TreatmentNoteRequest) -> Result<TreatmentNote, String> {
+    let staff = validate_treatment_note(&request)?;
+    let note = database::create_treatment_note(&request, staff.as_ref())
+        .map_err(|e| format!("시술 기록 저장 실패: {}", e))?;
+    events::emit(ChangeEvent::TreatmentNoteChanged { note: note.clone() });
+    Ok(note)
 }
 
 #[tauri::command]
-fn get_reservation(id: String) -> Result<Reservation, String> {
-    database::get_reservation_by_id(&id).ok_or("예약을 찾을 수 없습니다".to_string())
+fn update_treatment_note(
+    id: String,
+    request: TreatmentNoteRequest,
+) -> Result<TreatmentNote, String> {
+    let existing = database::get_treatment_note_by_id(&id).ok_or("시술 기록을 찾을 수 없습니다")?;
+    if existing.member_id != request.member_id {
+        return Err("다른 회원의 시술 기록으로 옮길 수 없습니다".to_string());
+    }
+    let staff = validate_treatment_note(&request)?;
+    let note = database::update_treatment_note(&id, &request, staff.as_ref())
+        .map_err(|e| format!("시술 기록 수정 실패: {}", e))?;
+    events::emit(ChangeEvent::TreatmentNoteChanged { note: note.clone() });
+    Ok(note)
 }
 
 #[tauri::command]
-fn create_reservation(request: CreateReservationRequest) -> Result<Reservation, String> {
-    database::create_reservation(&request).map_err(|e| format!("예약 생성 실패: {}", e))
+fn delete_treatment_note(id: String) -> Result<(), String> {
+    database::delete_treatment_note(&id).map_err(|e| format!("시술 기록 삭제 실패: {}", e))
+}
+
+// Checks the visit the note is linked to belongs to the member and resolves
+// the designer
+fn validate_treatment_note(request: &TreatmentNoteRequest) -> Result<Option<Staff>, String> {
+    database::get_member_by_id(&request.member_id).ok_or("회원을 찾을 수 없습니다")?;
+    if let Some(ledger_entry_id) = &request.ledger_entry_id {
+        let entry = database::get_ledger_entry_by_id(ledger_entry_id)
+            .ok_or("매출 내역을 찾을 수 없습니다")?;
+        if entry.member_id.as_ref() != Some(&request.member_id) {
+            return Err("해당 회원의 매출이 아닙니다".to_string());
+        }
+    }
+    if let Some(session_id) = &request.session_id {
+        let member_id =
+            database::get_session_member_id(session_id).ok_or("진행 중인 서비스가 없습니다")?;
+        if member_id.as_ref() != Some(&request.member_id) {
+            return Err("해당 회원의 서비스가 아닙니다".to_string());
+        }
+    }
+    if request
+        .color_formula
+        .as_ref()
+        .and_then(|f| f.processing_minutes)
+        .is_some_and(|m| m < 0)
+    {
+        return Err("방치 시간은 0분 이상이어야 합니다".to_string());
+    }
+
+    request
+        .staff_id
+        .as_deref()
+        .map(|id| database::get_staff_by_id(id).ok_or("디자이너를 찾을 수 없습니다".to_string()))
+        .transpose()
 }
 
+// ==================== Member Photo Commands ====================
 #[tauri::command]
-fn update_reservation(
-    id: String,
-    request: UpdateReservationRequest,
-) -> Result<Reservation, String> {
-    database::update_reservation(&id, &request).map_err(|e| format!("예약 수정 실패: {}", e))
+fn get_member_photos(member_id: String) -> Result<Vec<MemberPhoto>, String> {
+    database::get_member_by_id(&member_id).ok_or("회원을 찾을 수 없습니다")?;
+    Ok(database::get_member_photos(&member_id))
 }
 
 #[tauri::command]
-fn update_reservation_status(
-    id: String,
-    request: UpdateReservationStatusRequest,
-) -> Result<Reservation, String> {
-    database::update_reservation_status(&id, request.status)
-        .map_err(|e| format!("예약 상태 업데이트 실패: {}", e))
+fn attach_member_photo(request: AttachPhotoRequest) -> Result<MemberPhoto, String> {
+    database::get_member_by_id(&request.member_id).ok_or("회원을 찾을 수 없습니다")?;
+    // A photo on a visit is dated to that visit
+    let taken_at = match &request.ledger_entry_id {
+        Some(ledger_entry_id) => {
+            let entry = database::get_ledger_entry_by_id(ledger_entry_id)
+                .ok_or("매출 내역을 찾을 수 없습니다")?;
+            if entry.member_id.as_ref() != Some(&request.member_id) {
+                return Err("해당 회원의 매출이 아닙니다".to_string());
+            }
+            entry.completed_at
+        }
+        None => chrono::Utc::now(),
+    };
+
+    let bytes =
+        std::fs::read(&request.source_path).map_err(|e| format!("사진을 열 수 없습니다: {}", e))?;
+    let stored = photos::store(&bytes)?;
+    let photo = database::create_member_photo(&request, &stored, taken_at)
+        .map_err(|e| format!("사진 등록 실패: {}", e))?;
+    events::emit(ChangeEvent::MemberPhotoAdded {
+        photo: photo.clone(),
+    });
+    Ok(photo)
 }
 
 #[tauri::command]
-fn delete_reservation(id: String) -> Result<(), String> {
-    database::delete_reservation(&id).map_err(|e| format!("예약 삭제 실패: {}", e))
+fn delete_member_photo(id: String) -> Result<(), String> {
+    database::delete_member_photo(&id).map_err(|e| format!("사진 삭제 실패: {}", e))
 }
 
-// ==================== Backup Commands ====================
+// ==================== Membership Tier Commands ====================
 #[tauri::command]
-fn backup_database(path: String) -> Result<(), String> {
-    database::backup_database(&path)
+fn get_membership_tiers() -> Vec<MembershipTier> {
+    database::get_membership_tiers()
 }
 
 #[tauri::command]
-fn restore_database(path: String) -> Result<(), String> {
-    database::restore_database(&path)
+fn create_membership_tier(request: MembershipTierRequest) -> Result<MembershipTier, String> {
+    validate_membership_tier(&request)?;
+    let tier = database::create_membership_tier(&request)
+        .map_err(|e| format!("회원 등급 생성 실패: {}", e))?;
+    recalculate_member_tiers();
+    Ok(tier)
 }
 
 #[tauri::command]
-fn get_db_path() -> Result<String, String> {
-    database::get_db_path()
-        .map(|p| p.to_string_lossy().to_string())
-        .ok_or("DB 경로를 찾을 수 없습니다".to_string())
+fn update_membership_tier(
+    id: String,
+    request: MembershipTierRequest,
+) -> Result<MembershipTier, String> {
+    validate_membership_tier(&request)?;
+    let tier = database::update_membership_tier(&id, &request)
+        .map_err(|e| format!("회원 등급 수정 실패: {}", e))?;
+    recalculate_member_tiers();
+    Ok(tier)
 }
 
-// ==================== Ledger Commands ====================
 #[tauri::command]
-fn get_ledger_entries(
-    date: Option<String>,
-    start_date: Option<String>,
-    end_date: Option<String>,
-    staff_id: Option<String>,
-) -> Vec<LedgerEntry> {
-    let query = LedgerQuery {
-        date,
-        start_date,
-        end_date,
-        staff_id,
-    };
-    database::get_ledger_entries(&query)
+fn delete_membership_tier(id: String) -> Result<(), String> {
+    database::delete_membership_tier(&id).map_err(|e| format!("회원 등급 삭제 실패: {}", e))?;
+    recalculate_member_tiers();
+    Ok(())
+}
+
+fn validate_membership_tier(request: &MembershipTierRequest) -> Result<(), String> {
+    if request.name.trim().is_empty() {
+        return Err("등급 이름을 입력해주세요".to_string());
+    }
+    if request.min_spend < 0 || request.min_visits < 0 {
+        return Err("등급 기준은 0 이상이어야 합니다".to_string());
+    }
+    if !(0..=100).contains(&request.discount_percent) {
+        return Err("등급 할인율은 0~100 사이여야 합니다".to_string());
+    }
+    Ok(())
 }
 
+// Re-judges every member on the last 12 months and lists who moved
 #[tauri::command]
-fn get_ledger_summary(
-    date: Option<String>,
-    start_date: Option<String>,
-    end_date: Option<String>,
-) -> LedgerSummary {
-    let query = LedgerQuery {
-        date,
+fn recalculate_member_tiers() -> Vec<TierChange> {
+    let changes = tiers::recalculate_all();
+    for change in &changes {
+        events::emit(ChangeEvent::MemberTierChanged {
+            change: change.clone(),
+        });
+    }
+    changes
+}
+
+fn recalculate_tier(member_id: &str) {
+    if let Some(change) = tiers::recalculate(member_id) {
+        events::emit(ChangeEvent::MemberTierChanged { change });
+    }
+}
+
+// Defaults to the current month
+#[tauri::command]
+fn get_tier_movements(start_date: Option<String>, end_date: Option<String>) -> TierMovementReport {
+    let today = chrono::Local::now().date_naive();
+    let start_date = start_date.unwrap_or_else(|| today.format("%Y-%m-01").to_string());
+    let end_date = end_date.unwrap_or_else(|| today.format("%Y-%m-%d").to_string());
+    let changes = database::get_tier_changes(&start_date, &end_date);
+    let upgrades = changes.iter().filter(|c| c.upgrade).count() as i32;
+
+    TierMovementReport {
         start_date,
         end_date,
-        staff_id: None,
-    };
-    database::get_ledger_summary(&query)
+        upgrades,
+        downgrades: changes.len() as i32 - upgrades,
+        changes,
+    }
 }
 
+// ==================== Stamp Program Commands ====================
 #[tauri::command]
-fn get_daily_summary(year: Option<i32>, month: Option<u32>) -> Vec<DailySummary> {
-    let now = chrono::Utc::now();
-    let y = year.unwrap_or(now.format("%Y").to_string().parse().unwrap_or(2025));
-    let m = month.unwrap_or(now.format("%m").to_string().parse().unwrap_or(1));
-    database::get_daily_summary(y, m)
+fn get_stamp_programs() -> Vec<StampProgram> {
+    database::get_stamp_programs()
 }
 
-fn main() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_shell::init())
-        .plugin(tauri_plugin_updater::Builder::new().build())
-        .plugin(tauri_plugin_dialog::init())
-        .setup(|app| {
-            // Initialize database
-            let app_data_dir = app
-                .path()
-                .app_data_dir()
-                .expect("Failed to get app data dir");
+#[tauri::command]
+fn create_stamp_program(request: StampProgramRequest) -> Result<StampProgram, String> {
+    validate_stamp_program(&request)?;
+    database::create_stamp_program(&request)
+        .map_err(|e| format!("스탬프 프로그램 생성 실패: {}", e))
+}
 
-            println!("App Data Dir: {:?}", app_data_dir);
+#[tauri::command]
+fn update_stamp_program(id: String, request: StampProgramRequest) -> Result<StampProgram, String> {
+    validate_stamp_program(&request)?;
+    database::update_stamp_program(&id, &request)
+        .map_err(|e| format!("스탬프 프로그램 수정 실패: {}", e))
+}
 
-            database::init_database(app_data_dir).expect("Failed to initialize database");
+#[tauri::command]
+fn delete_stamp_program(id: String) -> Result<(), String> {
+    database::delete_stamp_program(&id).map_err(|e| format!("스탬프 프로그램 삭제 실패: {}", e))
+}
 
-            println!("Database initialized successfully");
+fn validate_stamp_program(request: &StampProgramRequest) -> Result<(), String> {
+    if request.name.trim().is_empty() {
+        return Err("프로그램 이름을 입력해주세요".to_string());
+    }
+    if request.stamps_required <= 0 {
+        return Err("필요한 스탬프 수는 1개 이상이어야 합니다".to_string());
+    }
+    match request.reward_kind {
+        StampRewardKind::FreeService
+            if request
+                .reward_service_name
+                .as_deref()
+                .is_none_or(|s| s.trim().is_empty()) =>
+        {
+            return Err("무료로 제공할 시술을 입력해주세요".to_string());
+        }
+        StampRewardKind::Percentage => {
+            discounts::validate_value(DiscountKind::Percentage, request.reward_value)?
+        }
+        StampRewardKind::FixedAmount => {
+            discounts::validate_value(DiscountKind::FixedAmount, request.reward_value)?
+        }
+        _ => {}
+    }
+    if request.amount_per_stamp.is_some_and(|a| a <= 0) {
+        return Err("스탬프 적립 기준 금액은 0원보다 커야 합니다".to_string());
+    }
+    if request.expiry_days < 0 {
+        return Err("유효기간은 0일 이상이어야 합니다".to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn get_stamp_status(member_id: String) -> Result<StampStatus, String> {
+    stamps::expire();
+    let member = database::get_member_by_id(&member_id).ok_or("회원을 찾을 수 없습니다")?;
+    let program = database::get_active_stamp_program();
+
+    Ok(StampStatus {
+        member_id: member.id.clone(),
+        stamps: member.stamps,
+        reward_available: program
+            .as_ref()
+            .is_some_and(|p| member.stamps >= p.stamps_required),
+        expires_at: stamps::expires_at(&member, program.as_ref()),
+        program,
+    })
+}
+
+// ==================== Package Commands ====================
+#[tauri::command]
+fn get_packages() -> Vec<Package> {
+    database::get_packages()
+}
+
+#[tauri::command]
+fn create_package(request: PackageRequest) -> Result<Package, String> {
+    validate_package(&request)?;
+    database::create_package(&request).map_err(|e| format!("회수권 생성 실패: {}", e))
+}
+
+#[tauri::command]
+fn update_package(id: String, request: PackageRequest) -> Result<Package, String> {
+    validate_package(&request)?;
+    database::update_package(&id, &request).map_err(|e| format!("회수권 수정 실패: {}", e))
+}
+
+#[tauri::command]
+fn delete_package(id: String) -> Result<(), String> {
+    database::delete_package(&id).map_err(|e| format!("회수권 삭제 실패: {}", e))
+}
+
+fn validate_package(request: &PackageRequest) -> Result<(), String> {
+    if request.name.trim().is_empty() {
+        return Err("회수권 이름을 입력해주세요".to_string());
+    }
+    if request.service_name.trim().is_empty() {
+        return Err("대상 시술을 입력해주세요".to_string());
+    }
+    if request.total_count <= 0 {
+        return Err("이용 횟수는 1회 이상이어야 합니다".to_string());
+    }
+    if request.price < 0 {
+        return Err("회수권 금액은 0원 이상이어야 합니다".to_string());
+    }
+    if request.validity_days < 0 {
+        return Err("유효기간은 0일 이상이어야 합니다".to_string());
+    }
+    Ok(())
+}
+
+// All passes the member has bought, including used up and expired ones
+#[tauri::command]
+fn get_member_packages(member_id: String) -> Result<Vec<MemberPackage>, String> {
+    database::get_member_by_id(&member_id).ok_or("회원을 찾을 수 없습니다")?;
+    Ok(database::get_member_packages(&member_id, false))
+}
+
+#[tauri::command]
+fn purchase_package(
+    member_id: String,
+    request: PurchasePackageRequest,
+) -> Result<MemberPackage, String> {
+    let member = database::get_member_by_id(&member_id).ok_or("회원을 찾을 수 없습니다")?;
+    let package = database::get_package_by_id(&request.package_id)
+        .filter(|p| p.active)
+        .ok_or("판매 중인 회수권을 찾을 수 없습니다")?;
+    if matches!(
+        request.method,
+        PaymentMethod::Deposit | PaymentMethod::Points
+    ) {
+        return Err("회수권 결제 수단을 선택해주세요".to_string());
+    }
+    let cashier = auth::verify_credentials(&request.created_by)?;
+    ensure_day_open(chrono::Utc::now(), request.owner_override.as_ref())?;
+
+    // A pass can be bought from the prepaid balance
+    let payment = PaymentInput {
+        method: request.method,
+        amount: package.price,
+        approval_number: request.approval_number.clone(),
+    };
+    if payment.method == PaymentMethod::Prepaid {
+        ensure_prepaid_balance(Some(&member.id), payment.amount)?;
+    }
+
+    let completed = database::complete_package_sale(&member, &package, &payment, &cashier.username)
+        .map_err(write_error("회수권 구매 실패"))?;
+    let member_package = completed.member_package;
+
+    if let Some(transaction) = completed.prepaid {
+        events::emit(ChangeEvent::PrepaidBalanceChanged {
+            member_id: member_id.clone(),
+            transaction,
+        });
+    }
+    events::emit(ChangeEvent::MemberPackageChanged {
+        member_package: member_package.clone(),
+    });
+    recalculate_tier(&member_id);
+    Ok(member_package)
+}
+
+#[tauri::command]
+fn get_package_usages(member_package_id: String) -> Result<Vec<PackageUsage>, String> {
+    database::get_member_package_by_id(&member_package_id).ok_or("회수권을 찾을 수 없습니다")?;
+    Ok(database::get_package_usages(&member_package_id))
+}
+
+// ==================== Staff Commands ====================
+#[tauri::command]
+fn get_staff_list() -> Vec<Staff> {
+    database::get_staff_list()
+}
+
+#[tauri::command]
+fn get_staff(id: String) -> Result<Staff, String> {
+    database::get_staff_by_id(&id).ok_or("직원을 찾을 수 없습니다".to_string())
+}
+
+#[tauri::command]
+fn create_staff(request: CreateStaffRequest) -> Result<Staff, String> {
+    database::create_staff(&request.name).map_err(|e| format!("직원 생성 실패: {}", e))
+}
+
+#[tauri::command]
+fn update_staff(id: String, request: UpdateStaffRequest) -> Result<Staff, String> {
+    database::update_staff(&id, &request.name).map_err(|e| format!("직원 수정 실패: {}", e))
+}
+
+#[tauri::command]
+fn delete_staff(id: String) -> Result<(), String> {
+    database::delete_staff(&id).map_err(|e| format!("직원 삭제 실패: {}", e))
+}
+
+// ==================== Seat Commands ====================
+#[tauri::command]
+fn get_seats() -> Vec<Seat> {
+    database::get_seats()
+}
+
+#[tauri::command]
+fn get_seat(id: i32) -> Result<Seat, String> {
+    database::get_seat_by_id(id).ok_or("좌석을 찾을 수 없습니다".to_string())
+}
+
+#[tauri::command]
+fn start_service(id: i32, mut request: StartServiceRequest) -> Result<Seat, String> {
+    // Check if seat is available
+    let seat = database::get_seat_by_id(id).ok_or("좌석을 찾을 수 없습니다")?;
+
+    if seat.status != SeatStatus::Available && seat.status != SeatStatus::Reserved {
+        return Err("이미 사용 중인 좌석입니다".to_string());
+    }
+    resolve_service_staff(&mut request.services)?;
+
+    // Create service session
+    database::create_session(
+        id,
+        request.member_id.as_deref(),
+        &request.member_name,
+        &request.staff_id,
+        &request.staff_name,
+        &request.services,
+        request.total_price,
+        request.reservation_id.as_deref(),
+    )
+    .map_err(|e| format!("세션 생성 실패: {}", e))?;
+
+    // Update seat status
+    database::update_seat_status(id, SeatStatus::InUse)
+        .map_err(|e| format!("좌석 상태 업데이트 실패: {}", e))?;
+
+    // Update reservation status if exists
+    if let Some(reservation_id) = &request.reservation_id {
+        if let Ok(reservation) =
+            database::update_reservation_status(reservation_id, ReservationStatus::InProgress)
+        {
+            events::emit(ChangeEvent::ReservationUpdated { reservation });
+        }
+    }
+
+    let seat = database::get_seat_by_id(id).ok_or("좌석을 찾을 수 없습니다")?;
+
+    events::emit(ChangeEvent::SessionStarted { seat: seat.clone() });
+    events::emit(ChangeEvent::SeatStatusChanged {
+        seat_id: id,
+        status: seat.status,
+    });
+
+    Ok(seat)
+}
+
+#[tauri::command]
+fn complete_service(
+    id: i32,
+    request: Option<CompleteServiceRequest>,
+) -> Result<LedgerEntry, String> {
+    // Get current session
+    let session = database::get_session_by_seat_id(id).ok_or("진행 중인 서비스가 없습니다")?;
+
+    // Everything is checked before anything is written
+    let request = request.unwrap_or_default();
+    ensure_day_open(chrono::Utc::now(), request.owner_override.as_ref())?;
+    let checkout = build_checkout(&session, &request)?;
+    validate_payments(&request.payments, checkout.amount_due)?;
+    let member_id = session.member_id.as_deref();
+    ensure_prepaid_balance(
+        member_id,
+        paid_with(&request.payments, PaymentMethod::Prepaid),
+    )?;
+    let points_used = paid_with(&request.payments, PaymentMethod::Points);
+    ensure_points_balance(member_id, points_used)?;
+    if let Some(tip) = &request.tip {
+        validate_tip(tip)?;
+    }
+
+    let policy = database::get_point_policy();
+    let postings = CheckoutPostings {
+        tip: request.tip.clone(),
+        points_earned: match member_id {
+            Some(_) if policy.enabled => points_earned(&policy, checkout.net_amount - points_used),
+            _ => 0,
+        },
+        stamp_program: database::get_active_stamp_program(),
+        redeemed_programs: checkout
+            .discounts
+            .iter()
+            .filter(|d| d.source == DiscountSource::Stamp)
+            .filter_map(|d| d.reference_id.as_deref())
+            .filter_map(database::get_stamp_program_by_id)
+            .collect(),
+    };
+
+    let completed = database::complete_checkout(&session, &checkout, &postings)
+        .map_err(write_error("매출 기록 실패"))?;
+    let ledger_entry = completed.ledger_entry;
+
+    if let Ok(notes) = database::link_treatment_notes(&session.id, &ledger_entry.id) {
+        for note in notes {
+            events::emit(ChangeEvent::TreatmentNoteChanged { note });
+        }
+    }
+    for member_package_id in &completed.member_package_ids {
+        if let Some(member_package) = database::get_member_package_by_id(member_package_id) {
+            events::emit(ChangeEvent::MemberPackageChanged { member_package });
+        }
+    }
+
+    if let Some(member_id) = member_id {
+        if let Some(transaction) = completed.prepaid {
+            events::emit(ChangeEvent::PrepaidBalanceChanged {
+                member_id: member_id.to_string(),
+                transaction,
+            });
+        }
+        for transaction in completed.points {
+            events::emit(ChangeEvent::PointsChanged {
+                member_id: member_id.to_string(),
+                transaction,
+            });
+        }
+        if let Some(transaction) = completed.stamps_earned {
+            if let Some(member) = database::get_member_by_id(member_id) {
+                notifications::enqueue_stamp_reward_ready(
+                    &member,
+                    transaction.balance_after - transaction.amount,
+                );
+            }
+        }
+        recalculate_tier(member_id);
+    }
+
+    if let Some(reservation) = session
+        .reservation_id
+        .as_deref()
+        .and_then(database::get_reservation_by_id)
+    {
+        events::emit(ChangeEvent::ReservationUpdated { reservation });
+    }
+
+    events::emit(ChangeEvent::SessionCompleted {
+        seat_id: id,
+        ledger_entry: ledger_entry.clone(),
+    });
+    events::emit(ChangeEvent::SeatStatusChanged {
+        seat_id: id,
+        status: SeatStatus::Available,
+    });
+
+    Ok(ledger_entry)
+}
+
+// Passes on the message of a check that failed inside a write transaction;
+// other database errors get `context` in front
+fn write_error(context: &'static str) -> impl Fn(database::WriteError) -> String {
+    move |e| match e {
+        database::WriteError::Conflict(message) => message,
+        e => format!("{}: {}", context, e),
+    }
+}
+
+// Shows the bill for an in-progress session without completing it
+#[tauri::command]
+fn preview_checkout(id: i32, request: Option<CompleteServiceRequest>) -> Result<Checkout, String> {
+    let session = database::get_session_by_seat_id(id).ok_or("진행 중인 서비스가 없습니다")?;
+    let mut request = request.unwrap_or_default();
+    // Payments are not known yet when previewing
+    request.payments.clear();
+    build_checkout(&session, &request)
+}
+
+fn build_checkout(
+    session: &ServiceSession,
+    request: &CompleteServiceRequest,
+) -> Result<Checkout, String> {
+    let redemptions = discounts::package_redemptions(session, request);
+    let discounts = discounts::compute(session, request, &redemptions)?;
+    let gross_amount = session.total_price - redemptions.iter().map(|r| r.amount).sum::<i32>();
+    let discount_amount: i32 = discounts.iter().map(|d| d.amount).sum();
+    let net_amount = gross_amount - discount_amount;
+
+    // Apply a paid reservation deposit to the final bill
+    let deposit_applied = session
+        .reservation_id
+        .as_deref()
+        .and_then(database::get_reservation_by_id)
+        .filter(|r| r.deposit_status == DepositStatus::Paid)
+        .map(|r| r.deposit_amount.min(net_amount))
+        .unwrap_or(0);
+    let amount_due = net_amount - deposit_applied;
+
+    // The payment breakdown covers whatever the deposit did not
+    let mut payments = request.payments.clone();
+    if deposit_applied > 0 {
+        payments.push(PaymentInput {
+            method: PaymentMethod::Deposit,
+            amount: deposit_applied,
+            approval_number: None,
+        });
+    }
+
+    Ok(Checkout {
+        redemptions,
+        gross_amount,
+        discounts,
+        discount_amount,
+        net_amount,
+        deposit_applied,
+        amount_due,
+        payments,
+    })
+}
+
+fn paid_with(payments: &[PaymentInput], method: PaymentMethod) -> i32 {
+    payments
+        .iter()
+        .filter(|p| p.method == method)
+        .map(|p| p.amount)
+        .sum()
+}
+
+// Paying from a prepaid balance needs a member with enough unexpired credit
+fn ensure_prepaid_balance(member_id: Option<&str>, amount: i32) -> Result<(), String> {
+    if amount <= 0 {
+        return Ok(());
+    }
+    let member_id = member_id.ok_or("선불권 결제는 회원만 사용할 수 있습니다")?;
+    expire_prepaid_balances();
+    let member = database::get_member_by_id(member_id).ok_or("회원을 찾을 수 없습니다")?;
+    if member.prepaid_balance < amount {
+        return Err(format!(
+            "선불권 잔액({}원)이 부족합니다",
+            member.prepaid_balance
+        ));
+    }
+    Ok(())
+}
+
+// Points need a member holding at least the amount, spent in policy-sized steps
+fn ensure_points_balance(member_id: Option<&str>, amount: i32) -> Result<(), String> {
+    if amount <= 0 {
+        return Ok(());
+    }
+    let member_id = member_id.ok_or("포인트 결제는 회원만 사용할 수 있습니다")?;
+    let member = database::get_member_by_id(member_id).ok_or("회원을 찾을 수 없습니다")?;
+    if member.points < amount {
+        return Err(format!("포인트 잔액({}P)이 부족합니다", member.points));
+    }
+    let policy = database::get_point_policy();
+    if amount < policy.min_redeem {
+        return Err(format!(
+            "포인트는 {}P 이상부터 사용할 수 있습니다",
+            policy.min_redeem
+        ));
+    }
+    Ok(())
+}
+
+// Earned points are rounded down to whole points
+fn points_earned(policy: &PointPolicy, spend: i32) -> i32 {
+    if spend <= 0 {
+        return 0;
+    }
+    (f64::from(spend) * policy.earn_percent / 100.0).floor() as i32
+}
+
+// Reservation phones are optional; a blank one is dropped
+fn normalize_optional_phone(phone: Option<String>) -> Result<Option<String>, String> {
+    phone
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| phone::normalize(&p))
+        .transpose()
+}
+
+// Checks the per-service designer shares and fills in their names.
+// Shares on a line must add up to 100%.
+fn resolve_service_staff(services: &mut [ServiceInput]) -> Result<(), String> {
+    for service in services.iter_mut().filter(|s| !s.staff.is_empty()) {
+        let mut total = 0;
+        for i in 0..service.staff.len() {
+            let share = &service.staff[i];
+            if !(1..=100).contains(&share.share_percent) {
+                return Err("담당 비율은 1~100% 사이여야 합니다".to_string());
+            }
+            if service.staff[..i]
+                .iter()
+                .any(|s| s.staff_id == share.staff_id)
+            {
+                return Err(format!(
+                    "'{}' 시술에 같은 직원이 중복되었습니다",
+                    service.name
+                ));
+            }
+            let staff =
+                database::get_staff_by_id(&share.staff_id).ok_or("직원을 찾을 수 없습니다")?;
+            total += share.share_percent;
+            service.staff[i].staff_name = staff.name;
+        }
+        if total != 100 {
+            return Err(format!(
+                "'{}' 시술의 담당 비율 합계가 100%가 아닙니다 ({}%)",
+                service.name, total
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Every sale needs a breakdown adding up to what is due, so the payment
+// method totals and the cash drawer see all of it
+fn validate_payments(payments: &[PaymentInput], amount_due: i32) -> Result<(), String> {
+    if payments.is_empty() && amount_due > 0 {
+        return Err("결제 수단을 입력해주세요".to_string());
+    }
+    for payment in payments {
+        if payment.amount <= 0 {
+            return Err("결제 금액은 0원보다 커야 합니다".to_string());
+        }
+        if payment.method == PaymentMethod::Deposit {
+            return Err("예약금은 결제 수단으로 직접 입력할 수 없습니다".to_string());
+        }
+    }
+
+    let paid: i32 = payments.iter().map(|p| p.amount).sum();
+    if paid != amount_due {
+        return Err(format!(
+            "결제 금액 합계({}원)가 청구 금액({}원)과 일치하지 않습니다",
+            paid, amount_due
+        ));
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+fn cancel_service(id: i32) -> Result<(), String> {
+    // Get current session
+    let session = database::get_session_by_seat_id(id).ok_or("진행 중인 서비스가 없습니다")?;
+
+    // Update reservation status back to scheduled if exists
+    if let Some(reservation_id) = &session.reservation_id {
+        if let Ok(reservation) =
+            database::update_reservation_status(reservation_id, ReservationStatus::Scheduled)
+        {
+            events::emit(ChangeEvent::ReservationUpdated { reservation });
+        }
+    }
+
+    // Delete session and update seat status
+    database::delete_session(id).map_err(|e| format!("세션 삭제 실패: {}", e))?;
+    database::update_seat_status(id, SeatStatus::Available)
+        .map_err(|e| format!("좌석 상태 업데이트 실패: {}", e))?;
+
+    events::emit(ChangeEvent::SessionCancelled { seat_id: id });
+    events::emit(ChangeEvent::SeatStatusChanged {
+        seat_id: id,
+        status: SeatStatus::Available,
+    });
+
+    Ok(())
+}
+
+// ==================== Reservation Commands ====================
+#[tauri::command]
+fn get_reservations(
+    status: Option<String>,
+    date: Option<String>,
+    all: Option<String>,
+) -> Vec<Reservation> {
+    let query = ReservationQuery { status, date, all };
+    database::get_reservations(&query)
+}
+
+#[tauri::command]
+fn get_reservation(id: String) -> Result<Reservation, String> {
+    database::get_reservation_by_id(&id).ok_or("예약을 찾을 수 없습니다".to_string())
+}
+
+#[tauri::command]
+fn create_reservation(mut request: CreateReservationRequest) -> Result<Reservation, String> {
+    resolve_service_staff(&mut request.services)?;
+    request.member_phone = normalize_optional_phone(request.member_phone)?;
+
+    let reservation =
+        database::create_reservation(&request).map_err(|e| format!("예약 생성 실패: {}", e))?;
+
+    notifications::enqueue_reservation_confirmed(&reservation);
+    events::emit(ChangeEvent::ReservationCreated {
+        reservation: reservation.clone(),
+    });
+
+    Ok(reservation)
+}
+
+#[tauri::command]
+fn update_reservation(
+    id: String,
+    mut request: UpdateReservationRequest,
+) -> Result<Reservation, String> {
+    resolve_service_staff(&mut request.services)?;
+    request.member_phone = normalize_optional_phone(request.member_phone)?;
+
+    let reservation = database::update_reservation(&id, &request)
+        .map_err(|e| format!("예약 수정 실패: {}", e))?;
+
+    notifications::reschedule_reservation(&reservation);
+    events::emit(ChangeEvent::ReservationUpdated {
+        reservation: reservation.clone(),
+    });
+
+    Ok(reservation)
+}
+
+#[tauri::command]
+fn update_reservation_status(
+    id: String,
+    request: UpdateReservationStatusRequest,
+) -> Result<Reservation, String> {
+    let current = database::get_reservation_by_id(&id).ok_or("예약을 찾을 수 없습니다")?;
+    let forfeit = deposit_forfeit(&current, request.status);
+    if forfeit > 0 {
+        ensure_day_open(chrono::Utc::now(), request.owner_override.as_ref())?;
+    }
+
+    let reservation = database::settle_reservation(&current, request.status, forfeit)
+        .map_err(write_error("예약 상태 업데이트 실패"))?;
+
+    if reservation.status != ReservationStatus::Scheduled {
+        let _ = database::cancel_reservation_notifications(&id);
+    }
+
+    events::emit(ChangeEvent::ReservationUpdated {
+        reservation: reservation.clone(),
+    });
+
+    Ok(reservation)
+}
+
+// The share of a paid deposit kept as income when a booking is cancelled
+// late or missed
+fn deposit_forfeit(reservation: &Reservation, status: ReservationStatus) -> i32 {
+    if reservation.deposit_status != DepositStatus::Paid {
+        return 0;
+    }
+
+    let policy = database::get_deposit_policy();
+    let percent = match status {
+        ReservationStatus::NoShow => policy.no_show_percent,
+        ReservationStatus::Cancelled => {
+            let minutes_left = (reservation.reserved_at - chrono::Utc::now()).num_minutes();
+            if minutes_left < i64::from(policy.late_cancel_hours) * 60 {
+                policy.late_cancel_percent
+            } else {
+                0
+            }
+        }
+        _ => 0,
+    };
+    reservation.deposit_amount * percent.clamp(0, 100) / 100
+}
+
+#[tauri::command]
+fn record_deposit(id: String, request: RecordDepositRequest) -> Result<Reservation, String> {
+    if request.amount <= 0 {
+        return Err("예약금은 0원보다 커야 합니다".to_string());
+    }
+    if matches!(
+        request.method,
+        PaymentMethod::Deposit | PaymentMethod::Prepaid | PaymentMethod::Points
+    ) {
+        return Err("예약금 결제 수단을 선택해주세요".to_string());
+    }
+
+    let reservation = database::get_reservation_by_id(&id).ok_or("예약을 찾을 수 없습니다")?;
+    if reservation.status != ReservationStatus::Scheduled {
+        return Err("예정된 예약에만 예약금을 받을 수 있습니다".to_string());
+    }
+    if !matches!(
+        reservation.deposit_status,
+        DepositStatus::None | DepositStatus::Pending
+    ) {
+        return Err("이미 예약금이 처리된 예약입니다".to_string());
+    }
+
+    let reservation = database::record_deposit(&id, request.amount, request.method)
+        .map_err(write_error("예약금 등록 실패"))?;
+    events::emit(ChangeEvent::ReservationUpdated {
+        reservation: reservation.clone(),
+    });
+
+    Ok(reservation)
+}
+
+#[tauri::command]
+fn refund_deposit(id: String, request: RefundDepositRequest) -> Result<Reservation, String> {
+    let reservation = database::get_reservation_by_id(&id).ok_or("예약을 찾을 수 없습니다")?;
+
+    if reservation.deposit_status != DepositStatus::Paid {
+        return Err("환불할 수 있는 예약금이 없습니다".to_string());
+    }
+    auth::verify_approver(&request.approved_by)?;
+    if let Some(paid_at) = reservation.deposit_paid_at {
+        ensure_day_open(paid_at, request.owner_override.as_ref())?;
+    }
+    ensure_day_open(chrono::Utc::now(), request.owner_override.as_ref())?;
+
+    let reservation = database::refund_deposit(&id).map_err(write_error("예약금 환불 실패"))?;
+    events::emit(ChangeEvent::ReservationUpdated {
+        reservation: reservation.clone(),
+    });
+
+    Ok(reservation)
+}
+
+#[tauri::command]
+fn get_deposit_policy() -> DepositPolicy {
+    database::get_deposit_policy()
+}
+
+#[tauri::command]
+fn update_deposit_policy(request: DepositPolicy) -> Result<DepositPolicy, String> {
+    database::save_deposit_policy(&request).map_err(|e| format!("예약금 정책 저장 실패: {}", e))
+}
+
+#[tauri::command]
+fn delete_reservation(id: String) -> Result<(), String> {
+    let _ = database::cancel_reservation_notifications(&id);
+    database::delete_reservation(&id).map_err(|e| format!("예약 삭제 실패: {}", e))?;
+    events::emit(ChangeEvent::ReservationDeleted { reservation_id: id });
+
+    Ok(())
+}
+
+// ==================== Calendar Commands ====================
+#[tauri::command]
+fn export_reservations_ics(
+    path: String,
+    staff_id: Option<String>,
+    date: Option<String>,
+    all: Option<String>,
+) -> Result<usize, String> {
+    let query = ReservationQuery {
+        status: None,
+        date,
+        all,
+    };
+    let reservations: Vec<Reservation> = database::get_reservations(&query)
+        .into_iter()
+        .filter(|r| staff_id.as_deref().is_none_or(|id| r.staff_id == id))
+        .collect();
+
+    let calendar_name = match staff_id.as_deref().and_then(database::get_staff_by_id) {
+        Some(staff) => format!("타임헤어 - {}", staff.name),
+        None => "타임헤어".to_string(),
+    };
+
+    std::fs::write(
+        &path,
+        ical::export_reservations(&reservations, &calendar_name),
+    )
+    .map_err(|e| format!("캘린더 내보내기 실패: {}", e))?;
+
+    Ok(reservations.len())
+}
+
+#[tauri::command]
+fn import_reservations_ics(request: ImportIcsRequest) -> Result<IcsImportReport, String> {
+    let staff = database::get_staff_by_id(&request.staff_id).ok_or("직원을 찾을 수 없습니다")?;
+    let content =
+        std::fs::read_to_string(&request.path).map_err(|e| format!("파일 읽기 실패: {}", e))?;
+    let events = ical::parse_events(&content)?;
+
+    // Active bookings for this designer, checked for overlaps as events are added
+    let mut booked: Vec<Reservation> = database::get_reservations(&ReservationQuery {
+        status: None,
+        date: None,
+        all: Some("true".to_string()),
+    })
+    .into_iter()
+    .filter(|r| {
+        r.staff_id == staff.id
+            && matches!(
+                r.status,
+                ReservationStatus::Scheduled | ReservationStatus::InProgress
+            )
+    })
+    .collect();
+
+    let mut report = IcsImportReport {
+        created: vec![],
+        duplicates: vec![],
+        conflicts: vec![],
+        skipped: vec![],
+        errors: vec![],
+    };
+
+    for event in events {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                report.errors.push(IcsImportIssue {
+                    uid: e.uid,
+                    summary: e.summary,
+                    reserved_at: None,
+                    reason: e.reason,
+                    conflicting_reservation_id: None,
+                });
+                continue;
+            }
+        };
+        let issue = |reason: &str, conflicting_reservation_id: Option<String>| IcsImportIssue {
+            uid: event.uid.clone(),
+            summary: event.summary.clone(),
+            reserved_at: Some(event.start),
+            reason: reason.to_string(),
+            conflicting_reservation_id,
+        };
+
+        if event.cancelled {
+            report.skipped.push(issue("취소된 일정", None));
+            continue;
+        }
+
+        if let Some(uid) = &event.uid {
+            let own = ical::reservation_id_from_uid(uid).and_then(database::get_reservation_by_id);
+            if let Some(existing) = own {
+                report
+                    .duplicates
+                    .push(issue("이미 등록된 예약", Some(existing.id)));
+                continue;
+            }
+            if database::external_uid_exists(uid) {
+                report.duplicates.push(issue("이미 가져온 일정", None));
+                continue;
+            }
+        }
+
+        let end = event.start + chrono::Duration::minutes(i64::from(event.duration_minutes));
+        let conflict = booked.iter().find(|r| {
+            let r_end = r.reserved_at + chrono::Duration::minutes(i64::from(r.estimated_duration));
+            r.reserved_at < end && event.start < r_end
+        });
+        if let Some(conflict) = conflict {
+            report.conflicts.push(issue(
+                "담당 직원의 다른 예약과 시간이 겹칩니다",
+                Some(conflict.id.clone()),
+            ));
+            continue;
+        }
+
+        let member_name = if event.summary.trim().is_empty() {
+            "(제목 없음)".to_string()
+        } else {
+            event.summary.trim().to_string()
+        };
+
+        let created = database::create_reservation(&CreateReservationRequest {
+            member_id: None,
+            member_name,
+            member_phone: None,
+            seat_id: request.seat_id,
+            staff_id: staff.id.clone(),
+            staff_name: staff.name.clone(),
+            services: vec![],
+            total_price: 0,
+            reserved_at: event.start,
+            estimated_duration: event.duration_minutes,
+            deposit_amount: 0,
+            deposit_method: None,
+        });
+        let reservation = match created {
+            Ok(reservation) => reservation,
+            Err(e) => {
+                report
+                    .errors
+                    .push(issue(&format!("예약 생성 실패: {}", e), None));
+                continue;
+            }
+        };
+
+        if let Some(uid) = &event.uid {
+            let _ = database::set_reservation_external_uid(&reservation.id, uid);
+        }
+
+        events::emit(ChangeEvent::ReservationCreated {
+            reservation: reservation.clone(),
+        });
+        booked.push(reservation.clone());
+        report.created.push(reservation);
+    }
+
+    Ok(report)
+}
+
+// ==================== Notification Commands ====================
+#[tauri::command]
+fn get_notifications(
+    status: Option<String>,
+    member_id: Option<String>,
+    reservation_id: Option<String>,
+) -> Vec<Notification> {
+    let query = NotificationQuery {
+        status,
+        member_id,
+        reservation_id,
+    };
+    database::get_notifications(&query)
+}
+
+#[tauri::command]
+fn retry_notification(id: String) -> Result<Notification, String> {
+    let notification = database::get_notification_by_id(&id).ok_or("알림을 찾을 수 없습니다")?;
+
+    if notification.status == NotificationStatus::Sent {
+        return Err("이미 발송된 알림입니다".to_string());
+    }
+    if notification.status == NotificationStatus::Sending {
+        return Err("발송 중인 알림입니다".to_string());
+    }
+
+    database::requeue_notification(&id).map_err(|e| format!("알림 재시도 실패: {}", e))
+}
+
+#[tauri::command]
+fn cancel_notification(id: String) -> Result<Notification, String> {
+    database::cancel_notification(&id).map_err(|e| format!("알림 취소 실패: {}", e))
+}
+
+#[tauri::command]
+fn process_notifications() -> usize {
+    notifications::process_due()
+}
+
+#[tauri::command]
+fn get_notification_settings() -> NotificationSettings {
+    database::get_notification_settings()
+}
+
+#[tauri::command]
+fn update_notification_settings(
+    request: NotificationSettings,
+) -> Result<NotificationSettings, String> {
+    database::save_notification_settings(&request)
+        .map_err(|e| format!("알림 설정 저장 실패: {}", e))
+}
+
+#[tauri::command]
+fn get_notification_templates() -> NotificationTemplates {
+    database::get_notification_templates()
+}
+
+#[tauri::command]
+fn update_notification_templates(
+    request: NotificationTemplates,
+) -> Result<NotificationTemplates, String> {
+    database::save_notification_templates(&request)
+        .map_err(|e| format!("알림 템플릿 저장 실패: {}", e))
+}
+
+// ==================== API Server Commands ====================
+#[tauri::command]
+fn get_api_server_settings() -> ApiServerSettings {
+    database::get_api_server_settings()
+}
+
+#[tauri::command]
+fn update_api_server_settings(request: ApiServerSettings) -> Result<ApiServerStatus, String> {
+    let mut settings = request;
+    if settings.token.trim().is_empty() {
+        settings.token = uuid::Uuid::new_v4().simple().to_string();
+    }
+
+    database::save_api_server_settings(&settings)
+        .map_err(|e| format!("API 서버 설정 저장 실패: {}", e))?;
+
+    if settings.enabled {
+        server::start(&settings)?;
+    } else {
+        server::stop();
+    }
+
+    Ok(server::status())
+}
+
+#[tauri::command]
+fn regenerate_api_token() -> Result<ApiServerSettings, String> {
+    let mut settings = database::get_api_server_settings();
+    settings.token = uuid::Uuid::new_v4().simple().to_string();
+
+    database::save_api_server_settings(&settings)
+        .map_err(|e| format!("API 토큰 재발급 실패: {}", e))
+}
+
+#[tauri::command]
+fn get_api_server_status() -> ApiServerStatus {
+    server::status()
+}
+
+// ==================== Backup Commands ====================
+#[tauri::command]
+fn backup_database(path: String) -> Result<(), String> {
+    database::backup_database(&path)
+}
+
+#[tauri::command]
+fn restore_database(path: String) -> Result<(), String> {
+    database::restore_database(&path)
+}
+
+#[tauri::command]
+fn get_db_path() -> Result<String, String> {
+    database::get_db_path()
+        .map(|p| p.to_string_lossy().to_string())
+        .ok_or("DB 경로를 찾을 수 없습니다".to_string())
+}
+
+// ==================== Discount Commands ====================
+#[tauri::command]
+fn get_discount_rules() -> Vec<DiscountRule> {
+    database::get_discount_rules()
+}
+
+#[tauri::command]
+fn create_discount_rule(request: DiscountRuleRequest) -> Result<DiscountRule, String> {
+    validate_discount_rule(&request)?;
+    database::create_discount_rule(&request).map_err(|e| format!("할인 규칙 생성 실패: {}", e))
+}
+
+#[tauri::command]
+fn update_discount_rule(id: String, request: DiscountRuleRequest) -> Result<DiscountRule, String> {
+    validate_discount_rule(&request)?;
+    database::update_discount_rule(&id, &request).map_err(|e| format!("할인 규칙 수정 실패: {}", e))
+}
+
+#[tauri::command]
+fn delete_discount_rule(id: String) -> Result<(), String> {
+    database::delete_discount_rule(&id).map_err(|e| format!("할인 규칙 삭제 실패: {}", e))
+}
+
+fn validate_discount_rule(request: &DiscountRuleRequest) -> Result<(), String> {
+    if request.name.trim().is_empty() {
+        return Err("할인 이름을 입력해주세요".to_string());
+    }
+    if let (Some(starts_at), Some(ends_at)) = (request.starts_at, request.ends_at) {
+        if ends_at <= starts_at {
+            return Err("할인 종료일은 시작일 이후여야 합니다".to_string());
+        }
+    }
+    discounts::validate_value(request.kind, request.value)
+}
+
+#[tauri::command]
+fn get_coupons() -> Vec<Coupon> {
+    database::get_coupons()
+}
+
+#[tauri::command]
+fn create_coupon(request: CouponRequest) -> Result<Coupon, String> {
+    validate_coupon_request(&request, None)?;
+    database::create_coupon(&request).map_err(|e| format!("쿠폰 생성 실패: {}", e))
+}
+
+#[tauri::command]
+fn update_coupon(id: String, request: CouponRequest) -> Result<Coupon, String> {
+    validate_coupon_request(&request, Some(&id))?;
+    database::update_coupon(&id, &request).map_err(|e| format!("쿠폰 수정 실패: {}", e))
+}
+
+#[tauri::command]
+fn delete_coupon(id: String) -> Result<(), String> {
+    database::delete_coupon(&id).map_err(|e| format!("쿠폰 삭제 실패: {}", e))
+}
+
+// Checks that a code can be redeemed right now
+#[tauri::command]
+fn validate_coupon(code: String) -> Result<Coupon, String> {
+    discounts::validate_coupon(&code, chrono::Utc::now())
+}
+
+fn validate_coupon_request(
+    request: &CouponRequest,
+    exclude_id: Option<&str>,
+) -> Result<(), String> {
+    if request.code.trim().is_empty() || request.name.trim().is_empty() {
+        return Err("쿠폰 코드와 이름을 입력해주세요".to_string());
+    }
+    if request.max_uses.is_some_and(|max| max <= 0) {
+        return Err("최대 사용 횟수는 1회 이상이어야 합니다".to_string());
+    }
+    if database::coupon_code_exists(&request.code, exclude_id) {
+        return Err("이미 등록된 쿠폰 코드입니다".to_string());
+    }
+    discounts::validate_value(request.kind, request.value)
+}
+
+// ==================== Ledger Commands ====================
+#[tauri::command]
+fn get_ledger_entries(
+    date: Option<String>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    staff_id: Option<String>,
+) -> Vec<LedgerEntry> {
+    let query = LedgerQuery {
+        date,
+        start_date,
+        end_date,
+        staff_id,
+    };
+    database::get_ledger_entries(&query)
+}
+
+#[tauri::command]
+fn get_ledger_summary(
+    date: Option<String>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+) -> LedgerSummary {
+    let query = LedgerQuery {
+        date,
+        start_date,
+        end_date,
+        staff_id: None,
+    };
+    database::get_ledger_summary(&query)
+}
+
+// Voids all or part of a completed entry with a compensating negative entry
+#[tauri::command]
+fn refund_ledger_entry(id: String, request: RefundRequest) -> Result<LedgerEntry, String> {
+    let original = database::get_ledger_entry_by_id(&id).ok_or("매출 내역을 찾을 수 없습니다")?;
+    if original.entry_type == LedgerEntryType::Refund {
+        return Err("환불 내역은 다시 환불할 수 없습니다".to_string());
+    }
+    if request.reason.trim().is_empty() {
+        return Err("환불 사유를 입력해주세요".to_string());
+    }
+    let approver = auth::verify_approver(&request.approved_by)?;
+    ensure_day_open(original.completed_at, request.owner_override.as_ref())?;
+    ensure_day_open(chrono::Utc::now(), request.owner_override.as_ref())?;
+
+    let refundable = original.total_price - original.refunded_amount;
+    // A visit paid entirely with package tickets can still be voided once
+    let zero_void =
+        original.total_price == 0 && database::get_refunds_by_ledger_id(&original.id).is_empty();
+    if refundable <= 0 && !zero_void {
+        return Err("이미 전액 환불된 매출입니다".to_string());
+    }
+    let amount = request.amount.unwrap_or(refundable);
+    if zero_void && amount != 0 {
+        return Err("회수권으로만 결제한 매출은 0원으로 취소해야 합니다".to_string());
+    }
+    if !zero_void && (amount <= 0 || amount > refundable) {
+        return Err(format!(
+            "환불 금액은 1원 이상 {}원 이하여야 합니다",
+            refundable
+        ));
+    }
+
+    let payments = if request.payments.is_empty() {
+        allocate_refund(&original, amount)
+    } else {
+        for payment in &request.payments {
+            if payment.amount <= 0 {
+                return Err("결제 금액은 0원보다 커야 합니다".to_string());
+            }
+        }
+        let total: i32 = request.payments.iter().map(|p| p.amount).sum();
+        if total != amount {
+            return Err(format!(
+                "환불 수단 합계({}원)가 환불 금액({}원)과 일치하지 않습니다",
+                total, amount
+            ));
+        }
+        request.payments
+    };
+    let prepaid_restored = paid_with(&payments, PaymentMethod::Prepaid);
+    if prepaid_restored > 0 && original.member_id.is_none() {
+        return Err("회원 매출만 선불권으로 환불할 수 있습니다".to_string());
+    }
+    let points_restored = paid_with(&payments, PaymentMethod::Points);
+    if points_restored > 0 && original.member_id.is_none() {
+        return Err("회원 매출만 포인트로 환불할 수 있습니다".to_string());
+    }
+
+    let mut postings = RefundPostings {
+        prepaid_restored,
+        points_restored,
+        points_reversed: points_to_reverse(&original, amount),
+        ..Default::default()
+    };
+    // A fully voided visit takes back the stamps it earned and gives back
+    // the stamps and package ticket visits it used
+    if amount == refundable && original.entry_type == LedgerEntryType::Service {
+        if original.member_id.is_some() {
+            let history = database::get_stamp_transactions_by_ledger_id(&original.id);
+            let total = |kind: StampTransactionKind| -> i32 {
+                history
+                    .iter()
+                    .filter(|t| t.kind == kind)
+                    .map(|t| t.amount.abs())
+                    .sum()
+            };
+            postings.stamps_revoked = total(StampTransactionKind::Earn);
+            postings.stamps_restored = total(StampTransactionKind::Redeem);
+        }
+        postings.restore_packages = true;
+    }
+    if amount == refundable {
+        postings.release_coupon = original
+            .discounts
+            .iter()
+            .find(|d| d.source == DiscountSource::Coupon)
+            .and_then(|d| d.reference_id.clone());
+    }
+    // Refunding a pass sale closes the pass, so only an unused pass can be refunded
+    if original.entry_type == LedgerEntryType::PackageSale {
+        let member_package = database::get_member_package_by_ledger_id(&original.id)
+            .ok_or("회수권을 찾을 수 없습니다")?;
+        if amount != refundable || member_package.remaining_count != member_package.total_count {
+            return Err("사용하지 않은 회수권만 전액 환불할 수 있습니다".to_string());
+        }
+        postings.close_package = Some(member_package.id);
+    }
+
+    let completed = database::complete_refund(
+        &original,
+        amount,
+        request.reason.trim(),
+        &approver.username,
+        &payments,
+        &postings,
+    )
+    .map_err(write_error("환불 기록 실패"))?;
+    let refund = completed.ledger_entry;
+
+    if let Some(member_id) = &original.member_id {
+        if let Some(transaction) = completed.prepaid {
+            events::emit(ChangeEvent::PrepaidBalanceChanged {
+                member_id: member_id.clone(),
+                transaction,
+            });
+        }
+        for transaction in completed.points {
+            events::emit(ChangeEvent::PointsChanged {
+                member_id: member_id.clone(),
+                transaction,
+            });
+        }
+    }
+    for member_package in completed
+        .member_package_ids
+        .iter()
+        .filter_map(|id| database::get_member_package_by_id(id))
+    {
+        events::emit(ChangeEvent::MemberPackageChanged { member_package });
+    }
+
+    if let Some(member_id) = &original.member_id {
+        recalculate_tier(member_id);
+    }
+
+    events::emit(ChangeEvent::LedgerEntryRefunded {
+        ledger_entry_id: original.id,
+        refund: refund.clone(),
+    });
+
+    Ok(refund)
+}
+
+// The share of earned points that matches how much of the sale will have been
+// refunded, less what earlier refunds already took back
+fn points_to_reverse(original: &LedgerEntry, amount: i32) -> i32 {
+    if original.member_id.is_none() || original.total_price <= 0 {
+        return 0;
+    }
+
+    let history = database::get_point_transactions_for_sale(&original.id);
+    let total = |kind: PointTransactionKind| -> i64 {
+        history
+            .iter()
+            .filter(|t| t.kind == kind)
+            .map(|t| i64::from(t.amount.abs()))
+            .sum()
+    };
+    let earned = total(PointTransactionKind::Earn);
+    let refunded = i64::from(original.refunded_amount + amount);
+    let due = (earned * refunded / i64::from(original.total_price)).min(earned);
+    (due - total(PointTransactionKind::Reverse)).max(0) as i32
+}
+
+// Returns money through the original payment methods, newest first, skipping
+// whatever earlier refunds already paid back
+fn allocate_refund(original: &LedgerEntry, amount: i32) -> Vec<PaymentInput> {
+    let mut refunded: std::collections::HashMap<&'static str, i32> =
+        std::collections::HashMap::new();
+    let previous = database::get_refunds_by_ledger_id(&original.id);
+    for payment in previous.iter().flat_map(|r| &r.payments) {
+        *refunded.entry(payment.method.as_str()).or_insert(0) -= payment.amount;
+    }
+
+    let mut remaining = amount;
+    let mut allocated = vec![];
+    for payment in original.payments.iter().rev() {
+        let already = refunded.entry(payment.method.as_str()).or_insert(0);
+        let available = payment.amount - (*already).min(payment.amount);
+        *already -= payment.amount - available;
+
+        let take = available.min(remaining);
+        if take > 0 {
+            allocated.push(PaymentInput {
+                method: payment.method,
+                amount: take,
+                approval_number: payment.approval_number.clone(),
+            });
+            remaining -= take;
+        }
+    }
+
+    allocated
+}
+
+// Fixes staff, services or prices on a completed entry; the previous version
+// is kept in the correction history
+#[tauri::command]
+fn correct_ledger_entry(
+    id: String,
+    request: CorrectLedgerEntryRequest,
+) -> Result<LedgerEntry, String> {
+    let original = database::get_ledger_entry_by_id(&id).ok_or("매출 내역을 찾을 수 없습니다")?;
+    if original.entry_type != LedgerEntryType::Service {
+        return Err("시술 매출만 수정할 수 있습니다".to_string());
+    }
+    if original.refunded_amount > 0 {
+        return Err("환불 내역이 있는 매출은 수정할 수 없습니다".to_string());
+    }
+    if request.reason.trim().is_empty() {
+        return Err("수정 사유를 입력해주세요".to_string());
+    }
+    let corrector = auth::verify_approver(&request.corrected_by)?;
+    ensure_day_open(original.completed_at, request.owner_override.as_ref())?;
+
+    let mut corrected = original.clone();
+    let mut changes = vec![];
+    let mut change = |field: &str, before: String, after: String| {
+        changes.push(FieldChange {
+            field: field.to_string(),
+            before,
+            after,
+        })
+    };
+
+    if let Some(staff_id) = request.staff_id.filter(|s| *s != original.staff_id) {
+        let staff = database::get_staff_by_id(&staff_id).ok_or("직원을 찾을 수 없습니다")?;
+        change("staff", original.staff_name.clone(), staff.name.clone());
+        corrected.staff_id = staff.id;
+        corrected.staff_name = staff.name;
+    }
+
+    if let Some(mut services) = request.services {
+        if services.is_empty() {
+            return Err("시술을 하나 이상 선택해주세요".to_string());
+        }
+        if services.iter().any(|s| s.price < 0) {
+            return Err("시술 금액은 0원 이상이어야 합니다".to_string());
+        }
+        resolve_service_staff(&mut services)?;
+
+        let before = describe_services(&original.services);
+        corrected.services = services
+            .into_iter()
+            .map(|s| SelectedService {
+                id: 0,
+                service_session_id: None,
+                reservation_id: None,
+                ledger_entry_id: Some(original.id.clone()),
+                name: s.name,
+                length: s.length,
+                price: s.price,
+                category: s.category,
+                staff: s.staff,
+                member_package_id: None,
+            })
+            .collect();
+        discounts::carry_redemptions(&original.services, &mut corrected.services)?;
+        let after = describe_services(&corrected.services);
+        if before != after {
+            change("services", before, after);
+        }
+
+        corrected.gross_amount = corrected
+            .services
+            .iter()
+            .filter(|s| s.member_package_id.is_none())
+            .map(|s| s.price)
+            .sum();
+        corrected.discounts = discounts::recompute(&corrected);
+        corrected.discount_amount = corrected.discounts.iter().map(|d| d.amount).sum();
+        if corrected.discount_amount != original.discount_amount {
+            change(
+                "discount_amount",
+                original.discount_amount.to_string(),
+                corrected.discount_amount.to_string(),
+            );
+        }
+        corrected.total_price = corrected.gross_amount - corrected.discount_amount;
+        if corrected.total_price < corrected.deposit_applied.max(0) {
+            return Err("수정 금액이 할인 및 예약금 적용액보다 작습니다".to_string());
+        }
+        if corrected.total_price != original.total_price {
+            change(
+                "total_price",
+                original.total_price.to_string(),
+                corrected.total_price.to_string(),
+            );
+        }
+    }
+
+    // Payments must still add up to the corrected bill
+    let has_breakdown = original
+        .payments
+        .iter()
+        .any(|p| p.method != PaymentMethod::Deposit);
+    let price_changed = corrected.total_price != original.total_price;
+    if !request.payments.is_empty() || (price_changed && has_breakdown) {
+        if request.payments.is_empty() {
+            return Err("변경된 금액에 맞는 결제 내역을 입력해주세요".to_string());
+        }
+        validate_payments(
+            &request.payments,
+            corrected.total_price - corrected.deposit_applied,
+        )?;
+        for method in [PaymentMethod::Prepaid, PaymentMethod::Points] {
+            let before: i32 = original
+                .payments
+                .iter()
+                .filter(|p| p.method == method)
+                .map(|p| p.amount)
+                .sum();
+            if paid_with(&request.payments, method) != before {
+                return Err(
+                    "선불권·포인트 결제 금액은 수정할 수 없습니다. 환불 후 다시 결제해주세요"
+                        .to_string(),
+                );
+            }
+        }
+
+        let before = describe_payments(&original.payments);
+        let deposit_lines = original
+            .payments
+            .iter()
+            .filter(|p| p.method == PaymentMethod::Deposit)
+            .cloned();
+        corrected.payments = request
+            .payments
+            .into_iter()
+            .map(|p| Payment {
+                id: String::new(),
+                ledger_entry_id: original.id.clone(),
+                method: p.method,
+                amount: p.amount,
+                approval_number: p.approval_number,
+                paid_at: original.completed_at,
+            })
+            .chain(deposit_lines)
+            .collect();
+        let after = describe_payments(&corrected.payments);
+        if before != after {
+            change("payments", before, after);
+        }
+    }
+
+    if changes.is_empty() {
+        return Err("변경된 내용이 없습니다".to_string());
+    }
+
+    let ledger_entry = database::correct_ledger_entry(
+        &original,
+        &corrected,
+        &corrector.username,
+        request.reason.trim(),
+        &changes,
+    )
+    .map_err(write_error("매출 수정 실패"))?;
+
+    if let Some(member_id) = &ledger_entry.member_id {
+        recalculate_tier(member_id);
+    }
+
+    events::emit(ChangeEvent::LedgerEntryCorrected {
+        ledger_entry: ledger_entry.clone(),
+    });
+
+    Ok(ledger_entry)
+}
+
+fn describe_services(services: &[SelectedService]) -> String {
+    services
+        .iter()
+        .map(|s| {
+            let staff: Vec<String> = s
+                .staff
+                .iter()
+                .map(|share| format!("{} {}%", share.staff_name, share.share_percent))
+                .collect();
+            if staff.is_empty() {
+                format!("{} {}원", s.name, s.price)
+            } else {
+                format!("{} {}원 ({})", s.name, s.price, staff.join(", "))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn describe_payments(payments: &[Payment]) -> String {
+    payments
+        .iter()
+        .map(|p| format!("{} {}원", p.method.as_str(), p.amount))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[tauri::command]
+fn get_ledger_corrections(id: String) -> Vec<LedgerCorrection> {
+    database::get_ledger_corrections(&id)
+}
+
+#[tauri::command]
+fn get_ledger_refunds(id: String) -> Vec<LedgerEntry> {
+    database::get_refunds_by_ledger_id(&id)
+}
+
+#[tauri::command]
+fn get_daily_summary(year: Option<i32>, month: Option<u32>) -> Vec<DailySummary> {
+    let now = chrono::Utc::now();
+    let y = year.unwrap_or(now.format("%Y").to_string().parse().unwrap_or(2025));
+    let m = month.unwrap_or(now.format("%m").to_string().parse().unwrap_or(1));
+    database::get_daily_summary(y, m)
+}
+
+// ==================== Commission Commands ====================
+#[tauri::command]
+fn get_commission_rules() -> Vec<CommissionRule> {
+    database::get_commission_rules()
+}
+
+#[tauri::command]
+fn create_commission_rule(request: CommissionRuleRequest) -> Result<CommissionRule, String> {
+    validate_commission_rule(&request)?;
+    database::create_commission_rule(&request).map_err(|e| format!("수수료 규칙 생성 실패: {}", e))
+}
+
+#[tauri::command]
+fn update_commission_rule(
+    id: String,
+    request: CommissionRuleRequest,
+) -> Result<CommissionRule, String> {
+    validate_commission_rule(&request)?;
+    database::update_commission_rule(&id, &request)
+        .map_err(|e| format!("수수료 규칙 수정 실패: {}", e))
+}
+
+#[tauri::command]
+fn delete_commission_rule(id: String) -> Result<(), String> {
+    database::delete_commission_rule(&id).map_err(|e| format!("수수료 규칙 삭제 실패: {}", e))
+}
+
+fn validate_commission_rule(request: &CommissionRuleRequest) -> Result<(), String> {
+    if let Some(staff_id) = &request.staff_id {
+        database::get_staff_by_id(staff_id).ok_or("직원을 찾을 수 없습니다")?;
+    }
+
+    match request.kind {
+        CommissionKind::Percentage if !(0..=100).contains(&request.value) => {
+            Err("수수료율은 0~100 사이여야 합니다".to_string())
+        }
+        CommissionKind::FixedPerService if request.value <= 0 => {
+            Err("건당 수수료는 0원보다 커야 합니다".to_string())
+        }
+        CommissionKind::Tiered if request.tiers.is_empty() => {
+            Err("구간별 수수료율을 하나 이상 입력해주세요".to_string())
+        }
+        CommissionKind::Tiered
+            if request
+                .tiers
+                .iter()
+                .any(|t| t.min_revenue < 0 || !(0..=100).contains(&t.percent)) =>
+        {
+            Err("구간 매출은 0원 이상, 수수료율은 0~100 사이여야 합니다".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+#[tauri::command]
+fn add_tip(ledger_entry_id: String, request: AddTipRequest) -> Result<Tip, String> {
+    let entry =
+        database::get_ledger_entry_by_id(&ledger_entry_id).ok_or("매출 내역을 찾을 수 없습니다")?;
+    if entry.entry_type != LedgerEntryType::Service {
+        return Err("시술 매출에만 팁을 기록할 수 있습니다".to_string());
+    }
+    validate_tip(&request.tip)?;
+    ensure_day_open(chrono::Utc::now(), request.owner_override.as_ref())?;
+
+    database::create_tip(&entry, &request.tip).map_err(|e| format!("팁 기록 실패: {}", e))
+}
+
+#[tauri::command]
+fn get_tips(ledger_entry_id: String) -> Vec<Tip> {
+    database::get_tips_by_ledger_id(&ledger_entry_id)
+}
+
+fn validate_tip(tip: &TipInput) -> Result<(), String> {
+    if tip.amount <= 0 {
+        return Err("팁 금액은 0원보다 커야 합니다".to_string());
+    }
+    if matches!(
+        tip.method,
+        PaymentMethod::Deposit | PaymentMethod::Prepaid | PaymentMethod::Points
+    ) {
+        return Err("예약금·선불권·포인트는 팁 결제 수단으로 사용할 수 없습니다".to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn get_payroll_report(
+    start_date: String,
+    end_date: String,
+    staff_id: Option<String>,
+) -> Result<Vec<StaffPayroll>, String> {
+    if parse_business_date(&end_date)? < parse_business_date(&start_date)? {
+        return Err("종료일은 시작일 이후여야 합니다".to_string());
+    }
+
+    Ok(payroll::build_report(&LedgerQuery {
+        date: None,
+        start_date: Some(start_date),
+        end_date: Some(end_date),
+        staff_id,
+    }))
+}
+
+// ==================== Receipt Commands ====================
+#[tauri::command]
+fn get_receipt_settings() -> ReceiptSettings {
+    database::get_receipt_settings()
+}
+
+#[tauri::command]
+fn update_receipt_settings(settings: ReceiptSettings) -> Result<ReceiptSettings, String> {
+    if !(24..=64).contains(&settings.paper_width) {
+        return Err("영수증 폭은 24~64자 사이여야 합니다".to_string());
+    }
+    database::save_receipt_settings(&settings)
+        .map_err(|e| format!("영수증 설정 저장 실패: {}", e))?;
+    Ok(settings)
+}
+
+#[tauri::command]
+fn get_receipt_text(id: String, reprint: Option<bool>) -> Result<String, String> {
+    let entry = database::get_ledger_entry_by_id(&id).ok_or("매출 내역을 찾을 수 없습니다")?;
+    Ok(receipt::render_text(
+        &entry,
+        &database::get_receipt_settings(),
+        reprint.unwrap_or(false),
+    ))
+}
+
+#[tauri::command]
+fn save_receipt(
+    id: String,
+    format: ReceiptFormat,
+    path: String,
+    reprint: Option<bool>,
+) -> Result<(), String> {
+    let entry = database::get_ledger_entry_by_id(&id).ok_or("매출 내역을 찾을 수 없습니다")?;
+    let settings = database::get_receipt_settings();
+    let reprint = reprint.unwrap_or(false);
+
+    let bytes = match format {
+        ReceiptFormat::Text => receipt::render_text(&entry, &settings, reprint).into_bytes(),
+        ReceiptFormat::Pdf => receipt::render_pdf(&entry, &settings, reprint),
+        ReceiptFormat::EscPos => receipt::render_escpos(&entry, &settings, reprint),
+    };
+
+    std::fs::write(&path, bytes).map_err(|e| format!("영수증 저장 실패: {}", e))
+}
+
+#[tauri::command]
+fn print_receipt(id: String) -> Result<(), String> {
+    send_receipt_to_printer(&id, false)
+}
+
+// Prints a past entry again, marked as a reprint
+#[tauri::command]
+fn reprint_receipt(id: String) -> Result<(), String> {
+    send_receipt_to_printer(&id, true)
+}
+
+fn send_receipt_to_printer(id: &str, reprint: bool) -> Result<(), String> {
+    let entry = database::get_ledger_entry_by_id(id).ok_or("매출 내역을 찾을 수 없습니다")?;
+    let settings = database::get_receipt_settings();
+    let address = settings
+        .printer_address
+        .as_deref()
+        .filter(|a| !a.trim().is_empty())
+        .ok_or("영수증 프린터가 설정되지 않았습니다")?;
+
+    receipt::print_escpos(
+        address.trim(),
+        &receipt::render_escpos(&entry, &settings, reprint),
+    )
+}
+
+// ==================== Closing Commands ====================
+#[tauri::command]
+fn get_cash_drawer_summary(
+    business_date: String,
+    opening_float: i32,
+) -> Result<CashDrawerSummary, String> {
+    parse_business_date(&business_date)?;
+    Ok(database::get_cash_drawer_summary(
+        &business_date,
+        opening_float,
+    ))
+}
+
+#[tauri::command]
+fn close_day(request: CloseDayRequest) -> Result<DailyClosing, String> {
+    let date = parse_business_date(&request.business_date)?;
+    if date > chrono::Local::now().date_naive() {
+        return Err("아직 영업이 끝나지 않은 날짜입니다".to_string());
+    }
+    if request.opening_float < 0 || request.counted_cash < 0 {
+        return Err("시재 금액은 0원 이상이어야 합니다".to_string());
+    }
+    let closer = auth::verify_approver(&request.closed_by)?;
+    if database::is_day_closed(&request.business_date) {
+        return Err("이미 마감된 날짜입니다".to_string());
+    }
+
+    let closing = database::create_daily_closing(&request, &closer.username)
+        .map_err(|e| format!("일마감 실패: {}", e))?;
+
+    events::emit(ChangeEvent::DayClosed {
+        closing: closing.clone(),
+    });
+
+    Ok(closing)
+}
+
+#[tauri::command]
+fn get_daily_closings(start_date: Option<String>, end_date: Option<String>) -> Vec<DailyClosing> {
+    database::get_daily_closings(start_date.as_deref(), end_date.as_deref())
+}
+
+#[tauri::command]
+fn get_closing_report(business_date: String) -> Result<ClosingReport, String> {
+    let closing =
+        database::get_daily_closing(&business_date).ok_or("마감 기록을 찾을 수 없습니다")?;
+    let query = LedgerQuery {
+        date: Some(business_date),
+        ..Default::default()
+    };
+
+    Ok(ClosingReport {
+        closing,
+        summary: database::get_ledger_summary(&query),
+        entries: database::get_ledger_entries(&query),
+    })
+}
+
+fn parse_business_date(date: &str) -> Result<chrono::NaiveDate, String> {
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| "날짜 형식이 올바르지 않습니다 (YYYY-MM-DD)".to_string())
+}
+
+// Business dates are local (KST) dates, matching DATE(completed_at, 'localtime')
+fn ensure_day_open(
+    at: chrono::DateTime<chrono::Utc>,
+    owner_override: Option<&LoginRequest>,
+) -> Result<(), String> {
+    let date = at.with_timezone(&chrono::Local).format("%Y-%m-%d");
+    if !database::is_day_closed(&date.to_string()) {
+        return Ok(());
+    }
+
+    match owner_override {
+        Some(credentials) => auth::verify_owner(credentials).map(|_| ()),
+        None => Err("마감된 날짜의 매출은 사장님 승인 없이 수정할 수 없습니다".to_string()),
+    }
+}
+
+fn main() {
+    tauri::Builder::default()
+        .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .plugin(tauri_plugin_dialog::init())
+        .setup(|app| {
+            // Initialize database
+            let app_data_dir = app
+                .path()
+                .app_data_dir()
+                .expect("Failed to get app data dir");
+
+            println!("App Data Dir: {:?}", app_data_dir);
+
+            database::init_database(app_data_dir.clone()).expect("Failed to initialize database");
+
+            println!("Database initialized successfully");
+
+            events::init(app.handle().clone());
+
+            expire_prepaid_balances();
+            stamps::expire();
+            let moved = tiers::recalculate_all();
+            if !moved.is_empty() {
+                println!("Moved {} members between tiers", moved.len());
+            }
+            if let Ok(fixed) = database::reconcile_stamps() {
+                if !fixed.is_empty() {
+                    println!("Reconciled stamps for {} members", fixed.len());
+                }
+            }
+            if let Ok(report) = database::normalize_member_phones() {
+                if report.normalized > 0 || !report.collisions.is_empty() {
+                    println!(
+                        "Normalized {} member phones, {} collisions",
+                        report.normalized,
+                        report.collisions.len()
+                    );
+                }
+                let _ = database::save_phone_normalization_report(&report);
+            }
+
+            photos::init(app_data_dir.clone());
+            notifications::init(app_data_dir);
+
+            let api_settings = database::get_api_server_settings();
+            if api_settings.enabled {
+                if let Err(e) = server::start(&api_settings) {
+                    eprintln!("{}", e);
+                }
+            }
 
             Ok(())
         })
@@ -312,15 +2617,70 @@ fn main() {
             // Auth
             login,
             get_current_user,
+            get_users,
+            create_user,
             // Members
             get_members,
+            get_birthday_members,
             get_member,
+            get_member_profile,
             search_member_by_phone,
             create_member,
             update_member,
+            normalize_member_phones,
+            get_phone_normalization_report,
             delete_member,
+            get_member_fields,
+            update_member_fields,
+            find_duplicate_members,
+            merge_members,
+            get_member_merges,
             add_stamp,
             reset_stamps,
+            get_stamp_transactions,
+            reconcile_stamps,
+            // Prepaid
+            get_prepaid_transactions,
+            top_up_prepaid,
+            refund_prepaid,
+            get_prepaid_policy,
+            update_prepaid_policy,
+            // Points
+            get_point_transactions,
+            adjust_points,
+            get_point_policy,
+            update_point_policy,
+            // Treatment notes
+            get_treatment_notes,
+            get_latest_formula,
+            create_treatment_note,
+            update_treatment_note,
+            delete_treatment_note,
+            // Member photos
+            get_member_photos,
+            attach_member_photo,
+            delete_member_photo,
+            // Membership tiers
+            get_membership_tiers,
+            create_membership_tier,
+            update_membership_tier,
+            delete_membership_tier,
+            recalculate_member_tiers,
+            get_tier_movements,
+            // Stamp programs
+            get_stamp_programs,
+            create_stamp_program,
+            update_stamp_program,
+            delete_stamp_program,
+            get_stamp_status,
+            // Packages
+            get_packages,
+            create_package,
+            update_package,
+            delete_package,
+            get_member_packages,
+            purchase_package,
+            get_package_usages,
             // Staff
             get_staff_list,
             get_staff,
@@ -332,6 +2692,7 @@ fn main() {
             get_seat,
             start_service,
             complete_service,
+            preview_checkout,
             cancel_service,
             // Reservations
             get_reservations,
@@ -340,10 +2701,65 @@ fn main() {
             update_reservation,
             update_reservation_status,
             delete_reservation,
+            record_deposit,
+            refund_deposit,
+            get_deposit_policy,
+            update_deposit_policy,
+            // Calendar
+            export_reservations_ics,
+            import_reservations_ics,
+            // Notifications
+            get_notifications,
+            retry_notification,
+            cancel_notification,
+            process_notifications,
+            get_notification_settings,
+            update_notification_settings,
+            get_notification_templates,
+            update_notification_templates,
+            // API Server
+            get_api_server_settings,
+            update_api_server_settings,
+            regenerate_api_token,
+            get_api_server_status,
+            // Discounts
+            get_discount_rules,
+            create_discount_rule,
+            update_discount_rule,
+            delete_discount_rule,
+            get_coupons,
+            create_coupon,
+            update_coupon,
+            delete_coupon,
+            validate_coupon,
             // Ledger
             get_ledger_entries,
             get_ledger_summary,
             get_daily_summary,
+            refund_ledger_entry,
+            get_ledger_refunds,
+            correct_ledger_entry,
+            get_ledger_corrections,
+            // Commission
+            get_commission_rules,
+            create_commission_rule,
+            update_commission_rule,
+            delete_commission_rule,
+            add_tip,
+            get_tips,
+            get_payroll_report,
+            // Receipts
+            get_receipt_settings,
+            update_receipt_settings,
+            get_receipt_text,
+            save_receipt,
+            print_receipt,
+            reprint_receipt,
+            // Closing
+            get_cash_drawer_summary,
+            close_day,
+            get_daily_closings,
+            get_closing_report,
             // Backup
             backup_database,
             restore_database,
@@ -1,5 +1,6 @@
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
 
 // ==================== User (Admin) ====================
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -8,10 +9,42 @@ pub struct User {
     pub username: String,
     #[serde(skip_serializing)]
     pub password: String,
+    pub role: UserRole,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserRole {
+    Owner,
+    Manager,
+    Staff,
+}
+
+impl UserRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserRole::Owner => "owner",
+            UserRole::Manager => "manager",
+            UserRole::Staff => "staff",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "owner" => UserRole::Owner,
+            "manager" => UserRole::Manager,
+            _ => UserRole::Staff,
+        }
+    }
+
+    // Refunds, corrections, merges and balance adjustments
+    pub fn can_approve(&self) -> bool {
+        matches!(self, UserRole::Owner | UserRole::Manager)
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct LoginRequest {
     pub username: String,
@@ -28,260 +61,2091 @@ pub struct LoginResponse {
 pub struct UserInfo {
     pub id: String,
     pub username: String,
+    pub role: UserRole,
     pub created_at: DateTime<Utc>,
 }
 
+// Only the owner can add accounts, so `owner` carries their credentials
+#[derive(Debug, Deserialize)]
+pub struct CreateUserRequest {
+    pub username: String,
+    pub password: String,
+    pub role: UserRole,
+    pub owner: LoginRequest,
+}
+
 // ==================== Member (Customer) ====================
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Member {
     pub id: String,
     pub name: String,
+    // E.164, e.g. "+821012345678"
     pub phone: String,
+    // Dashed for the screen, e.g. "010-1234-5678"
+    pub phone_display: String,
     pub stamps: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub prepaid_balance: i32,
+    pub prepaid_expires_at: Option<DateTime<Utc>>,
+    pub points: i32,
+    pub tier_id: Option<String>,
+    pub tier_name: Option<String>,
+    pub birthday: Option<NaiveDate>,
+    pub gender: Option<Gender>,
+    pub memo: Option<String>,
+    pub marketing_consent: bool,
+    // When the current consent was given
+    pub marketing_consent_at: Option<DateTime<Utc>>,
+    pub tags: Vec<String>,
+    // Values for the shop's member fields, keyed by MemberFieldDefinition::key
+    pub custom_fields: BTreeMap<String, String>,
+    // Package tickets with visits left
+    pub packages: Vec<MemberPackage>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Gender {
+    Female,
+    Male,
+    Other,
+}
+
+impl Gender {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Gender::Female => "female",
+            Gender::Male => "male",
+            Gender::Other => "other",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "female" => Gender::Female,
+            "male" => Gender::Male,
+            _ => Gender::Other,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CreateMemberRequest {
     pub name: String,
     pub phone: String,
+    #[serde(flatten)]
+    pub details: MemberDetailsInput,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateMemberRequest {
     pub name: String,
     pub phone: String,
+    #[serde(flatten)]
+    pub details: MemberDetailsInput,
 }
 
-// ==================== Staff ====================
+// Member fields beyond name and phone as sent by the client. On update a
+// field left out keeps its value and `null` clears it.
+#[derive(Debug, Default, Deserialize)]
+pub struct MemberDetailsInput {
+    #[serde(default, deserialize_with = "present")]
+    pub birthday: Option<Option<NaiveDate>>,
+    #[serde(default, deserialize_with = "present")]
+    pub gender: Option<Option<Gender>>,
+    #[serde(default, deserialize_with = "present")]
+    pub memo: Option<Option<String>>,
+    pub marketing_consent: Option<bool>,
+    pub tags: Option<Vec<String>>,
+    pub custom_fields: Option<BTreeMap<String, String>>,
+}
+
+// Tells a field sent as null apart from one left out
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+// Member fields as written to the database
+#[derive(Debug, Default)]
+pub struct MemberDetails {
+    pub birthday: Option<NaiveDate>,
+    pub gender: Option<Gender>,
+    pub memo: Option<String>,
+    pub marketing_consent: bool,
+    pub tags: Vec<String>,
+    pub custom_fields: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct MemberQuery {
+    // Matches name, phone, memo, tags and custom field values
+    pub search: Option<String>,
+    pub tag: Option<String>,
+    pub gender: Option<Gender>,
+    pub marketing_consent: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BirthdayRange {
+    Week,
+    Month,
+}
+
+// A field the shop adds to every member, e.g. "두피 타입" as a choice
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Staff {
-    pub id: String,
-    pub name: String,
-    pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
+pub struct MemberFieldDefinition {
+    pub key: String,
+    pub label: String,
+    pub field_type: MemberFieldType,
+    // Allowed values for a choice field
+    #[serde(default)]
+    pub options: Vec<String>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct CreateStaffRequest {
-    pub name: String,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemberFieldType {
+    Text,
+    Number,
+    Date,
+    Choice,
+}
+
+// ==================== Member Merge ====================
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateReason {
+    SamePhone,
+    // Last four digits match
+    SamePhoneSuffix,
+    SimilarName,
+    SameBirthday,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateCandidate {
+    pub member: Member,
+    pub duplicate: Member,
+    pub reasons: Vec<DuplicateReason>,
+    // 0.0 to 1.0
+    pub score: f64,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct UpdateStaffRequest {
-    pub name: String,
+pub struct MergeMembersRequest {
+    // The member that is kept
+    pub survivor_id: String,
+    // The member folded into the survivor and then removed
+    pub duplicate_id: String,
+    // Credentials of the manager or owner doing the merge
+    pub merged_by: LoginRequest,
+    pub reason: Option<String>,
 }
 
-// ==================== Seat ====================
+// A merge as recorded in history, with what the removed member brought over
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberMerge {
+    pub id: String,
+    pub survivor_id: String,
+    pub merged_member_id: String,
+    pub merged_name: String,
+    pub merged_phone: String,
+    pub stamps: i32,
+    pub prepaid_balance: i32,
+    pub points: i32,
+    pub ledger_entries: i32,
+    pub reservations: i32,
+    pub merged_by: String,
+    pub reason: Option<String>,
+    pub merged_at: DateTime<Utc>,
+}
+
+// ==================== Phone Normalization ====================
+// Result of rewriting stored member phones into E.164
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PhoneNormalizationReport {
+    pub normalized: i32,
+    // Numbers that are not a valid Korean phone number, left as typed
+    pub invalid: Vec<PhoneIssue>,
+    // Numbers whose normalized form another member already has, left as
+    // typed so staff can merge the two
+    pub collisions: Vec<PhoneIssue>,
+    pub checked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhoneIssue {
+    pub member_id: String,
+    pub member_name: String,
+    pub phone: String,
+    // For collisions, the member holding the normalized number
+    pub conflicts_with: Option<String>,
+}
+
+// ==================== Prepaid Balance ====================
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
-pub enum SeatStatus {
-    Available,
-    InUse,
-    Reserved,
+pub enum PrepaidTransactionKind {
+    TopUp,
+    Use,
+    // Balance given back when a sale paid from it is refunded
+    Restore,
+    Refund,
+    Expire,
 }
 
-impl SeatStatus {
+impl PrepaidTransactionKind {
     pub fn as_str(&self) -> &'static str {
         match self {
-            SeatStatus::Available => "available",
-            SeatStatus::InUse => "in_use",
-            SeatStatus::Reserved => "reserved",
+            PrepaidTransactionKind::TopUp => "top_up",
+            PrepaidTransactionKind::Use => "use",
+            PrepaidTransactionKind::Restore => "restore",
+            PrepaidTransactionKind::Refund => "refund",
+            PrepaidTransactionKind::Expire => "expire",
         }
     }
 
     pub fn from_str(s: &str) -> Self {
         match s {
-            "in_use" => SeatStatus::InUse,
-            "reserved" => SeatStatus::Reserved,
-            _ => SeatStatus::Available,
+            "use" => PrepaidTransactionKind::Use,
+            "restore" => PrepaidTransactionKind::Restore,
+            "refund" => PrepaidTransactionKind::Refund,
+            "expire" => PrepaidTransactionKind::Expire,
+            _ => PrepaidTransactionKind::TopUp,
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Seat {
-    pub id: i32,
-    pub name: String,
-    pub status: SeatStatus,
-    pub current_session: Option<ServiceSession>,
+pub struct PrepaidTransaction {
+    pub id: String,
+    pub member_id: String,
+    pub kind: PrepaidTransactionKind,
+    // Change to the balance, negative for use/refund/expire
+    pub amount: i32,
+    pub bonus_amount: i32,
+    // Money received for a top-up, negative when paid back
+    pub paid_amount: i32,
+    pub method: Option<PaymentMethod>,
+    pub ledger_entry_id: Option<String>,
+    pub balance_after: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub memo: Option<String>,
+    pub created_by: Option<String>,
     pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
 }
 
-// ==================== ServiceSession ====================
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ServiceSession {
-    pub id: String,
-    pub seat_id: i32,
-    pub member_id: Option<String>,
-    pub member_name: String,
-    pub services: Vec<SelectedService>,
-    pub total_price: i32,
-    pub staff_id: String,
-    pub staff_name: String,
-    pub start_time: DateTime<Utc>,
-    pub reservation_id: Option<String>,
-    pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
+// One balance movement as written by database::record_prepaid_transaction
+#[derive(Debug, Default)]
+pub struct PrepaidMovement {
+    pub amount: i32,
+    pub bonus_amount: i32,
+    pub paid_amount: i32,
+    pub method: Option<PaymentMethod>,
+    pub ledger_entry_id: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub memo: Option<String>,
+    pub created_by: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct StartServiceRequest {
-    pub member_id: Option<String>,
-    pub member_name: String,
-    pub staff_id: String,
-    pub staff_name: String,
-    pub services: Vec<ServiceInput>,
-    pub total_price: i32,
-    pub reservation_id: Option<String>,
+pub struct TopUpPrepaidRequest {
+    // Money received; the balance grows by this plus the bonus
+    pub amount: i32,
+    pub method: PaymentMethod,
+    // Overrides the policy's bonus for this top-up
+    #[serde(default)]
+    pub bonus_amount: Option<i32>,
+    #[serde(default)]
+    pub memo: Option<String>,
+    // Credentials of the staff member taking the payment
+    pub created_by: LoginRequest,
+    #[serde(default)]
+    pub owner_override: Option<LoginRequest>,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct ServiceInput {
-    pub name: String,
-    pub length: Option<String>,
-    pub price: i32,
+pub struct RefundPrepaidRequest {
+    // Balance to take off; the whole balance when omitted
+    #[serde(default)]
+    pub amount: Option<i32>,
+    // Money paid back; defaults to the amount less its share of bonus credit
+    #[serde(default)]
+    pub refund_amount: Option<i32>,
+    pub method: PaymentMethod,
+    pub reason: String,
+    // Credentials of the manager or owner approving the refund
+    pub approved_by: LoginRequest,
+    #[serde(default)]
+    pub owner_override: Option<LoginRequest>,
 }
 
-// ==================== SelectedService ====================
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SelectedService {
-    pub id: i32,
-    pub service_session_id: Option<String>,
-    pub reservation_id: Option<String>,
-    pub ledger_entry_id: Option<String>,
-    pub name: String,
-    pub length: Option<String>,
-    pub price: i32,
+pub struct PrepaidBonusTier {
+    pub min_amount: i32,
+    pub bonus_amount: i32,
 }
 
-// ==================== Reservation ====================
+// Balances expire `validity_days` after the latest top-up (0 = never).
+// A top-up earns the bonus of the highest tier it reaches.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrepaidPolicy {
+    pub validity_days: i32,
+    pub bonus_tiers: Vec<PrepaidBonusTier>,
+}
+
+impl Default for PrepaidPolicy {
+    fn default() -> Self {
+        PrepaidPolicy {
+            validity_days: 365,
+            bonus_tiers: vec![],
+        }
+    }
+}
+
+// ==================== Loyalty Points ====================
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
-pub enum ReservationStatus {
-    Scheduled,
-    InProgress,
-    Completed,
-    Cancelled,
+pub enum PointTransactionKind {
+    Earn,
+    Redeem,
+    // Earned points taken back when the sale is refunded
+    Reverse,
+    // Redeemed points given back when the sale is refunded
+    Restore,
+    Adjust,
 }
 
-impl ReservationStatus {
+impl PointTransactionKind {
     pub fn as_str(&self) -> &'static str {
         match self {
-            ReservationStatus::Scheduled => "scheduled",
-            ReservationStatus::InProgress => "in_progress",
-            ReservationStatus::Completed => "completed",
-            ReservationStatus::Cancelled => "cancelled",
+            PointTransactionKind::Earn => "earn",
+            PointTransactionKind::Redeem => "redeem",
+            PointTransactionKind::Reverse => "reverse",
+            PointTransactionKind::Restore => "restore",
+            PointTransactionKind::Adjust => "adjust",
         }
     }
 
     pub fn from_str(s: &str) -> Self {
         match s {
-            "in_progress" => ReservationStatus::InProgress,
-            "completed" => ReservationStatus::Completed,
-            "cancelled" => ReservationStatus::Cancelled,
-            _ => ReservationStatus::Scheduled,
+            "redeem" => PointTransactionKind::Redeem,
+            "reverse" => PointTransactionKind::Reverse,
+            "restore" => PointTransactionKind::Restore,
+            "adjust" => PointTransactionKind::Adjust,
+            _ => PointTransactionKind::Earn,
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Reservation {
+pub struct PointTransaction {
     pub id: String,
-    pub member_id: Option<String>,
-    pub member_name: String,
-    pub member_phone: Option<String>,
-    pub seat_id: Option<i32>,
-    pub staff_id: String,
-    pub staff_name: String,
-    pub services: Vec<SelectedService>,
-    pub total_price: i32,
-    pub reserved_at: DateTime<Utc>,
-    pub estimated_duration: i32,
-    pub status: ReservationStatus,
+    pub member_id: String,
+    pub kind: PointTransactionKind,
+    pub amount: i32,
+    pub balance_after: i32,
+    pub ledger_entry_id: Option<String>,
+    // For reversals and restores, the sale the points came from
+    pub source_entry_id: Option<String>,
+    pub actor: Option<String>,
+    pub reason: Option<String>,
     pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct CreateReservationRequest {
-    pub member_id: Option<String>,
-    pub member_name: String,
-    pub member_phone: Option<String>,
-    pub seat_id: Option<i32>,
-    pub staff_id: String,
-    pub staff_name: String,
-    pub services: Vec<ServiceInput>,
-    pub total_price: i32,
-    pub reserved_at: DateTime<Utc>,
-    pub estimated_duration: i32,
+// Context written with a point change by database::record_point_transaction
+#[derive(Debug, Default)]
+pub struct PointMovement {
+    pub ledger_entry_id: Option<String>,
+    pub source_entry_id: Option<String>,
+    pub actor: Option<String>,
+    pub reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct UpdateReservationRequest {
-    pub member_id: Option<String>,
-    pub member_name: String,
-    pub member_phone: Option<String>,
-    pub seat_id: Option<i32>,
-    pub staff_id: String,
-    pub staff_name: String,
-    pub services: Vec<ServiceInput>,
-    pub total_price: i32,
-    pub reserved_at: DateTime<Utc>,
-    pub estimated_duration: i32,
+pub struct AdjustPointsRequest {
+    pub amount: i32,
+    // Credentials of the manager or owner making the adjustment
+    pub actor: LoginRequest,
+    pub reason: String,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct UpdateReservationStatusRequest {
-    pub status: ReservationStatus,
+// Members earn `earn_percent` of what they actually paid, excluding the part
+// paid with points. Points are spent one per won, `min_redeem` at a time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PointPolicy {
+    pub enabled: bool,
+    pub earn_percent: f64,
+    pub min_redeem: i32,
 }
 
-// ==================== Ledger ====================
+impl Default for PointPolicy {
+    fn default() -> Self {
+        PointPolicy {
+            enabled: false,
+            earn_percent: 1.0,
+            min_redeem: 0,
+        }
+    }
+}
+
+// ==================== Package Ticket ====================
+// A count-based pass, e.g. 10 cuts paid up front
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct LedgerEntry {
+pub struct Package {
     pub id: String,
-    pub reservation_id: Option<String>,
-    pub member_id: Option<String>,
-    pub member_name: String,
-    pub seat_id: i32,
-    pub staff_id: String,
-    pub staff_name: String,
-    pub services: Vec<SelectedService>,
-    pub total_price: i32,
-    pub completed_at: DateTime<Utc>,
+    pub name: String,
+    pub service_name: String,
+    pub total_count: i32,
+    pub price: i32,
+    // 0 = never expires
+    pub validity_days: i32,
+    pub active: bool,
     pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct LedgerSummary {
-    pub total_revenue: i32,
+#[derive(Debug, Deserialize)]
+pub struct PackageRequest {
+    pub name: String,
+    pub service_name: String,
     pub total_count: i32,
-    pub by_staff: Vec<StaffRevenue>,
-    pub by_service: Vec<ServiceCount>,
+    pub price: i32,
+    #[serde(default)]
+    pub validity_days: i32,
+    pub active: bool,
 }
 
+// A package bought by a member; name, count and price are copied at purchase
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct StaffRevenue {
-    pub staff_id: String,
-    pub staff_name: String,
-    pub revenue: i32,
-    pub count: i32,
+pub struct MemberPackage {
+    pub id: String,
+    pub member_id: String,
+    pub package_id: String,
+    pub name: String,
+    pub service_name: String,
+    pub total_count: i32,
+    pub remaining_count: i32,
+    pub price: i32,
+    pub payment_method: PaymentMethod,
+    pub purchased_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_by: String,
+    // The sale that paid for the pass
+    pub ledger_entry_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PurchasePackageRequest {
+    pub package_id: String,
+    pub method: PaymentMethod,
+    #[serde(default)]
+    pub approval_number: Option<String>,
+    // Credentials of the staff member taking the payment
+    pub created_by: LoginRequest,
+    #[serde(default)]
+    pub owner_override: Option<LoginRequest>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ServiceCount {
+pub struct PackageUsage {
+    pub id: String,
+    pub member_package_id: String,
+    pub ledger_entry_id: String,
     pub service_name: String,
-    pub count: i32,
-    pub revenue: i32,
+    pub used_at: DateTime<Utc>,
+    // Set when the visit was voided and the count given back
+    pub restored_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DailySummary {
-    pub date: String,
-    pub revenue: i32,
-    pub count: i32,
+// ==================== Stamp History ====================
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StampTransactionKind {
+    // Balance carried over from before the history was kept
+    Opening,
+    Earn,
+    ManualAdd,
+    Redeem,
+    Reset,
+    Expire,
+    // Stamps taken back when the visit that earned them is voided
+    Revoke,
+    // Redeemed stamps given back when that visit is voided
+    Restore,
+}
+
+impl StampTransactionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StampTransactionKind::Opening => "opening",
+            StampTransactionKind::Earn => "earn",
+            StampTransactionKind::ManualAdd => "manual_add",
+            StampTransactionKind::Redeem => "redeem",
+            StampTransactionKind::Reset => "reset",
+            StampTransactionKind::Expire => "expire",
+            StampTransactionKind::Revoke => "revoke",
+            StampTransactionKind::Restore => "restore",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "opening" => StampTransactionKind::Opening,
+            "manual_add" => StampTransactionKind::ManualAdd,
+            "redeem" => StampTransactionKind::Redeem,
+            "reset" => StampTransactionKind::Reset,
+            "expire" => StampTransactionKind::Expire,
+            "revoke" => StampTransactionKind::Revoke,
+            "restore" => StampTransactionKind::Restore,
+            _ => StampTransactionKind::Earn,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StampTransaction {
+    pub id: String,
+    pub member_id: String,
+    pub kind: StampTransactionKind,
+    // Change to the stamp count, negative when stamps are taken off
+    pub amount: i32,
+    pub balance_after: i32,
+    pub ledger_entry_id: Option<String>,
+    pub program_id: Option<String>,
+    pub actor: Option<String>,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Context written with a stamp change by database::record_stamp_transaction
+#[derive(Debug, Default)]
+pub struct StampMovement {
+    pub ledger_entry_id: Option<String>,
+    pub program_id: Option<String>,
+    pub actor: Option<String>,
+    pub reason: Option<String>,
+}
+
+// Who adjusted the stamps by hand and why; `count` defaults to one
+#[derive(Debug, Deserialize)]
+pub struct StampAdjustRequest {
+    pub count: Option<i32>,
+    // Credentials of the staff member adjusting the stamps
+    pub actor: LoginRequest,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StampDiscrepancy {
+    pub member_id: String,
+    pub member_name: String,
+    // members.stamps before it was rewritten
+    pub stamps: i32,
+    pub history_total: i32,
+}
+
+// ==================== Stamp Program ====================
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StampRewardKind {
+    FreeService,
+    Percentage,
+    FixedAmount,
+}
+
+impl StampRewardKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StampRewardKind::FreeService => "free_service",
+            StampRewardKind::Percentage => "percentage",
+            StampRewardKind::FixedAmount => "fixed_amount",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "percentage" => StampRewardKind::Percentage,
+            "fixed_amount" => StampRewardKind::FixedAmount,
+            _ => StampRewardKind::FreeService,
+        }
+    }
+}
+
+// `stamps_required` stamps buy the reward. A visit earns one stamp, or one
+// per `amount_per_stamp` won spent, and only when it includes one of the
+// `eligible_services` (empty = any). Only one program is active at a time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StampProgram {
+    pub id: String,
+    pub name: String,
+    pub stamps_required: i32,
+    pub reward_kind: StampRewardKind,
+    // Percent or won; unused for a free service
+    pub reward_value: i32,
+    // The free service, or the service a discount is limited to
+    pub reward_service_name: Option<String>,
+    pub amount_per_stamp: Option<i32>,
+    pub eligible_services: Vec<String>,
+    // Stamps lapse this many days after the last one earned (0 = never)
+    pub expiry_days: i32,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StampProgramRequest {
+    pub name: String,
+    pub stamps_required: i32,
+    pub reward_kind: StampRewardKind,
+    #[serde(default)]
+    pub reward_value: i32,
+    pub reward_service_name: Option<String>,
+    pub amount_per_stamp: Option<i32>,
+    #[serde(default)]
+    pub eligible_services: Vec<String>,
+    #[serde(default)]
+    pub expiry_days: i32,
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StampStatus {
+    pub member_id: String,
+    pub stamps: i32,
+    pub program: Option<StampProgram>,
+    pub reward_available: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+// ==================== Membership Tier ====================
+// A member reaches a tier by spending `min_spend` or visiting `min_visits`
+// times over the last 12 months (0 = not judged on that). A tier with neither
+// is the base tier. The highest `level` reached wins.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MembershipTier {
+    pub id: String,
+    pub name: String,
+    pub level: i32,
+    pub min_spend: i32,
+    pub min_visits: i32,
+    pub discount_percent: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MembershipTierRequest {
+    pub name: String,
+    pub level: i32,
+    #[serde(default)]
+    pub min_spend: i32,
+    #[serde(default)]
+    pub min_visits: i32,
+    #[serde(default)]
+    pub discount_percent: i32,
+}
+
+// A member moving tiers, with the 12-month spend and visits that decided it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TierChange {
+    pub id: String,
+    pub member_id: String,
+    pub member_name: String,
+    pub from_tier_id: Option<String>,
+    pub from_tier_name: Option<String>,
+    pub to_tier_id: Option<String>,
+    pub to_tier_name: Option<String>,
+    pub upgrade: bool,
+    pub spend: i32,
+    pub visits: i32,
+    pub changed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TierMovementReport {
+    pub start_date: String,
+    pub end_date: String,
+    pub upgrades: i32,
+    pub downgrades: i32,
+    pub changes: Vec<TierChange>,
+}
+
+// ==================== Treatment Note ====================
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ColorFormula {
+    pub brand: Option<String>,
+    pub shade: Option<String>,
+    // e.g. "1:1.5"
+    pub developer_ratio: Option<String>,
+    pub processing_minutes: Option<i32>,
+}
+
+// What was done on a visit, kept on the member. Written against the running
+// session and moved onto the ledger entry when it is completed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreatmentNote {
+    pub id: String,
+    pub member_id: String,
+    pub ledger_entry_id: Option<String>,
+    pub session_id: Option<String>,
+    pub staff_id: Option<String>,
+    pub staff_name: Option<String>,
+    pub color_formula: Option<ColorFormula>,
+    pub perm_rod_size: Option<String>,
+    pub scalp_condition: Option<String>,
+    pub hair_condition: Option<String>,
+    pub allergies: Option<String>,
+    pub memo: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TreatmentNoteRequest {
+    pub member_id: String,
+    pub ledger_entry_id: Option<String>,
+    pub session_id: Option<String>,
+    pub staff_id: Option<String>,
+    pub color_formula: Option<ColorFormula>,
+    pub perm_rod_size: Option<String>,
+    pub scalp_condition: Option<String>,
+    pub hair_condition: Option<String>,
+    pub allergies: Option<String>,
+    pub memo: Option<String>,
+}
+
+// ==================== Member Photo ====================
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PhotoKind {
+    Before,
+    After,
+    Other,
+}
+
+impl PhotoKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PhotoKind::Before => "before",
+            PhotoKind::After => "after",
+            PhotoKind::Other => "other",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "before" => PhotoKind::Before,
+            "after" => PhotoKind::After,
+            _ => PhotoKind::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberPhoto {
+    pub id: String,
+    pub member_id: String,
+    pub ledger_entry_id: Option<String>,
+    pub kind: PhotoKind,
+    pub content_hash: String,
+    pub extension: String,
+    pub width: i32,
+    pub height: i32,
+    pub file_size: i64,
+    pub memo: Option<String>,
+    // The visit's time when attached to one, otherwise when it was added
+    pub taken_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    // Files under the app data dir, for display
+    pub path: String,
+    pub thumbnail_path: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AttachPhotoRequest {
+    pub member_id: String,
+    pub ledger_entry_id: Option<String>,
+    pub kind: PhotoKind,
+    // Image file picked on this computer
+    pub source_path: String,
+    pub memo: Option<String>,
+}
+
+// ==================== Staff ====================
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Staff {
+    pub id: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateStaffRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateStaffRequest {
+    pub name: String,
+}
+
+// ==================== Seat ====================
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeatStatus {
+    Available,
+    InUse,
+    Reserved,
+}
+
+impl SeatStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SeatStatus::Available => "available",
+            SeatStatus::InUse => "in_use",
+            SeatStatus::Reserved => "reserved",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "in_use" => SeatStatus::InUse,
+            "reserved" => SeatStatus::Reserved,
+            _ => SeatStatus::Available,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Seat {
+    pub id: i32,
+    pub name: String,
+    pub status: SeatStatus,
+    pub current_session: Option<ServiceSession>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// ==================== ServiceSession ====================
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceSession {
+    pub id: String,
+    pub seat_id: i32,
+    pub member_id: Option<String>,
+    pub member_name: String,
+    pub services: Vec<SelectedService>,
+    pub total_price: i32,
+    pub staff_id: String,
+    pub staff_name: String,
+    pub start_time: DateTime<Utc>,
+    pub reservation_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StartServiceRequest {
+    pub member_id: Option<String>,
+    pub member_name: String,
+    pub staff_id: String,
+    pub staff_name: String,
+    pub services: Vec<ServiceInput>,
+    pub total_price: i32,
+    pub reservation_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ServiceInput {
+    pub name: String,
+    pub length: Option<String>,
+    pub price: i32,
+    #[serde(default)]
+    pub category: Option<String>,
+    // Empty means the session's designer did the whole service
+    #[serde(default)]
+    pub staff: Vec<StaffShare>,
+}
+
+// ==================== SelectedService ====================
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelectedService {
+    pub id: i32,
+    pub service_session_id: Option<String>,
+    pub reservation_id: Option<String>,
+    pub ledger_entry_id: Option<String>,
+    pub name: String,
+    pub length: Option<String>,
+    pub price: i32,
+    #[serde(default)]
+    pub category: Option<String>,
+    // Empty means the session's designer did the whole service
+    #[serde(default)]
+    pub staff: Vec<StaffShare>,
+    // Set on ledger lines paid for with a package ticket visit. The pass was
+    // paid for when it was sold, so the line is not charged or counted again.
+    #[serde(default)]
+    pub member_package_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaffShare {
+    pub staff_id: String,
+    #[serde(default)]
+    pub staff_name: String,
+    pub share_percent: i32,
+}
+
+// ==================== Reservation ====================
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReservationStatus {
+    Scheduled,
+    InProgress,
+    Completed,
+    Cancelled,
+    NoShow,
+}
+
+impl ReservationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReservationStatus::Scheduled => "scheduled",
+            ReservationStatus::InProgress => "in_progress",
+            ReservationStatus::Completed => "completed",
+            ReservationStatus::Cancelled => "cancelled",
+            ReservationStatus::NoShow => "no_show",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "in_progress" => ReservationStatus::InProgress,
+            "completed" => ReservationStatus::Completed,
+            "cancelled" => ReservationStatus::Cancelled,
+            "no_show" => ReservationStatus::NoShow,
+            _ => ReservationStatus::Scheduled,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reservation {
+    pub id: String,
+    pub member_id: Option<String>,
+    pub member_name: String,
+    pub member_phone: Option<String>,
+    pub seat_id: Option<i32>,
+    pub staff_id: String,
+    pub staff_name: String,
+    pub services: Vec<SelectedService>,
+    pub total_price: i32,
+    pub reserved_at: DateTime<Utc>,
+    pub estimated_duration: i32,
+    pub status: ReservationStatus,
+    pub deposit_amount: i32,
+    pub deposit_method: Option<PaymentMethod>,
+    pub deposit_status: DepositStatus,
+    pub deposit_paid_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateReservationRequest {
+    pub member_id: Option<String>,
+    pub member_name: String,
+    pub member_phone: Option<String>,
+    pub seat_id: Option<i32>,
+    pub staff_id: String,
+    pub staff_name: String,
+    pub services: Vec<ServiceInput>,
+    pub total_price: i32,
+    pub reserved_at: DateTime<Utc>,
+    pub estimated_duration: i32,
+    #[serde(default)]
+    pub deposit_amount: i32,
+    pub deposit_method: Option<PaymentMethod>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateReservationRequest {
+    pub member_id: Option<String>,
+    pub member_name: String,
+    pub member_phone: Option<String>,
+    pub seat_id: Option<i32>,
+    pub staff_id: String,
+    pub staff_name: String,
+    pub services: Vec<ServiceInput>,
+    pub total_price: i32,
+    pub reserved_at: DateTime<Utc>,
+    pub estimated_duration: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateReservationStatusRequest {
+    pub status: ReservationStatus,
+    // Owner credentials, required when a forfeited deposit would be booked on
+    // a closed day
+    #[serde(default)]
+    pub owner_override: Option<LoginRequest>,
+}
+
+// ==================== iCalendar ====================
+#[derive(Debug, Deserialize)]
+pub struct ImportIcsRequest {
+    pub path: String,
+    pub staff_id: String,
+    pub seat_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IcsImportIssue {
+    pub uid: Option<String>,
+    pub summary: String,
+    pub reserved_at: Option<DateTime<Utc>>,
+    pub reason: String,
+    pub conflicting_reservation_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IcsImportReport {
+    pub created: Vec<Reservation>,
+    pub duplicates: Vec<IcsImportIssue>,
+    pub conflicts: Vec<IcsImportIssue>,
+    pub skipped: Vec<IcsImportIssue>,
+    // Events that could not be read or saved
+    pub errors: Vec<IcsImportIssue>,
+}
+
+// ==================== Deposit ====================
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DepositStatus {
+    None,
+    Pending,
+    Paid,
+    Applied,
+    Refunded,
+    Forfeited,
+}
+
+impl DepositStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DepositStatus::None => "none",
+            DepositStatus::Pending => "pending",
+            DepositStatus::Paid => "paid",
+            DepositStatus::Applied => "applied",
+            DepositStatus::Refunded => "refunded",
+            DepositStatus::Forfeited => "forfeited",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "pending" => DepositStatus::Pending,
+            "paid" => DepositStatus::Paid,
+            "applied" => DepositStatus::Applied,
+            "refunded" => DepositStatus::Refunded,
+            "forfeited" => DepositStatus::Forfeited,
+            _ => DepositStatus::None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RecordDepositRequest {
+    pub amount: i32,
+    pub method: PaymentMethod,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RefundDepositRequest {
+    // Credentials of the manager or owner approving the refund
+    pub approved_by: LoginRequest,
+    // Owner credentials, required when the deposit was taken on a day that is
+    // already closed or today is
+    #[serde(default)]
+    pub owner_override: Option<LoginRequest>,
+}
+
+// Forfeit rules: cancelling within `late_cancel_hours` of the booking keeps
+// `late_cancel_percent` of the deposit, a no-show keeps `no_show_percent`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepositPolicy {
+    pub late_cancel_hours: i32,
+    pub late_cancel_percent: i32,
+    pub no_show_percent: i32,
+}
+
+impl Default for DepositPolicy {
+    fn default() -> Self {
+        DepositPolicy {
+            late_cancel_hours: 24,
+            late_cancel_percent: 50,
+            no_show_percent: 100,
+        }
+    }
+}
+
+// ==================== Payment ====================
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentMethod {
+    Cash,
+    Card,
+    Transfer,
+    Deposit,
+    Prepaid,
+    // Loyalty points, one point per won
+    Points,
+}
+
+impl PaymentMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentMethod::Cash => "cash",
+            PaymentMethod::Card => "card",
+            PaymentMethod::Transfer => "transfer",
+            PaymentMethod::Deposit => "deposit",
+            PaymentMethod::Prepaid => "prepaid",
+            PaymentMethod::Points => "points",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "card" => PaymentMethod::Card,
+            "transfer" => PaymentMethod::Transfer,
+            "deposit" => PaymentMethod::Deposit,
+            "prepaid" => PaymentMethod::Prepaid,
+            "points" => PaymentMethod::Points,
+            _ => PaymentMethod::Cash,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Payment {
+    pub id: String,
+    pub ledger_entry_id: String,
+    pub method: PaymentMethod,
+    pub amount: i32,
+    pub approval_number: Option<String>,
+    pub paid_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentInput {
+    pub method: PaymentMethod,
+    pub amount: i32,
+    pub approval_number: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CompleteServiceRequest {
+    #[serde(default)]
+    pub payments: Vec<PaymentInput>,
+    #[serde(default)]
+    pub discount_rule_ids: Vec<String>,
+    pub coupon_code: Option<String>,
+    #[serde(default)]
+    pub is_student: bool,
+    pub tip: Option<TipInput>,
+    // Charge the services even when the member holds a package ticket for them
+    #[serde(default)]
+    pub skip_packages: bool,
+    // Spend the member's stamps on the active program's reward
+    #[serde(default)]
+    pub redeem_stamps: bool,
+    // Leave out the member's tier discount
+    #[serde(default)]
+    pub skip_tier_discount: bool,
+    // Owner credentials, required when today is already closed
+    #[serde(default)]
+    pub owner_override: Option<LoginRequest>,
+}
+
+// Bill computed at checkout from the session, discounts and deposit
+#[derive(Debug, Clone, Serialize)]
+pub struct Checkout {
+    // Services covered by package tickets; they are left out of the bill
+    pub redemptions: Vec<PackageRedemption>,
+    // List price of the services that are charged
+    pub gross_amount: i32,
+    pub discounts: Vec<DiscountLine>,
+    pub discount_amount: i32,
+    pub net_amount: i32,
+    pub deposit_applied: i32,
+    pub amount_due: i32,
+    pub payments: Vec<PaymentInput>,
+}
+
+// One service line paid for with a visit from the member's pass
+#[derive(Debug, Clone, Serialize)]
+pub struct PackageRedemption {
+    pub service_index: usize,
+    pub member_package_id: String,
+    pub name: String,
+    pub amount: i32,
+}
+
+// What a checkout writes besides the sale itself, worked out up front so
+// database::complete_checkout only has to apply it
+#[derive(Debug, Default)]
+pub struct CheckoutPostings {
+    pub tip: Option<TipInput>,
+    pub points_earned: i32,
+    // Program the visit earns stamps under; without one a visit earns one stamp
+    pub stamp_program: Option<StampProgram>,
+    pub redeemed_programs: Vec<StampProgram>,
+}
+
+// What database::complete_checkout wrote, for the change events
+#[derive(Debug)]
+pub struct CompletedCheckout {
+    pub ledger_entry: LedgerEntry,
+    pub member_package_ids: Vec<String>,
+    pub prepaid: Option<PrepaidTransaction>,
+    pub points: Vec<PointTransaction>,
+    pub stamps_earned: Option<StampTransaction>,
+}
+
+// What database::complete_package_sale wrote, for the change events
+#[derive(Debug)]
+pub struct CompletedPackageSale {
+    pub member_package: MemberPackage,
+    pub prepaid: Option<PrepaidTransaction>,
+}
+
+// What a refund gives back or takes back besides the money, worked out up
+// front so database::complete_refund only has to apply it
+#[derive(Debug, Default)]
+pub struct RefundPostings {
+    pub prepaid_restored: i32,
+    pub points_restored: i32,
+    pub points_reversed: i32,
+    pub stamps_revoked: i32,
+    pub stamps_restored: i32,
+    // Only a full void gives back the package ticket visits the sale used
+    pub restore_packages: bool,
+    // Pass closed by refunding the sale that paid for it
+    pub close_package: Option<String>,
+    // Coupon whose use a full refund gives back
+    pub release_coupon: Option<String>,
+}
+
+// What database::complete_refund wrote, for the change events
+#[derive(Debug)]
+pub struct CompletedRefund {
+    pub ledger_entry: LedgerEntry,
+    pub member_package_ids: Vec<String>,
+    pub prepaid: Option<PrepaidTransaction>,
+    pub points: Vec<PointTransaction>,
+}
+
+// Full refund when `amount` is omitted. Without a payment breakdown the
+// refund goes back through the original payment methods.
+#[derive(Debug, Deserialize)]
+pub struct RefundRequest {
+    pub amount: Option<i32>,
+    pub reason: String,
+    // Credentials of the manager or owner approving the refund
+    pub approved_by: LoginRequest,
+    #[serde(default)]
+    pub payments: Vec<PaymentInput>,
+    // Owner credentials, required when the entry's day is already closed
+    pub owner_override: Option<LoginRequest>,
+}
+
+// ==================== Ledger Correction ====================
+// Omitted fields keep their current value. A new payment breakdown is
+// required when the corrected price changes an entry that has one.
+#[derive(Debug, Deserialize)]
+pub struct CorrectLedgerEntryRequest {
+    pub staff_id: Option<String>,
+    pub services: Option<Vec<ServiceInput>>,
+    #[serde(default)]
+    pub payments: Vec<PaymentInput>,
+    pub reason: String,
+    // Credentials of the manager or owner making the correction
+    pub corrected_by: LoginRequest,
+    pub owner_override: Option<LoginRequest>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: String,
+    pub after: String,
+}
+
+// Append-only: `original` is the entry as it was before this correction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerCorrection {
+    pub id: String,
+    pub ledger_entry_id: String,
+    pub corrected_by: String,
+    pub reason: String,
+    pub changes: Vec<FieldChange>,
+    pub original: LedgerEntry,
+    pub created_at: DateTime<Utc>,
+}
+
+// ==================== Daily Closing ====================
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CashDrawerSummary {
+    pub business_date: String,
+    pub opening_float: i32,
+    pub cash_sales: i32,
+    pub cash_refunds: i32,
+    pub cash_tips: i32,
+    // Cash taken for prepaid top-ups, less cash paid back for unused balances
+    pub prepaid_cash: i32,
+    pub expected_cash: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyClosing {
+    pub id: String,
+    pub business_date: String,
+    pub opening_float: i32,
+    pub cash_sales: i32,
+    pub cash_refunds: i32,
+    pub cash_tips: i32,
+    pub prepaid_cash: i32,
+    pub expected_cash: i32,
+    pub counted_cash: i32,
+    // counted - expected (negative when the drawer is short)
+    pub difference: i32,
+    pub gross_revenue: i32,
+    pub total_discount: i32,
+    pub total_refunds: i32,
+    pub total_revenue: i32,
+    pub total_count: i32,
+    pub closed_by: String,
+    pub memo: Option<String>,
+    pub closed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CloseDayRequest {
+    pub business_date: String,
+    pub opening_float: i32,
+    pub counted_cash: i32,
+    // Credentials of the manager or owner closing the day
+    pub closed_by: LoginRequest,
+    pub memo: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClosingReport {
+    pub closing: DailyClosing,
+    pub summary: LedgerSummary,
+    pub entries: Vec<LedgerEntry>,
+}
+
+// ==================== Commission & Tips ====================
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommissionKind {
+    // `value` percent of the service revenue
+    Percentage,
+    // `value` won for each service performed
+    FixedPerService,
+    // Percent picked from `tiers` by the designer's revenue that month
+    Tiered,
+}
+
+impl CommissionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommissionKind::Percentage => "percentage",
+            CommissionKind::FixedPerService => "fixed_per_service",
+            CommissionKind::Tiered => "tiered",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "fixed_per_service" => CommissionKind::FixedPerService,
+            "tiered" => CommissionKind::Tiered,
+            _ => CommissionKind::Percentage,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommissionTier {
+    pub min_revenue: i32,
+    pub percent: i32,
+}
+
+// A rule without staff or category applies to everyone / every category;
+// the most specific matching rule wins
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommissionRule {
+    pub id: String,
+    pub staff_id: Option<String>,
+    pub service_category: Option<String>,
+    pub kind: CommissionKind,
+    pub value: i32,
+    pub tiers: Vec<CommissionTier>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CommissionRuleRequest {
+    pub staff_id: Option<String>,
+    pub service_category: Option<String>,
+    pub kind: CommissionKind,
+    #[serde(default)]
+    pub value: i32,
+    #[serde(default)]
+    pub tiers: Vec<CommissionTier>,
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tip {
+    pub id: String,
+    pub ledger_entry_id: String,
+    pub staff_id: String,
+    pub staff_name: String,
+    pub amount: i32,
+    pub method: PaymentMethod,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TipInput {
+    pub amount: i32,
+    pub method: PaymentMethod,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddTipRequest {
+    #[serde(flatten)]
+    pub tip: TipInput,
+    // Owner credentials, required when the sale's day or today is already closed
+    #[serde(default)]
+    pub owner_override: Option<LoginRequest>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommissionLine {
+    pub ledger_entry_id: String,
+    pub completed_at: DateTime<Utc>,
+    pub service_name: String,
+    pub category: Option<String>,
+    pub revenue: i32,
+    pub rule_id: Option<String>,
+    pub commission: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StaffPayroll {
+    pub staff_id: String,
+    pub staff_name: String,
+    pub service_count: i32,
+    pub revenue: i32,
+    pub commission: i32,
+    pub tips: i32,
+    pub total_pay: i32,
+    pub lines: Vec<CommissionLine>,
+}
+
+// ==================== Receipt ====================
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiptFormat {
+    Text,
+    Pdf,
+    EscPos,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReceiptSettings {
+    pub shop_name: String,
+    pub business_number: String,
+    pub address: String,
+    pub phone: String,
+    pub footer: String,
+    // Characters per line: 42 for 80mm paper, 32 for 58mm
+    pub paper_width: usize,
+    // Network thermal printer (raw TCP, usually port 9100)
+    pub printer_address: Option<String>,
+}
+
+impl Default for ReceiptSettings {
+    fn default() -> Self {
+        ReceiptSettings {
+            shop_name: "타임헤어".to_string(),
+            business_number: String::new(),
+            address: String::new(),
+            phone: String::new(),
+            footer: "이용해 주셔서 감사합니다".to_string(),
+            paper_width: 42,
+            printer_address: None,
+        }
+    }
+}
+
+// ==================== Discount ====================
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscountKind {
+    Percentage,
+    FixedAmount,
+}
+
+impl DiscountKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiscountKind::Percentage => "percentage",
+            DiscountKind::FixedAmount => "fixed_amount",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "fixed_amount" => DiscountKind::FixedAmount,
+            _ => DiscountKind::Percentage,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscountSource {
+    Rule,
+    Coupon,
+    Stamp,
+    Tier,
+}
+
+impl DiscountSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiscountSource::Rule => "rule",
+            DiscountSource::Coupon => "coupon",
+            DiscountSource::Stamp => "stamp",
+            DiscountSource::Tier => "tier",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "coupon" => DiscountSource::Coupon,
+            "stamp" => DiscountSource::Stamp,
+            "tier" => DiscountSource::Tier,
+            _ => DiscountSource::Rule,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscountRule {
+    pub id: String,
+    pub name: String,
+    pub kind: DiscountKind,
+    pub value: i32,
+    pub service_name: Option<String>,
+    pub member_only: bool,
+    pub student_only: bool,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiscountRuleRequest {
+    pub name: String,
+    pub kind: DiscountKind,
+    pub value: i32,
+    pub service_name: Option<String>,
+    #[serde(default)]
+    pub member_only: bool,
+    #[serde(default)]
+    pub student_only: bool,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Coupon {
+    pub id: String,
+    pub code: String,
+    pub name: String,
+    pub kind: DiscountKind,
+    pub value: i32,
+    pub max_uses: Option<i32>,
+    pub used_count: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CouponRequest {
+    pub code: String,
+    pub name: String,
+    pub kind: DiscountKind,
+    pub value: i32,
+    pub max_uses: Option<i32>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub active: bool,
+}
+
+// How a discount line was worked out at checkout. It is kept with the sale so
+// a correction recalculates the line the same way after the rule changed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiscountTerms {
+    pub kind: DiscountKind,
+    pub value: i32,
+    // Only these services count towards the base; the whole bill otherwise
+    pub service_name: Option<String>,
+    // A free-service reward covers one line, not every matching one
+    #[serde(default)]
+    pub single_line: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscountLine {
+    pub source: DiscountSource,
+    pub reference_id: Option<String>,
+    pub name: String,
+    pub amount: i32,
+    #[serde(default)]
+    pub terms: Option<DiscountTerms>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerDiscount {
+    pub id: String,
+    pub ledger_entry_id: String,
+    pub source: DiscountSource,
+    pub reference_id: Option<String>,
+    pub name: String,
+    pub amount: i32,
+    // Missing on sales made before terms were kept
+    #[serde(default)]
+    pub terms: Option<DiscountTerms>,
+}
+
+// ==================== Ledger ====================
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerEntryType {
+    Service,
+    DepositForfeit,
+    Refund,
+    PackageSale,
+}
+
+impl LedgerEntryType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LedgerEntryType::Service => "service",
+            LedgerEntryType::DepositForfeit => "deposit_forfeit",
+            LedgerEntryType::Refund => "refund",
+            LedgerEntryType::PackageSale => "package_sale",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "deposit_forfeit" => LedgerEntryType::DepositForfeit,
+            "refund" => LedgerEntryType::Refund,
+            "package_sale" => LedgerEntryType::PackageSale,
+            _ => LedgerEntryType::Service,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub id: String,
+    pub entry_type: LedgerEntryType,
+    pub reservation_id: Option<String>,
+    pub member_id: Option<String>,
+    pub member_name: String,
+    pub seat_id: i32,
+    pub staff_id: String,
+    pub staff_name: String,
+    pub services: Vec<SelectedService>,
+    pub gross_amount: i32,
+    pub discount_amount: i32,
+    pub discounts: Vec<LedgerDiscount>,
+    pub total_price: i32,
+    pub deposit_applied: i32,
+    pub payments: Vec<Payment>,
+    // Set on refund entries: the entry being refunded, why and who approved it
+    pub refund_of: Option<String>,
+    pub refund_reason: Option<String>,
+    pub approved_by: Option<String>,
+    // Total refunded so far against this entry (positive)
+    pub refunded_amount: i32,
+    pub corrected_at: Option<DateTime<Utc>>,
+    // Tips are paid to the designer and are not part of revenue
+    pub tip_amount: i32,
+    pub completed_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerSummary {
+    pub gross_revenue: i32,
+    pub total_discount: i32,
+    pub total_refunds: i32,
+    // Net: gross - discounts - refunds
+    pub total_revenue: i32,
+    pub total_count: i32,
+    pub refund_count: i32,
+    pub total_tips: i32,
+    pub by_staff: Vec<StaffRevenue>,
+    pub by_service: Vec<ServiceCount>,
+    pub by_payment_method: Vec<PaymentMethodTotal>,
+    pub by_discount: Vec<DiscountTotal>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaffRevenue {
+    pub staff_id: String,
+    pub staff_name: String,
+    pub revenue: i32,
+    pub count: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceCount {
+    pub service_name: String,
+    pub count: i32,
+    pub revenue: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentMethodTotal {
+    pub method: PaymentMethod,
+    pub amount: i32,
+    pub count: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscountTotal {
+    pub name: String,
+    pub amount: i32,
+    pub count: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailySummary {
+    pub date: String,
+    pub gross_revenue: i32,
+    pub total_discount: i32,
+    pub total_refunds: i32,
+    // Net: gross - discounts - refunds
+    pub revenue: i32,
+    pub count: i32,
+}
+
+// Everything the member has done with the shop, built from their sales
+#[derive(Debug, Clone, Serialize)]
+pub struct MemberProfile {
+    pub member: Member,
+    // Completed visits that were not fully refunded
+    pub visit_count: i32,
+    pub first_visit_at: Option<DateTime<Utc>>,
+    pub last_visit_at: Option<DateTime<Utc>>,
+    // Net of discounts and refunds
+    pub total_spend: i32,
+    pub average_spend: i32,
+    // Most booked first
+    pub favorite_services: Vec<ServiceCount>,
+    // Designer seen on the most visits; `count` is visits together
+    pub favorite_staff: Option<StaffRevenue>,
+    // Median days between visits
+    pub typical_return_days: Option<i64>,
+    pub upcoming_reservations: Vec<Reservation>,
+    // Sales and refunds, newest first
+    pub history: Vec<LedgerEntry>,
+}
+
+// ==================== Notification ====================
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    ReservationConfirmed,
+    ReservationReminder,
+    StampRewardReady,
+}
+
+impl NotificationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::ReservationConfirmed => "reservation_confirmed",
+            NotificationKind::ReservationReminder => "reservation_reminder",
+            NotificationKind::StampRewardReady => "stamp_reward_ready",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "reservation_reminder" => NotificationKind::ReservationReminder,
+            "stamp_reward_ready" => NotificationKind::StampRewardReady,
+            _ => NotificationKind::ReservationConfirmed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationChannel {
+    Sms,
+    KakaoTalk,
+}
+
+impl NotificationChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationChannel::Sms => "sms",
+            NotificationChannel::KakaoTalk => "kakao_talk",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "kakao_talk" => NotificationChannel::KakaoTalk,
+            _ => NotificationChannel::Sms,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationStatus {
+    Pending,
+    // Claimed by a sender; keeps two pollers from delivering the same message
+    Sending,
+    Sent,
+    Failed,
+    Cancelled,
+}
+
+impl NotificationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationStatus::Pending => "pending",
+            NotificationStatus::Sending => "sending",
+            NotificationStatus::Sent => "sent",
+            NotificationStatus::Failed => "failed",
+            NotificationStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "sending" => NotificationStatus::Sending,
+            "sent" => NotificationStatus::Sent,
+            "failed" => NotificationStatus::Failed,
+            "cancelled" => NotificationStatus::Cancelled,
+            _ => NotificationStatus::Pending,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: String,
+    pub kind: NotificationKind,
+    pub channel: NotificationChannel,
+    pub member_id: Option<String>,
+    pub reservation_id: Option<String>,
+    pub recipient: String,
+    pub body: String,
+    pub status: NotificationStatus,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub provider_message_id: Option<String>,
+    pub scheduled_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Placeholders: {shop}, {name}, {date}, {time}, {staff}, {stamps}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationTemplates {
+    pub reservation_confirmed: String,
+    pub reservation_reminder: String,
+    pub stamp_reward_ready: String,
+}
+
+impl Default for NotificationTemplates {
+    fn default() -> Self {
+        NotificationTemplates {
+            reservation_confirmed:
+                "[{shop}] {name}님, {date} {time} {staff} 디자이너 예약이 확정되었습니다."
+                    .to_string(),
+            reservation_reminder:
+                "[{shop}] {name}님, 내일 {time} {staff} 디자이너 예약이 있습니다. 방문을 기다리겠습니다."
+                    .to_string(),
+            stamp_reward_ready:
+                "[{shop}] {name}님, 스탬프 {stamps}개가 모였습니다. 다음 방문 시 혜택을 받아가세요!"
+                    .to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationSettings {
+    pub enabled: bool,
+    pub channel: NotificationChannel,
+    pub shop_name: String,
+    pub reminder_hour: u32,
+    pub stamp_reward_threshold: i32,
+    pub max_attempts: i32,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        NotificationSettings {
+            enabled: false,
+            channel: NotificationChannel::Sms,
+            shop_name: "타임헤어".to_string(),
+            reminder_hour: 10,
+            stamp_reward_threshold: 10,
+            max_attempts: 3,
+        }
+    }
+}
+
+// ==================== API Server ====================
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiServerSettings {
+    pub enabled: bool,
+    pub bind_address: String,
+    pub port: u16,
+    pub token: String,
+}
+
+impl Default for ApiServerSettings {
+    fn default() -> Self {
+        ApiServerSettings {
+            enabled: false,
+            bind_address: "0.0.0.0".to_string(),
+            port: 8787,
+            token: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiServerStatus {
+    pub running: bool,
+    pub address: Option<String>,
+}
+
+// ==================== Events ====================
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChangeEvent {
+    SeatStatusChanged {
+        seat_id: i32,
+        status: SeatStatus,
+    },
+    SessionStarted {
+        seat: Seat,
+    },
+    SessionCompleted {
+        seat_id: i32,
+        ledger_entry: LedgerEntry,
+    },
+    SessionCancelled {
+        seat_id: i32,
+    },
+    ReservationCreated {
+        reservation: Reservation,
+    },
+    ReservationUpdated {
+        reservation: Reservation,
+    },
+    ReservationDeleted {
+        reservation_id: String,
+    },
+    LedgerEntryRefunded {
+        ledger_entry_id: String,
+        refund: LedgerEntry,
+    },
+    LedgerEntryCorrected {
+        ledger_entry: LedgerEntry,
+    },
+    DayClosed {
+        closing: DailyClosing,
+    },
+    PrepaidBalanceChanged {
+        member_id: String,
+        transaction: PrepaidTransaction,
+    },
+    MemberPackageChanged {
+        member_package: MemberPackage,
+    },
+    PointsChanged {
+        member_id: String,
+        transaction: PointTransaction,
+    },
+    MemberTierChanged {
+        change: TierChange,
+    },
+    TreatmentNoteChanged {
+        note: TreatmentNote,
+    },
+    MemberPhotoAdded {
+        photo: MemberPhoto,
+    },
+    MembersMerged {
+        merge: MemberMerge,
+    },
+}
+
+impl ChangeEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChangeEvent::SeatStatusChanged { .. } => "seat_status_changed",
+            ChangeEvent::SessionStarted { .. } => "session_started",
+            ChangeEvent::SessionCompleted { .. } => "session_completed",
+            ChangeEvent::SessionCancelled { .. } => "session_cancelled",
+            ChangeEvent::ReservationCreated { .. } => "reservation_created",
+            ChangeEvent::ReservationUpdated { .. } => "reservation_updated",
+            ChangeEvent::ReservationDeleted { .. } => "reservation_deleted",
+            ChangeEvent::LedgerEntryRefunded { .. } => "ledger_entry_refunded",
+            ChangeEvent::LedgerEntryCorrected { .. } => "ledger_entry_corrected",
+            ChangeEvent::DayClosed { .. } => "day_closed",
+            ChangeEvent::PrepaidBalanceChanged { .. } => "prepaid_balance_changed",
+            ChangeEvent::MemberPackageChanged { .. } => "member_package_changed",
+            ChangeEvent::PointsChanged { .. } => "points_changed",
+            ChangeEvent::MemberTierChanged { .. } => "member_tier_changed",
+            ChangeEvent::TreatmentNoteChanged { .. } => "treatment_note_changed",
+            ChangeEvent::MemberPhotoAdded { .. } => "member_photo_added",
+            ChangeEvent::MembersMerged { .. } => "members_merged",
+        }
+    }
 }
 
 // ==================== Query Parameters ====================
@@ -292,6 +2156,13 @@ pub struct ReservationQuery {
     pub all: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Default)]
+pub struct NotificationQuery {
+    pub status: Option<String>,
+    pub member_id: Option<String>,
+    pub reservation_id: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Default)]
 pub struct LedgerQuery {
     pub date: Option<String>,
@@ -0,0 +1,427 @@
+use chrono::{DateTime, Duration, Local, TimeZone, Utc};
+use once_cell::sync::OnceCell;
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::database;
+use crate::models::*;
+
+static PROVIDER: OnceCell<Box<dyn NotificationProvider>> = OnceCell::new();
+
+const POLL_INTERVAL_SECS: u64 = 60;
+const RETRY_BACKOFF_MINUTES: i64 = 5;
+
+// ==================== Providers ====================
+pub trait NotificationProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Delivers one message and returns the provider's message id
+    fn send(&self, notification: &Notification) -> Result<String, String>;
+}
+
+// Appends messages to a JSON lines file instead of calling an SMS/KakaoTalk gateway
+pub struct LogProvider {
+    path: PathBuf,
+}
+
+impl LogProvider {
+    pub fn new(path: PathBuf) -> Self {
+        LogProvider { path }
+    }
+}
+
+impl NotificationProvider for LogProvider {
+    fn name(&self) -> &'static str {
+        "log"
+    }
+
+    fn send(&self, notification: &Notification) -> Result<String, String> {
+        let message_id = format!("log-{}", notification.id);
+        let line = serde_json::json!({
+            "message_id": message_id,
+            "channel": notification.channel,
+            "recipient": notification.recipient,
+            "body": notification.body,
+            "sent_at": Utc::now(),
+        });
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("알림 로그 열기 실패: {}", e))?;
+        writeln!(file, "{}", line).map_err(|e| format!("알림 로그 기록 실패: {}", e))?;
+
+        Ok(message_id)
+    }
+}
+
+pub fn init(app_data_dir: PathBuf) {
+    let provider = LogProvider::new(app_data_dir.join("notifications.log"));
+    if PROVIDER.set(Box::new(provider)).is_err() {
+        return;
+    }
+    let _ = database::release_sending_notifications();
+
+    std::thread::spawn(|| loop {
+        process_due();
+        std::thread::sleep(std::time::Duration::from_secs(POLL_INTERVAL_SECS));
+    });
+}
+
+// Sends every pending message whose time has come; returns how many were delivered
+pub fn process_due() -> usize {
+    let Some(provider) = PROVIDER.get() else {
+        return 0;
+    };
+    let settings = database::get_notification_settings();
+    let mut sent = 0;
+
+    for notification in database::get_due_notifications(Utc::now()) {
+        // The poller and a manual run can pick up the same row
+        if !database::claim_notification(&notification.id).unwrap_or(false) {
+            continue;
+        }
+
+        match attempt(
+            provider.as_ref(),
+            &notification,
+            settings.max_attempts,
+            Utc::now(),
+        ) {
+            Attempt::Sent(message_id) => {
+                if database::mark_notification_sent(&notification.id, &message_id).is_ok() {
+                    sent += 1;
+                }
+            }
+            Attempt::Retry { error, at } => {
+                let _ = database::mark_notification_failed(&notification.id, &error, Some(at));
+            }
+            Attempt::Failed(error) => {
+                let _ = database::mark_notification_failed(&notification.id, &error, None);
+            }
+        }
+    }
+
+    sent
+}
+
+#[derive(Debug, PartialEq)]
+enum Attempt {
+    Sent(String),
+    Retry { error: String, at: DateTime<Utc> },
+    Failed(String),
+}
+
+fn attempt(
+    provider: &dyn NotificationProvider,
+    notification: &Notification,
+    max_attempts: i32,
+    now: DateTime<Utc>,
+) -> Attempt {
+    match provider.send(notification) {
+        Ok(message_id) => Attempt::Sent(message_id),
+        Err(error) => {
+            let error = format!("[{}] {}", provider.name(), error);
+            match retry_at(notification.attempts + 1, max_attempts, now) {
+                Some(at) => Attempt::Retry { error, at },
+                None => Attempt::Failed(error),
+            }
+        }
+    }
+}
+
+// Waits a little longer after each failed attempt, up to `max_attempts`
+fn retry_at(attempts: i32, max_attempts: i32, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    (attempts < max_attempts)
+        .then(|| now + Duration::minutes(RETRY_BACKOFF_MINUTES * i64::from(attempts)))
+}
+
+// ==================== Templates ====================
+pub fn render(template: &str, values: &[(&str, String)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |text, (key, value)| {
+            text.replace(&format!("{{{}}}", key), value)
+        })
+}
+
+fn reservation_values(
+    settings: &NotificationSettings,
+    r: &Reservation,
+) -> Vec<(&'static str, String)> {
+    let local = r.reserved_at.with_timezone(&Local);
+    vec![
+        ("shop", settings.shop_name.clone()),
+        ("name", r.member_name.clone()),
+        ("date", local.format("%m월 %d일").to_string()),
+        ("time", local.format("%H:%M").to_string()),
+        ("staff", r.staff_name.clone()),
+    ]
+}
+
+fn reservation_recipient(r: &Reservation) -> Option<String> {
+    r.member_phone
+        .clone()
+        .filter(|p| !p.trim().is_empty())
+        .or_else(|| {
+            r.member_id
+                .as_deref()
+                .and_then(database::get_member_by_id)
+                .map(|m| m.phone)
+        })
+}
+
+// D-1 at the configured local hour
+fn reminder_time(
+    settings: &NotificationSettings,
+    reserved_at: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let day_before = reserved_at.with_timezone(&Local).date_naive() - Duration::days(1);
+    let naive = day_before.and_hms_opt(settings.reminder_hour.min(23), 0, 0)?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+// ==================== Scheduling ====================
+pub fn enqueue_reservation_confirmed(reservation: &Reservation) {
+    let settings = database::get_notification_settings();
+    if !settings.enabled {
+        return;
+    }
+    let Some(recipient) = reservation_recipient(reservation) else {
+        return;
+    };
+
+    let templates = database::get_notification_templates();
+    let body = render(
+        &templates.reservation_confirmed,
+        &reservation_values(&settings, reservation),
+    );
+    let _ = database::create_notification(
+        NotificationKind::ReservationConfirmed,
+        settings.channel,
+        reservation.member_id.as_deref(),
+        Some(&reservation.id),
+        &recipient,
+        &body,
+        Utc::now(),
+    );
+
+    schedule_reminder(&settings, &templates, reservation, &recipient);
+}
+
+// Drops pending messages for the reservation and queues a fresh reminder
+pub fn reschedule_reservation(reservation: &Reservation) {
+    let _ = database::cancel_reservation_notifications(&reservation.id);
+
+    let settings = database::get_notification_settings();
+    if !settings.enabled || reservation.status != ReservationStatus::Scheduled {
+        return;
+    }
+    let Some(recipient) = reservation_recipient(reservation) else {
+        return;
+    };
+
+    let templates = database::get_notification_templates();
+    schedule_reminder(&settings, &templates, reservation, &recipient);
+}
+
+fn schedule_reminder(
+    settings: &NotificationSettings,
+    templates: &NotificationTemplates,
+    reservation: &Reservation,
+    recipient: &str,
+) {
+    let Some(send_at) = reminder_time(settings, reservation.reserved_at) else {
+        return;
+    };
+    if send_at <= Utc::now() {
+        return;
+    }
+
+    let body = render(
+        &templates.reservation_reminder,
+        &reservation_values(settings, reservation),
+    );
+    let _ = database::create_notification(
+        NotificationKind::ReservationReminder,
+        settings.channel,
+        reservation.member_id.as_deref(),
+        Some(&reservation.id),
+        recipient,
+        &body,
+        send_at,
+    );
+}
+
+// Sent when a visit takes the member past the reward threshold
+pub fn enqueue_stamp_reward_ready(member: &Member, previous_stamps: i32) {
+    let settings = database::get_notification_settings();
+    let threshold = database::get_active_stamp_program()
+        .map(|p| p.stamps_required)
+        .unwrap_or(settings.stamp_reward_threshold);
+    if !settings.enabled || previous_stamps >= threshold || member.stamps < threshold {
+        return;
+    }
+
+    let templates = database::get_notification_templates();
+    let body = render(
+        &templates.stamp_reward_ready,
+        &[
+            ("shop", settings.shop_name.clone()),
+            ("name", member.name.clone()),
+            ("stamps", member.stamps.to_string()),
+        ],
+    );
+    let _ = database::create_notification(
+        NotificationKind::StampRewardReady,
+        settings.channel,
+        Some(&member.id),
+        None,
+        &member.phone,
+        &body,
+        Utc::now(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Timelike;
+
+    struct FakeProvider {
+        result: Result<String, String>,
+    }
+
+    impl NotificationProvider for FakeProvider {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn send(&self, _notification: &Notification) -> Result<String, String> {
+            self.result.clone()
+        }
+    }
+
+    fn notification(attempts: i32) -> Notification {
+        let now = Utc::now();
+        Notification {
+            id: "n1".to_string(),
+            kind: NotificationKind::ReservationReminder,
+            channel: NotificationChannel::Sms,
+            member_id: None,
+            reservation_id: None,
+            recipient: "+821012345678".to_string(),
+            body: "내일 예약".to_string(),
+            status: NotificationStatus::Sending,
+            attempts,
+            last_error: None,
+            provider_message_id: None,
+            scheduled_at: now,
+            sent_at: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn render_fills_known_placeholders() {
+        let text = render(
+            "{name}님, {date} {time} {name} {unknown}",
+            &[
+                ("name", "김철수".to_string()),
+                ("date", "03월 05일".to_string()),
+                ("time", "14:30".to_string()),
+            ],
+        );
+        assert_eq!(text, "김철수님, 03월 05일 14:30 김철수 {unknown}");
+    }
+
+    #[test]
+    fn reminder_is_the_day_before_at_the_configured_hour() {
+        let settings = NotificationSettings {
+            reminder_hour: 10,
+            ..Default::default()
+        };
+        let reserved_at = Local
+            .with_ymd_and_hms(2026, 3, 5, 14, 30, 0)
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let at = reminder_time(&settings, reserved_at)
+            .unwrap()
+            .with_timezone(&Local);
+        assert_eq!(
+            at.date_naive(),
+            chrono::NaiveDate::from_ymd_opt(2026, 3, 4).unwrap()
+        );
+        assert_eq!((at.hour(), at.minute()), (10, 0));
+    }
+
+    #[test]
+    fn reminder_hour_is_clamped_to_the_day() {
+        let settings = NotificationSettings {
+            reminder_hour: 30,
+            ..Default::default()
+        };
+        let reserved_at = Local
+            .with_ymd_and_hms(2026, 3, 1, 9, 0, 0)
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let at = reminder_time(&settings, reserved_at)
+            .unwrap()
+            .with_timezone(&Local);
+        assert_eq!(
+            at.date_naive(),
+            chrono::NaiveDate::from_ymd_opt(2026, 2, 28).unwrap()
+        );
+        assert_eq!(at.hour(), 23);
+    }
+
+    #[test]
+    fn successful_send_returns_the_message_id() {
+        let provider = FakeProvider {
+            result: Ok("m-1".to_string()),
+        };
+        let result = attempt(&provider, &notification(0), 3, Utc::now());
+        assert_eq!(result, Attempt::Sent("m-1".to_string()));
+    }
+
+    #[test]
+    fn failed_sends_back_off_then_give_up() {
+        let provider = FakeProvider {
+            result: Err("timeout".to_string()),
+        };
+        let now = Utc::now();
+
+        let first = attempt(&provider, &notification(0), 3, now);
+        assert_eq!(
+            first,
+            Attempt::Retry {
+                error: "[fake] timeout".to_string(),
+                at: now + Duration::minutes(5),
+            }
+        );
+
+        let second = attempt(&provider, &notification(1), 3, now);
+        assert_eq!(
+            second,
+            Attempt::Retry {
+                error: "[fake] timeout".to_string(),
+                at: now + Duration::minutes(10),
+            }
+        );
+
+        let last = attempt(&provider, &notification(2), 3, now);
+        assert_eq!(last, Attempt::Failed("[fake] timeout".to_string()));
+    }
+
+    #[test]
+    fn single_attempt_never_retries() {
+        assert_eq!(retry_at(1, 1, Utc::now()), None);
+    }
+}
@@ -0,0 +1,460 @@
+use std::collections::HashMap;
+
+use crate::database;
+use crate::models::*;
+
+// ==================== Payroll Report ====================
+// Commission and tips per designer for the entries matched by `query`.
+// Shared services are credited to each designer by their share.
+pub fn build_report(query: &LedgerQuery) -> Vec<StaffPayroll> {
+    let rules: Vec<CommissionRule> = database::get_commission_rules()
+        .into_iter()
+        .filter(|r| r.active)
+        .collect();
+    let mut monthly = MonthlyRevenue::default();
+    let mut payroll: HashMap<String, StaffPayroll> = HashMap::new();
+
+    let mut entries = database::get_ledger_entries(query);
+    entries.reverse();
+
+    for entry in &entries {
+        // Pass sales and their refunds have no designer to pay
+        if entry.staff_id.is_empty() {
+            continue;
+        }
+        let lines = match entry.entry_type {
+            LedgerEntryType::Service => commission_lines(entry, &rules, &mut monthly, |_| true),
+            LedgerEntryType::Refund => refund_lines(entry, &rules, &mut monthly),
+            LedgerEntryType::DepositForfeit | LedgerEntryType::PackageSale => continue,
+        };
+
+        // Tips stay with the designer who closed the sale
+        staff_payroll(&mut payroll, &entry.staff_id, &entry.staff_name).tips += entry.tip_amount;
+
+        for (staff_id, staff_name, revenue) in database::staff_revenue_split(entry) {
+            let staff = staff_payroll(&mut payroll, &staff_id, &staff_name);
+            staff.revenue += revenue;
+        }
+
+        for (staff_id, staff_name, line) in lines {
+            let staff = staff_payroll(&mut payroll, &staff_id, &staff_name);
+            if entry.entry_type == LedgerEntryType::Service {
+                staff.service_count += 1;
+            }
+            staff.commission += line.commission;
+            staff.lines.push(line);
+        }
+    }
+
+    let mut report: Vec<StaffPayroll> = payroll
+        .into_values()
+        .filter(|p| query.staff_id.as_ref().is_none_or(|id| *id == p.staff_id))
+        .map(|mut p| {
+            p.total_pay = p.commission + p.tips;
+            p
+        })
+        .collect();
+    report.sort_by(|a, b| a.staff_name.cmp(&b.staff_name));
+    report
+}
+
+fn staff_payroll<'a>(
+    payroll: &'a mut HashMap<String, StaffPayroll>,
+    staff_id: &str,
+    staff_name: &str,
+) -> &'a mut StaffPayroll {
+    payroll
+        .entry(staff_id.to_string())
+        .or_insert_with(|| StaffPayroll {
+            staff_id: staff_id.to_string(),
+            staff_name: staff_name.to_string(),
+            service_count: 0,
+            revenue: 0,
+            commission: 0,
+            tips: 0,
+            total_pay: 0,
+            lines: vec![],
+        })
+}
+
+// One line per service and designer, for the services `include` picks.
+// Package ticket visits are commissioned when the designer does them, on the
+// pass's price per visit; the pass sale itself has no designer.
+fn commission_lines(
+    entry: &LedgerEntry,
+    rules: &[CommissionRule],
+    monthly: &mut MonthlyRevenue,
+    include: impl Fn(&SelectedService) -> bool,
+) -> Vec<(String, String, CommissionLine)> {
+    let revenues = database::credited_revenue(entry);
+    let mut lines = vec![];
+
+    for (service, line_revenue) in entry
+        .services
+        .iter()
+        .zip(revenues)
+        .filter(|(s, _)| include(s))
+    {
+        let shares = database::split_line_revenue(entry, service, line_revenue);
+        let share_count = service.staff.len().max(1) as i32;
+
+        for (i, (staff_id, staff_name, revenue)) in shares.into_iter().enumerate() {
+            let rule = find_rule(rules, &staff_id, service.category.as_deref());
+            let commission = rule
+                .map(|rule| match rule.kind {
+                    CommissionKind::Percentage => percent_of(revenue, rule.value),
+                    // A shared service splits the fixed amount by share
+                    CommissionKind::FixedPerService => match service.staff.get(i) {
+                        Some(share) => percent_of(rule.value, share.share_percent),
+                        None => rule.value / share_count,
+                    },
+                    CommissionKind::Tiered => {
+                        let month_revenue = monthly.get(&staff_id, entry);
+                        let percent = rule
+                            .tiers
+                            .iter()
+                            .filter(|t| t.min_revenue <= month_revenue)
+                            .max_by_key(|t| t.min_revenue)
+                            .map(|t| t.percent)
+                            .unwrap_or(0);
+                        percent_of(revenue, percent)
+                    }
+                })
+                .unwrap_or(0);
+
+            lines.push((
+                staff_id,
+                staff_name,
+                CommissionLine {
+                    ledger_entry_id: entry.id.clone(),
+                    completed_at: entry.completed_at,
+                    service_name: service.name.clone(),
+                    category: service.category.clone(),
+                    revenue,
+                    rule_id: rule.map(|r| r.id.clone()),
+                    commission,
+                },
+            ));
+        }
+    }
+    lines
+}
+
+// A refund takes back the same share of commission as it takes of the sale's
+// money, and all of it for the ticket visits it gives back
+fn refund_lines(
+    entry: &LedgerEntry,
+    rules: &[CommissionRule],
+    monthly: &mut MonthlyRevenue,
+) -> Vec<(String, String, CommissionLine)> {
+    let Some(original) = entry
+        .refund_of
+        .as_deref()
+        .and_then(database::get_ledger_entry_by_id)
+    else {
+        return vec![];
+    };
+    if original.entry_type != LedgerEntryType::Service {
+        return vec![];
+    }
+
+    let mut by_staff: Vec<(String, String, i32, i32)> = vec![];
+    if original.total_price != 0 {
+        let charged =
+            commission_lines(&original, rules, monthly, |s| s.member_package_id.is_none());
+        for (staff_id, staff_name, line) in charged {
+            add_line(
+                &mut by_staff,
+                staff_id,
+                staff_name,
+                line.revenue,
+                line.commission,
+            );
+        }
+        let scale = |amount: i32| {
+            (i64::from(amount) * i64::from(entry.total_price) / i64::from(original.total_price))
+                as i32
+        };
+        for s in by_staff.iter_mut() {
+            s.2 = scale(s.2);
+            s.3 = scale(s.3);
+        }
+    }
+    if database::refund_gives_back_visits(entry, &original) {
+        let visits = commission_lines(&original, rules, monthly, |s| s.member_package_id.is_some());
+        for (staff_id, staff_name, line) in visits {
+            add_line(
+                &mut by_staff,
+                staff_id,
+                staff_name,
+                -line.revenue,
+                -line.commission,
+            );
+        }
+    }
+
+    by_staff
+        .into_iter()
+        .filter(|s| s.2 != 0 || s.3 != 0)
+        .map(|(staff_id, staff_name, revenue, commission)| {
+            (
+                staff_id,
+                staff_name,
+                CommissionLine {
+                    ledger_entry_id: entry.id.clone(),
+                    completed_at: entry.completed_at,
+                    service_name: "환불".to_string(),
+                    category: None,
+                    revenue,
+                    rule_id: None,
+                    commission,
+                },
+            )
+        })
+        .collect()
+}
+
+fn add_line(
+    by_staff: &mut Vec<(String, String, i32, i32)>,
+    staff_id: String,
+    staff_name: String,
+    revenue: i32,
+    commission: i32,
+) {
+    match by_staff.iter_mut().find(|s| s.0 == staff_id) {
+        Some(s) => {
+            s.2 += revenue;
+            s.3 += commission;
+        }
+        None => by_staff.push((staff_id, staff_name, revenue, commission)),
+    }
+}
+
+// Most specific rule first: staff + category, staff, category, shop default
+fn find_rule<'a>(
+    rules: &'a [CommissionRule],
+    staff_id: &str,
+    category: Option<&str>,
+) -> Option<&'a CommissionRule> {
+    rules
+        .iter()
+        .filter(|r| r.staff_id.as_deref().is_none_or(|id| id == staff_id))
+        .filter(|r| {
+            r.service_category
+                .as_deref()
+                .is_none_or(|c| Some(c) == category)
+        })
+        .max_by_key(|r| {
+            (
+                r.staff_id.is_some(),
+                r.service_category.is_some(),
+                std::cmp::Reverse(r.created_at),
+            )
+        })
+}
+
+fn percent_of(amount: i32, percent: i32) -> i32 {
+    (i64::from(amount) * i64::from(percent) / 100) as i32
+}
+
+// Net revenue per designer per month, used to pick the tier
+#[derive(Default)]
+struct MonthlyRevenue {
+    cache: HashMap<String, HashMap<String, i32>>,
+}
+
+impl MonthlyRevenue {
+    // Months follow the ledger's local business dates
+    fn get(&mut self, staff_id: &str, entry: &LedgerEntry) -> i32 {
+        let month = entry
+            .completed_at
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m")
+            .to_string();
+
+        let by_staff = self.cache.entry(month.clone()).or_insert_with(|| {
+            let query = LedgerQuery {
+                date: None,
+                start_date: Some(format!("{}-01", month)),
+                end_date: Some(format!("{}-31", month)),
+                staff_id: None,
+            };
+            database::get_ledger_entries(&query)
+                .iter()
+                .filter(|e| e.entry_type != LedgerEntryType::DepositForfeit)
+                .flat_map(database::staff_revenue_split)
+                .fold(HashMap::new(), |mut map, (id, _, revenue)| {
+                    *map.entry(id).or_insert(0) += revenue;
+                    map
+                })
+        });
+        by_staff.get(staff_id).copied().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request<T: serde::de::DeserializeOwned>(value: serde_json::Value) -> T {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn pass_visits_are_commissioned_when_used() {
+        database::init_test_database();
+        let staff = crate::create_staff(request(json!({ "name": "회수권 디자이너" }))).unwrap();
+        crate::create_commission_rule(request(json!({
+            "staff_id": staff.id, "kind": "percentage", "value": 10, "active": true
+        })))
+        .unwrap();
+        let package = crate::create_package(request(json!({
+            "name": "커트 5회권", "service_name": "커트", "total_count": 5, "price": 100000, "active": true
+        })))
+        .unwrap();
+        let member = crate::create_member(request(
+            json!({ "name": "회수권", "phone": "010-4444-0001" }),
+        ))
+        .unwrap();
+        crate::purchase_package(
+            member.id.clone(),
+            request(json!({
+                "package_id": package.id,
+                "method": "cash",
+                "created_by": { "username": "admin", "password": "12344321" },
+            })),
+        )
+        .unwrap();
+
+        crate::start_service(
+            1,
+            request(json!({
+                "member_id": member.id, "member_name": member.name,
+                "staff_id": staff.id, "staff_name": staff.name,
+                "services": [{ "name": "커트", "length": null, "price": 25000 }],
+                "total_price": 25000, "reservation_id": null
+            })),
+        )
+        .unwrap();
+        let visit = crate::complete_service(1, None).unwrap();
+        assert_eq!(visit.total_price, 0);
+
+        let report = || {
+            build_report(&LedgerQuery {
+                staff_id: Some(staff.id.clone()),
+                ..Default::default()
+            })
+        };
+        // The pass sale has no designer; the visit earns a fifth of the pass
+        let payroll = report();
+        assert_eq!(payroll.len(), 1);
+        assert_eq!(
+            (
+                payroll[0].service_count,
+                payroll[0].revenue,
+                payroll[0].commission
+            ),
+            (1, 20000, 2000)
+        );
+
+        // Voiding the visit gives the ticket back and takes the commission with it
+        crate::refund_ledger_entry(
+            visit.id.clone(),
+            request(json!({
+                "amount": 0, "reason": "취소",
+                "approved_by": { "username": "admin", "password": "12344321" }
+            })),
+        )
+        .unwrap();
+        let payroll = report();
+        assert_eq!((payroll[0].revenue, payroll[0].commission), (0, 0));
+    }
+
+    fn rule(staff_id: Option<&str>, category: Option<&str>, value: i32) -> CommissionRule {
+        CommissionRule {
+            id: format!("{:?}-{:?}", staff_id, category),
+            staff_id: staff_id.map(str::to_string),
+            service_category: category.map(str::to_string),
+            kind: CommissionKind::Percentage,
+            value,
+            tiers: vec![],
+            active: true,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn commission_rounds_toward_zero() {
+        assert_eq!(percent_of(33333, 15), 4999);
+        assert_eq!(percent_of(9999, 10), 999);
+        assert_eq!(percent_of(-9999, 10), -999);
+        assert_eq!(percent_of(10000, 33), 3300);
+        assert_eq!(percent_of(2_000_000_000, 50), 1_000_000_000);
+    }
+
+    #[test]
+    fn most_specific_rule_wins() {
+        let rules = vec![
+            rule(None, None, 10),
+            rule(None, Some("펌"), 20),
+            rule(Some("kim"), None, 30),
+            rule(Some("kim"), Some("펌"), 40),
+        ];
+        let value = |staff: &str, category: Option<&str>| {
+            find_rule(&rules, staff, category).map(|r| r.value)
+        };
+
+        assert_eq!(value("kim", Some("펌")), Some(40));
+        assert_eq!(value("kim", Some("커트")), Some(30));
+        assert_eq!(value("lee", Some("펌")), Some(20));
+        assert_eq!(value("lee", None), Some(10));
+        assert!(find_rule(&rules[1..2], "lee", Some("커트")).is_none());
+    }
+
+    #[test]
+    fn partial_refund_scales_the_rounded_commission() {
+        database::init_test_database();
+        let staff = crate::create_staff(request(json!({ "name": "환불 디자이너" }))).unwrap();
+        crate::create_commission_rule(request(json!({
+            "staff_id": staff.id, "kind": "percentage", "value": 15, "active": true
+        })))
+        .unwrap();
+
+        crate::start_service(
+            2,
+            request(json!({
+                "member_id": null, "member_name": "손님",
+                "staff_id": staff.id, "staff_name": staff.name,
+                "services": [{ "name": "펌", "length": null, "price": 33333 }],
+                "total_price": 33333, "reservation_id": null
+            })),
+        )
+        .unwrap();
+        let sale = crate::complete_service(
+            2,
+            Some(request(json!({
+                "payments": [{ "method": "cash", "amount": 33333, "approval_number": null }]
+            }))),
+        )
+        .unwrap();
+        crate::refund_ledger_entry(
+            sale.id.clone(),
+            request(json!({
+                "amount": 10000, "reason": "부분 환불",
+                "approved_by": { "username": "admin", "password": "12344321" }
+            })),
+        )
+        .unwrap();
+
+        let payroll = build_report(&LedgerQuery {
+            staff_id: Some(staff.id.clone()),
+            ..Default::default()
+        });
+        let commissions: Vec<i32> = payroll[0].lines.iter().map(|l| l.commission).collect();
+        // 4999 * 10000 / 33333 = 1499.7, cut to 1499 like the sale itself
+        assert_eq!(commissions, vec![4999, -1499]);
+        assert_eq!((payroll[0].revenue, payroll[0].commission), (23333, 3500));
+    }
+}
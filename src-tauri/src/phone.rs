@@ -0,0 +1,187 @@
+// ==================== Phone Numbers ====================
+// Korean numbers are stored in E.164 ("+821012345678") so every way of typing
+// the same number finds the same member, and shown dashed ("010-1234-5678").
+
+const INVALID: &str = "올바른 전화번호 형식이 아닙니다";
+
+// Accepts "010-1234-5678", "01012345678", "+82 10 1234 5678", "02)123-4567"
+// and similar; returns the E.164 form
+pub fn normalize(input: &str) -> Result<String, String> {
+    let trimmed = input.trim();
+    let allowed = |c: char| c.is_ascii_digit() || matches!(c, ' ' | '-' | '.' | '(' | ')');
+    let international = trimmed.starts_with('+');
+    let body = trimmed.strip_prefix('+').unwrap_or(trimmed);
+    if body.is_empty() || !body.chars().all(allowed) {
+        return Err(INVALID.to_string());
+    }
+
+    let digits: String = body.chars().filter(|c| c.is_ascii_digit()).collect();
+    // Domestic numbers never start with 8, so a leading 82 is the country code
+    let national = match digits.strip_prefix("82") {
+        Some(rest) => format!("0{}", rest.strip_prefix('0').unwrap_or(rest)),
+        None if international => return Err("국내 전화번호만 등록할 수 있습니다".to_string()),
+        None => digits,
+    };
+
+    if !is_valid_national(&national) {
+        return Err(INVALID.to_string());
+    }
+    // 15xx/16xx/18xx numbers have no trunk 0 to drop
+    let subscriber = national.strip_prefix('0').unwrap_or(&national);
+    Ok(format!("+82{}", subscriber))
+}
+
+fn is_valid_national(national: &str) -> bool {
+    let len = national.len();
+    let prefix = |p: &str| national.starts_with(p);
+    if prefix("01") {
+        // Mobile: 010, 011, 016, 017, 018, 019
+        matches!(national.as_bytes().get(2), Some(b'0' | b'1' | b'6'..=b'9'))
+            && (10..=11).contains(&len)
+    } else if prefix("02") {
+        (9..=10).contains(&len)
+    } else if prefix("050") {
+        (11..=12).contains(&len)
+    } else if prefix("070") || prefix("080") {
+        (10..=11).contains(&len)
+    } else if prefix("0") {
+        // Area codes 031 to 064
+        matches!(national.as_bytes().get(1), Some(b'3'..=b'6')) && (10..=11).contains(&len)
+    } else {
+        // Representative numbers such as 1588-1234
+        (prefix("15") || prefix("16") || prefix("18")) && len == 8
+    }
+}
+
+// The dashed domestic form; anything not in E.164 is shown as stored
+pub fn format(phone: &str) -> String {
+    // Rows written before numbers were normalized may hold anything after +82
+    let Some(rest) = phone
+        .strip_prefix("+82")
+        .filter(|rest| !rest.is_empty() && rest.bytes().all(|b| b.is_ascii_digit()))
+    else {
+        return phone.to_string();
+    };
+    let national = if rest.len() == 8 && rest.starts_with('1') {
+        rest.to_string()
+    } else {
+        format!("0{}", rest)
+    };
+
+    let split = |a: usize, b: usize| {
+        format!(
+            "{}-{}-{}",
+            &national[..a],
+            &national[a..a + b],
+            &national[a + b..]
+        )
+    };
+    match national.len() {
+        8 => format!("{}-{}", &national[..4], &national[4..]),
+        9 | 10 if national.starts_with("02") => split(2, national.len() - 6),
+        // 0505-123-4567 style safe numbers
+        11 | 12 if national.starts_with("050") => split(4, national.len() - 8),
+        10 => split(3, 3),
+        11 => split(3, 4),
+        12 => split(4, 4),
+        _ => national,
+    }
+}
+
+// A LIKE pattern matching stored numbers against the digits typed into a
+// search box, e.g. "010-1234" or "5678"
+pub fn search_pattern(search: &str) -> Option<String> {
+    let search = search.trim();
+    let digits: String = search.chars().filter(|c| c.is_ascii_digit()).collect();
+    if digits.len() < 3
+        || !search
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '-' || c == ' ')
+    {
+        return None;
+    }
+    Some(format!("%{}%", digits.strip_prefix('0').unwrap_or(&digits)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_accepts_common_forms() {
+        for input in [
+            "010-1234-5678",
+            "01012345678",
+            " 010.1234.5678 ",
+            "+82 10 1234 5678",
+            "+82 010-1234-5678",
+            "821012345678",
+        ] {
+            assert_eq!(normalize(input).unwrap(), "+821012345678", "{}", input);
+        }
+        assert_eq!(normalize("02)123-4567").unwrap(), "+8221234567");
+        assert_eq!(normalize("031-123-4567").unwrap(), "+82311234567");
+        assert_eq!(normalize("0505-123-4567").unwrap(), "+825051234567");
+        assert_eq!(normalize("1588-1234").unwrap(), "+8215881234");
+    }
+
+    #[test]
+    fn normalize_rejects_invalid_numbers() {
+        for input in [
+            "",
+            "+",
+            "010-1234",
+            "010-1234-56789",
+            "012-1234-5678",
+            "010-1234-567a",
+            "０１０-1234-5678",
+            "1234-5678",
+        ] {
+            assert_eq!(normalize(input), Err(INVALID.to_string()), "{}", input);
+        }
+        assert_eq!(
+            normalize("+1 212 555 0100"),
+            Err("국내 전화번호만 등록할 수 있습니다".to_string())
+        );
+    }
+
+    #[test]
+    fn format_dashes_stored_numbers() {
+        assert_eq!(format("+821012345678"), "010-1234-5678");
+        assert_eq!(format("+82111234567"), "011-123-4567");
+        assert_eq!(format("+8221234567"), "02-123-4567");
+        assert_eq!(format("+82212345678"), "02-1234-5678");
+        assert_eq!(format("+82311234567"), "031-123-4567");
+        assert_eq!(format("+825051234567"), "0505-123-4567");
+        assert_eq!(format("+8215881234"), "1588-1234");
+    }
+
+    #[test]
+    fn format_leaves_unnormalized_rows_alone() {
+        assert_eq!(format("010-1234-5678"), "010-1234-5678");
+        assert_eq!(format("+82"), "+82");
+        assert_eq!(format("+82 10-1234-5678"), "+82 10-1234-5678");
+        assert_eq!(format("+82홍길동전화번호"), "+82홍길동전화번호");
+        assert_eq!(format("+821０1234５678"), "+821０1234５678");
+    }
+
+    #[test]
+    fn format_round_trips_normalize() {
+        for input in ["010-1234-5678", "02-123-4567", "064-123-4567", "1588-1234"] {
+            assert_eq!(format(&normalize(input).unwrap()), input);
+        }
+    }
+
+    #[test]
+    fn search_pattern_drops_the_trunk_zero() {
+        assert_eq!(search_pattern("010-1234").as_deref(), Some("%101234%"));
+        assert_eq!(search_pattern(" 5678 ").as_deref(), Some("%5678%"));
+        assert_eq!(
+            search_pattern("010 1234 5678").as_deref(),
+            Some("%1012345678%")
+        );
+        assert_eq!(search_pattern("12"), None);
+        assert_eq!(search_pattern("홍길동"), None);
+        assert_eq!(search_pattern("010-12ab"), None);
+    }
+}
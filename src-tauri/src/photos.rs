@@ -0,0 +1,122 @@
+use image::ImageFormat;
+use once_cell::sync::OnceCell;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+// ==================== Photo Storage ====================
+// Photos live under the app data dir, named by the SHA-256 of their content
+// so the same image attached twice is stored once:
+//   photos/originals/<hash>.<ext>
+//   photos/thumbnails/<hash>.jpg
+static PHOTO_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+const THUMBNAIL_SIZE: u32 = 320;
+
+pub struct StoredPhoto {
+    pub content_hash: String,
+    pub extension: String,
+    pub width: u32,
+    pub height: u32,
+    pub file_size: i64,
+}
+
+pub fn init(app_data_dir: PathBuf) {
+    let dir = app_data_dir.join("photos");
+    std::fs::create_dir_all(dir.join("originals")).ok();
+    std::fs::create_dir_all(dir.join("thumbnails")).ok();
+    PHOTO_DIR.set(dir).ok();
+}
+
+fn photo_dir() -> Result<&'static PathBuf, String> {
+    PHOTO_DIR
+        .get()
+        .ok_or("사진 저장 경로를 찾을 수 없습니다".to_string())
+}
+
+pub fn original_path(content_hash: &str, extension: &str) -> PathBuf {
+    PHOTO_DIR
+        .get()
+        .map(|d| d.join("originals"))
+        .unwrap_or_default()
+        .join(format!("{}.{}", content_hash, extension))
+}
+
+pub fn thumbnail_path(content_hash: &str) -> PathBuf {
+    PHOTO_DIR
+        .get()
+        .map(|d| d.join("thumbnails"))
+        .unwrap_or_default()
+        .join(format!("{}.jpg", content_hash))
+}
+
+// Checks the bytes are an image, then writes the original and its thumbnail
+// unless a photo with the same content is already stored
+pub fn store(bytes: &[u8]) -> Result<StoredPhoto, String> {
+    photo_dir()?;
+    let format = image::guess_format(bytes).map_err(|_| "지원하지 않는 이미지 형식입니다")?;
+    let extension = match format {
+        ImageFormat::Jpeg => "jpg",
+        ImageFormat::Png => "png",
+        ImageFormat::WebP => "webp",
+        _ => return Err("JPG, PNG, WEBP 사진만 첨부할 수 있습니다".to_string()),
+    };
+    let image = image::load_from_memory_with_format(bytes, format)
+        .map_err(|e| format!("사진을 읽을 수 없습니다: {}", e))?;
+
+    let content_hash = format!("{:x}", Sha256::digest(bytes));
+    let original = original_path(&content_hash, extension);
+    if !original.exists() {
+        std::fs::write(&original, bytes).map_err(|e| format!("사진 저장 실패: {}", e))?;
+    }
+    let thumbnail = thumbnail_path(&content_hash);
+    if !thumbnail.exists() {
+        image
+            .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+            .to_rgb8()
+            .save_with_format(&thumbnail, ImageFormat::Jpeg)
+            .map_err(|e| format!("썸네일 생성 실패: {}", e))?;
+    }
+
+    Ok(StoredPhoto {
+        content_hash,
+        extension: extension.to_string(),
+        width: image.width(),
+        height: image.height(),
+        file_size: bytes.len() as i64,
+    })
+}
+
+// ==================== Photo Backup ====================
+// Photos are copied next to the database backup, into `<backup>.photos`
+pub fn backup_dir(backup_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.photos", backup_path))
+}
+
+pub fn backup(backup_path: &str) -> Result<(), String> {
+    copy_missing(photo_dir()?, &backup_dir(backup_path))
+        .map_err(|e| format!("사진 백업 실패: {}", e))
+}
+
+// Backups made before photos existed have no photo folder
+pub fn restore(backup_path: &str) -> Result<(), String> {
+    let source = backup_dir(backup_path);
+    if !source.is_dir() {
+        return Ok(());
+    }
+    copy_missing(&source, photo_dir()?).map_err(|e| format!("사진 복원 실패: {}", e))
+}
+
+// Files are named by content, so ones already at the target are skipped
+fn copy_missing(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_missing(&entry.path(), &target)?;
+        } else if !target.exists() {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
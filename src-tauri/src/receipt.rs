@@ -0,0 +1,610 @@
+use chrono::Local;
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::models::*;
+
+const PRINTER_PORT: u16 = 9100;
+const PRINTER_TIMEOUT_SECS: u64 = 5;
+
+// Receipt content shared by every output format
+enum Line {
+    Title(String),
+    Center(String),
+    Text(String),
+    Pair(String, String),
+    Rule,
+}
+
+fn build(entry: &LedgerEntry, settings: &ReceiptSettings, reprint: bool) -> Vec<Line> {
+    let mut lines = vec![Line::Title(settings.shop_name.clone())];
+
+    if !settings.business_number.trim().is_empty() {
+        lines.push(Line::Center(format!(
+            "사업자번호 {}",
+            settings.business_number.trim()
+        )));
+    }
+    if !settings.address.trim().is_empty() {
+        lines.push(Line::Center(settings.address.trim().to_string()));
+    }
+    if !settings.phone.trim().is_empty() {
+        lines.push(Line::Center(format!("Tel {}", settings.phone.trim())));
+    }
+    lines.push(Line::Rule);
+
+    if reprint {
+        lines.push(Line::Center("[재발행]".to_string()));
+    }
+    lines.push(Line::Center(
+        match entry.entry_type {
+            LedgerEntryType::Service => "영수증",
+            LedgerEntryType::DepositForfeit => "예약금 위약금 영수증",
+            LedgerEntryType::Refund => "환불 영수증",
+            LedgerEntryType::PackageSale => "회수권 구매 영수증",
+        }
+        .to_string(),
+    ));
+    lines.push(Line::Pair(
+        "일시".to_string(),
+        entry
+            .completed_at
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+    ));
+    lines.push(Line::Pair(
+        "영수증번호".to_string(),
+        receipt_number(&entry.id),
+    ));
+    lines.push(Line::Pair("담당".to_string(), entry.staff_name.clone()));
+    lines.push(Line::Pair("고객".to_string(), entry.member_name.clone()));
+    if let Some(original_id) = &entry.refund_of {
+        lines.push(Line::Pair(
+            "원거래".to_string(),
+            receipt_number(original_id),
+        ));
+    }
+    if let Some(reason) = &entry.refund_reason {
+        lines.push(Line::Text(format!("사유: {}", reason)));
+    }
+    lines.push(Line::Rule);
+
+    for service in &entry.services {
+        let name = match &service.length {
+            Some(length) if !length.is_empty() => format!("{} ({})", service.name, length),
+            _ => service.name.clone(),
+        };
+        let price = match &service.member_package_id {
+            Some(_) => "회수권".to_string(),
+            None => format_won(service.price),
+        };
+        lines.push(Line::Pair(name, price));
+    }
+    if !entry.services.is_empty() {
+        lines.push(Line::Rule);
+    }
+
+    if entry.discount_amount > 0 {
+        lines.push(Line::Pair(
+            "합계".to_string(),
+            format_won(entry.gross_amount),
+        ));
+        for discount in &entry.discounts {
+            lines.push(Line::Pair(
+                format!("할인 {}", discount.name),
+                format_won(-discount.amount),
+            ));
+        }
+    }
+    lines.push(Line::Pair(
+        if entry.entry_type == LedgerEntryType::Refund {
+            "환불금액"
+        } else {
+            "결제금액"
+        }
+        .to_string(),
+        format_won(entry.total_price),
+    ));
+    for payment in &entry.payments {
+        let label = match &payment.approval_number {
+            Some(approval) if !approval.is_empty() => {
+                format!("  {} (승인 {})", method_label(payment.method), approval)
+            }
+            _ => format!("  {}", method_label(payment.method)),
+        };
+        lines.push(Line::Pair(label, format_won(payment.amount)));
+    }
+    if entry.tip_amount > 0 {
+        lines.push(Line::Pair("팁".to_string(), format_won(entry.tip_amount)));
+    }
+    lines.push(Line::Rule);
+
+    for footer in settings.footer.lines().filter(|l| !l.trim().is_empty()) {
+        lines.push(Line::Center(footer.trim().to_string()));
+    }
+
+    lines
+}
+
+pub fn receipt_number(ledger_entry_id: &str) -> String {
+    ledger_entry_id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .take(8)
+        .collect::<String>()
+        .to_uppercase()
+}
+
+fn method_label(method: PaymentMethod) -> &'static str {
+    match method {
+        PaymentMethod::Cash => "현금",
+        PaymentMethod::Card => "카드",
+        PaymentMethod::Transfer => "계좌이체",
+        PaymentMethod::Deposit => "예약금",
+        PaymentMethod::Prepaid => "선불권",
+        PaymentMethod::Points => "포인트",
+    }
+}
+
+fn format_won(amount: i32) -> String {
+    let digits = amount.unsigned_abs().to_string();
+    let mut grouped = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    if amount < 0 {
+        format!("-{}", grouped)
+    } else {
+        grouped
+    }
+}
+
+// ==================== Layout ====================
+// Hangul and other wide characters take two columns on receipt printers
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA960..=0xA97F
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6 => 2,
+        _ => 1,
+    }
+}
+
+fn text_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut current = String::new();
+    let mut current_width = 0;
+
+    for c in text.chars() {
+        let w = char_width(c);
+        if current_width + w > width && !current.is_empty() {
+            lines.push(std::mem::take(&mut current));
+            current_width = 0;
+        }
+        current.push(c);
+        current_width += w;
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+
+    lines
+}
+
+fn center(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(text_width(text)) / 2;
+    format!("{}{}", " ".repeat(padding), text)
+}
+
+// Left text and right-aligned value; the value moves to its own line when both do not fit
+fn pair(left: &str, right: &str, width: usize) -> Vec<String> {
+    let used = text_width(left) + text_width(right);
+    if used < width {
+        return vec![format!("{}{}{}", left, " ".repeat(width - used), right)];
+    }
+
+    let mut lines = wrap(left, width);
+    lines.push(format!(
+        "{}{}",
+        " ".repeat(width.saturating_sub(text_width(right))),
+        right
+    ));
+    lines
+}
+
+fn layout(line: &Line, width: usize) -> Vec<String> {
+    match line {
+        Line::Title(text) | Line::Center(text) => {
+            wrap(text, width).iter().map(|l| center(l, width)).collect()
+        }
+        Line::Text(text) => wrap(text, width),
+        Line::Pair(left, right) => pair(left, right, width),
+        Line::Rule => vec!["-".repeat(width)],
+    }
+}
+
+// ==================== Text ====================
+pub fn render_text(entry: &LedgerEntry, settings: &ReceiptSettings, reprint: bool) -> String {
+    let width = settings.paper_width.max(24);
+    build(entry, settings, reprint)
+        .iter()
+        .flat_map(|line| layout(line, width))
+        .map(|l| format!("{}\n", l.trim_end()))
+        .collect()
+}
+
+// ==================== ESC/POS ====================
+const ESC: u8 = 0x1B;
+const GS: u8 = 0x1D;
+const FS: u8 = 0x1C;
+
+// Korean text is sent as EUC-KR (KS X 1001) with the printer in double-byte mode
+pub fn render_escpos(entry: &LedgerEntry, settings: &ReceiptSettings, reprint: bool) -> Vec<u8> {
+    let width = settings.paper_width.max(24);
+    let mut out = vec![
+        ESC, b'@', // initialize
+        ESC, b'R', 13, // international character set: Korea
+        FS, b'&', // double-byte character mode
+    ];
+
+    for line in build(entry, settings, reprint) {
+        match &line {
+            Line::Title(text) => {
+                out.extend_from_slice(&[ESC, b'a', 1, GS, b'!', 0x11]);
+                for l in wrap(text, width / 2) {
+                    out.extend(encode_line(&l));
+                }
+                out.extend_from_slice(&[GS, b'!', 0x00, ESC, b'a', 0]);
+            }
+            _ => {
+                for l in layout(&line, width) {
+                    out.extend(encode_line(l.trim_end()));
+                }
+            }
+        }
+    }
+
+    // Feed past the cutter and cut
+    out.extend_from_slice(&[ESC, b'd', 4, GS, b'V', 66, 0]);
+    out
+}
+
+fn encode_line(text: &str) -> Vec<u8> {
+    let (bytes, _, _) = encoding_rs::EUC_KR.encode(text);
+    let mut line = bytes.into_owned();
+    line.push(b'\n');
+    line
+}
+
+pub fn print_escpos(address: &str, bytes: &[u8]) -> Result<(), String> {
+    let address = if address.contains(':') {
+        address.to_string()
+    } else {
+        format!("{}:{}", address, PRINTER_PORT)
+    };
+    let socket = address
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .ok_or_else(|| format!("프린터 주소를 찾을 수 없습니다: {}", address))?;
+
+    let timeout = Duration::from_secs(PRINTER_TIMEOUT_SECS);
+    let mut stream = TcpStream::connect_timeout(&socket, timeout)
+        .map_err(|e| format!("프린터 연결 실패: {}", e))?;
+    stream
+        .set_write_timeout(Some(timeout))
+        .and_then(|_| stream.write_all(bytes))
+        .and_then(|_| stream.flush())
+        .map_err(|e| format!("영수증 출력 실패: {}", e))
+}
+
+// ==================== PDF ====================
+// A single receipt-sized page using the non-embedded Adobe-Korea1 font, so
+// no font file has to ship with the app. The half-width CMap keeps ASCII at
+// half the width of Hangul, which preserves the column layout.
+const PDF_FONT_SIZE: f32 = 9.0;
+const PDF_TITLE_SIZE: f32 = 14.0;
+const PDF_MARGIN: f32 = 12.0;
+
+pub fn render_pdf(entry: &LedgerEntry, settings: &ReceiptSettings, reprint: bool) -> Vec<u8> {
+    let width = settings.paper_width.max(24);
+    let column = PDF_FONT_SIZE / 2.0;
+
+    // (font size, x offset, text) for each printed row
+    let mut rows: Vec<(f32, f32, String)> = vec![];
+    for line in build(entry, settings, reprint) {
+        match &line {
+            Line::Title(text) => {
+                let title_columns = (width as f32 * column / (PDF_TITLE_SIZE / 2.0)) as usize;
+                for l in wrap(text, title_columns) {
+                    let x = (width as f32 * column - text_width(&l) as f32 * PDF_TITLE_SIZE / 2.0)
+                        / 2.0;
+                    rows.push((PDF_TITLE_SIZE, x.max(0.0), l));
+                }
+            }
+            _ => {
+                for l in layout(&line, width) {
+                    rows.push((PDF_FONT_SIZE, 0.0, l));
+                }
+            }
+        }
+    }
+
+    let page_width = width as f32 * column + PDF_MARGIN * 2.0;
+    let page_height = rows.iter().map(|(size, _, _)| size * 1.4).sum::<f32>() + PDF_MARGIN * 2.0;
+
+    let mut content = String::from("BT\n");
+    let mut y = page_height - PDF_MARGIN;
+    for (size, x, text) in &rows {
+        y -= size * 1.4;
+        content.push_str(&format!(
+            "/F1 {} Tf\n1 0 0 1 {:.2} {:.2} Tm\n<{}> Tj\n",
+            size,
+            PDF_MARGIN + x,
+            y,
+            ucs2_hex(text.trim_end())
+        ));
+    }
+    content.push_str("ET\n");
+
+    pdf_document(page_width, page_height, &content)
+}
+
+fn ucs2_hex(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            let code = u16::try_from(c as u32).unwrap_or(u16::from(b'?'));
+            format!("{:04X}", code)
+        })
+        .collect()
+}
+
+fn pdf_document(width: f32, height: f32, content: &str) -> Vec<u8> {
+    let objects = [
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources << /Font << /F1 5 0 R >> >> /Contents 4 0 R >>",
+            width, height
+        ),
+        format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ),
+        "<< /Type /Font /Subtype /Type0 /BaseFont /HYGoThic-Medium /Encoding /UniKS-UCS2-HW-H /DescendantFonts [6 0 R] >>".to_string(),
+        "<< /Type /Font /Subtype /CIDFontType0 /BaseFont /HYGoThic-Medium /CIDSystemInfo << /Registry (Adobe) /Ordering (Korea1) /Supplement 2 >> /FontDescriptor 7 0 R /DW 1000 /W [1 100 500 8094 8190 500] >>".to_string(),
+        "<< /Type /FontDescriptor /FontName /HYGoThic-Medium /Flags 6 /FontBBox [-6 -145 1003 880] /ItalicAngle 0 /Ascent 880 /Descent -120 /CapHeight 880 /StemV 59 >>".to_string(),
+    ];
+
+    let mut pdf = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+    let mut offsets = vec![];
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend(format!("{} 0 obj\n{}\nendobj\n", i + 1, object).into_bytes());
+    }
+
+    let xref = pdf.len();
+    pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+    for offset in offsets {
+        pdf.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+    }
+    pdf.extend(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .into_bytes(),
+    );
+
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn entry() -> LedgerEntry {
+        // Built from local time so the printed date does not depend on the machine's zone
+        let completed_at = Local
+            .with_ymd_and_hms(2026, 3, 14, 15, 30, 0)
+            .unwrap()
+            .with_timezone(&Utc);
+        let service = |id: i32, name: &str, length: Option<&str>, price: i32| SelectedService {
+            id,
+            service_session_id: None,
+            reservation_id: None,
+            ledger_entry_id: Some("a1b2c3d4-e5f6".to_string()),
+            name: name.to_string(),
+            length: length.map(str::to_string),
+            price,
+            category: None,
+            staff: vec![],
+            member_package_id: None,
+        };
+        let payment = |method: PaymentMethod, amount: i32, approval: Option<&str>| Payment {
+            id: format!("p-{}", amount),
+            ledger_entry_id: "a1b2c3d4-e5f6".to_string(),
+            method,
+            amount,
+            approval_number: approval.map(str::to_string),
+            paid_at: completed_at,
+        };
+
+        LedgerEntry {
+            id: "a1b2c3d4-e5f6".to_string(),
+            entry_type: LedgerEntryType::Service,
+            reservation_id: None,
+            member_id: Some("m1".to_string()),
+            member_name: "김철수".to_string(),
+            seat_id: 1,
+            staff_id: "s1".to_string(),
+            staff_name: "이디자".to_string(),
+            services: vec![
+                service(1, "커트", Some("단발"), 20000),
+                service(2, "염색", None, 50000),
+            ],
+            gross_amount: 70000,
+            discount_amount: 7000,
+            discounts: vec![LedgerDiscount {
+                id: "d1".to_string(),
+                ledger_entry_id: "a1b2c3d4-e5f6".to_string(),
+                source: DiscountSource::Tier,
+                reference_id: Some("t1".to_string()),
+                name: "VIP".to_string(),
+                amount: 7000,
+                terms: None,
+            }],
+            total_price: 63000,
+            deposit_applied: 0,
+            payments: vec![
+                payment(PaymentMethod::Card, 60000, Some("12345678")),
+                payment(PaymentMethod::Cash, 3000, None),
+            ],
+            refund_of: None,
+            refund_reason: None,
+            approved_by: None,
+            refunded_amount: 0,
+            corrected_at: None,
+            tip_amount: 5000,
+            completed_at,
+            created_at: completed_at,
+        }
+    }
+
+    fn settings() -> ReceiptSettings {
+        ReceiptSettings {
+            phone: "02-123-4567".to_string(),
+            paper_width: 32,
+            ..Default::default()
+        }
+    }
+
+    const TEXT: &str = "            타임헤어
+        Tel 02-123-4567
+--------------------------------
+             영수증
+일시            2026-03-14 15:30
+영수증번호              A1B2C3D4
+담당                      이디자
+고객                      김철수
+--------------------------------
+커트 (단발)               20,000
+염색                      50,000
+--------------------------------
+합계                      70,000
+할인 VIP                  -7,000
+결제금액                  63,000
+  카드 (승인 12345678)    60,000
+  현금                     3,000
+팁                         5,000
+--------------------------------
+    이용해 주셔서 감사합니다
+";
+
+    #[test]
+    fn text_receipt_matches_golden() {
+        assert_eq!(render_text(&entry(), &settings(), false), TEXT);
+    }
+
+    #[test]
+    fn text_receipt_marks_reprints_and_wraps_long_names() {
+        let mut entry = entry();
+        entry.services[1].name = "디지털 셋팅 펌 + 영양 클리닉".to_string();
+        let text = render_text(&entry, &settings(), true);
+
+        assert!(text.contains("\n            [재발행]\n             영수증\n"));
+        assert!(text.contains("\n디지털 셋팅 펌 + 영양 클리닉\n                          50,000\n"));
+        assert!(text.lines().all(|l| text_width(l) <= 32));
+    }
+
+    #[test]
+    fn escpos_receipt_matches_golden() {
+        let bytes = render_escpos(&entry(), &settings(), false);
+
+        let mut head = vec![ESC, b'@', ESC, b'R', 13, FS, b'&'];
+        head.extend_from_slice(&[ESC, b'a', 1, GS, b'!', 0x11]);
+        // 타임헤어 in EUC-KR
+        head.extend_from_slice(b"\xC5\xB8\xC0\xD3\xC7\xEC\xBE\xEE\n");
+        head.extend_from_slice(&[GS, b'!', 0x00, ESC, b'a', 0]);
+        let tail = [ESC, b'd', 4, GS, b'V', 66, 0];
+        assert!(bytes.starts_with(&head));
+        assert!(bytes.ends_with(&tail));
+
+        // 영수증 in EUC-KR, centred like the text receipt
+        let heading = [b"             ".as_slice(), b"\xBF\xB5\xBC\xF6\xC1\xF5\n"].concat();
+        assert!(bytes.windows(heading.len()).any(|w| w == heading));
+
+        // Everything below the title is the text receipt, byte for byte
+        let body = &bytes[head.len()..bytes.len() - tail.len()];
+        let (decoded, _, had_errors) = encoding_rs::EUC_KR.decode(body);
+        assert!(!had_errors);
+        assert_eq!(decoded, TEXT.split_once('\n').unwrap().1);
+    }
+
+    #[test]
+    fn pdf_receipt_has_valid_xref() {
+        let pdf = render_pdf(&entry(), &settings(), false);
+        let header = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n";
+        assert!(pdf.starts_with(header));
+        assert!(pdf.ends_with(b"\n%%EOF\n"));
+
+        // Everything after the binary comment line is ASCII
+        let text = std::str::from_utf8(&pdf[header.len()..]).unwrap();
+        let at = |offset: usize| &text[offset - header.len()..];
+
+        let startxref: usize = text
+            .rsplit_once("startxref\n")
+            .and_then(|(_, rest)| rest.lines().next())
+            .and_then(|n| n.parse().ok())
+            .unwrap();
+        assert!(at(startxref).starts_with("xref\n0 8\n0000000000 65535 f \n"));
+
+        let entries: Vec<&str> = at(startxref).lines().skip(3).take(7).collect();
+        assert_eq!(entries[0], "0000000015 00000 n ");
+        for (i, entry) in entries.iter().enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(entry.ends_with(" 00000 n "));
+            assert!(at(offset).starts_with(&format!("{} 0 obj\n", i + 1)));
+        }
+
+        // The stream length matches the content between the stream keywords
+        let (head, rest) = text.split_once("stream\n").unwrap();
+        let length: usize = head
+            .rsplit_once("/Length ")
+            .and_then(|(_, n)| n.split_whitespace().next())
+            .and_then(|n| n.parse().ok())
+            .unwrap();
+        assert!(rest[length..].starts_with("endstream"));
+
+        // 영수증 as UCS-2 for the UniKS-UCS2 CMap
+        assert!(text.contains(&format!("<{}> Tj", ucs2_hex("             영수증"))));
+        assert!(text.contains("C601C218C99D"));
+    }
+
+    #[test]
+    fn format_won_groups_thousands() {
+        assert_eq!(format_won(0), "0");
+        assert_eq!(format_won(999), "999");
+        assert_eq!(format_won(1000), "1,000");
+        assert_eq!(format_won(-1234567), "-1,234,567");
+    }
+}
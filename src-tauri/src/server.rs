@@ -0,0 +1,555 @@
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::models::*;
+use crate::{auth, database, events};
+
+static SERVER: Lazy<Mutex<Option<Running>>> = Lazy::new(|| Mutex::new(None));
+
+// How long a restart waits for the previous listener to let go of the port
+const REBIND_ATTEMPTS: u32 = 20;
+const REBIND_DELAY: Duration = Duration::from_millis(100);
+
+// Requests are served by a fixed set of workers; event streams stay open for
+// as long as the client is connected, so they get their own capped threads
+const WORKERS: usize = 4;
+const MAX_EVENT_STREAMS: usize = 16;
+
+static EVENT_STREAMS: AtomicUsize = AtomicUsize::new(0);
+
+struct Running {
+    server: Arc<Server>,
+    stopping: Arc<AtomicBool>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+struct ApiError {
+    status: u16,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        ApiError {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn not_found() -> Self {
+        ApiError::new(404, "요청한 경로를 찾을 수 없습니다")
+    }
+}
+
+// Command errors are user-facing messages from main.rs
+impl From<String> for ApiError {
+    fn from(message: String) -> Self {
+        ApiError::new(400, message)
+    }
+}
+
+type ApiResult = std::result::Result<Value, ApiError>;
+
+// ==================== Lifecycle ====================
+pub fn start(settings: &ApiServerSettings) -> std::result::Result<(), String> {
+    stop();
+
+    let address = format!("{}:{}", settings.bind_address, settings.port);
+    let server = Arc::new(bind(&address).map_err(|e| format!("API 서버 시작 실패: {}", e))?);
+
+    let stopping = Arc::new(AtomicBool::new(false));
+    let workers = (0..WORKERS)
+        .map(|_| {
+            let server = server.clone();
+            let stopping = stopping.clone();
+            std::thread::spawn(move || work(&server, &stopping))
+        })
+        .collect();
+
+    *SERVER.lock() = Some(Running {
+        server,
+        stopping,
+        workers,
+    });
+
+    Ok(())
+}
+
+// recv also fails when a client connection errors, which must not end the worker
+fn work(server: &Server, stopping: &AtomicBool) {
+    loop {
+        match server.recv() {
+            Ok(request) => handle(request),
+            Err(_) if stopping.load(Ordering::SeqCst) => return,
+            Err(_) => continue,
+        }
+    }
+}
+
+// The old server's accept thread closes its socket in the background after
+// stop(), so a restart on the same port retries for a moment
+fn bind(address: &str) -> std::result::Result<Server, Box<dyn std::error::Error + Send + Sync>> {
+    let mut attempts = 0;
+    loop {
+        match Server::http(address) {
+            Err(e)
+                if attempts < REBIND_ATTEMPTS
+                    && e.downcast_ref::<std::io::Error>()
+                        .is_some_and(|e| e.kind() == std::io::ErrorKind::AddrInUse) =>
+            {
+                attempts += 1;
+                std::thread::sleep(REBIND_DELAY);
+            }
+            result => return result,
+        }
+    }
+}
+
+pub fn stop() {
+    let Some(Running {
+        server,
+        stopping,
+        workers,
+    }) = SERVER.lock().take()
+    else {
+        return;
+    };
+
+    // Each unblock wakes a single waiting worker
+    stopping.store(true, Ordering::SeqCst);
+    for _ in &workers {
+        server.unblock();
+    }
+    for worker in workers {
+        let _ = worker.join();
+    }
+    // Dropping the last handle shuts the listening socket
+    drop(server);
+    events::disconnect_all();
+}
+
+pub fn status() -> ApiServerStatus {
+    let settings = database::get_api_server_settings();
+    let running = SERVER.lock().is_some();
+
+    ApiServerStatus {
+        running,
+        address: running.then(|| format!("{}:{}", settings.bind_address, settings.port)),
+    }
+}
+
+// ==================== Request Handling ====================
+fn handle(mut request: Request) {
+    let (path, _) = split_url(request.url());
+    if *request.method() == Method::Get && path == "/api/events" {
+        return match authorize(&request) {
+            Ok(()) => open_event_stream(request),
+            Err(e) => respond(request, Err(e)),
+        };
+    }
+
+    let mut body = String::new();
+    let result = match request.as_reader().read_to_string(&mut body) {
+        Ok(_) => dispatch(&request, &body),
+        Err(e) => Err(ApiError::new(400, format!("요청 본문 읽기 실패: {}", e))),
+    };
+
+    respond(request, result);
+}
+
+fn respond(request: Request, result: ApiResult) {
+    let (status, payload) = match result {
+        Ok(value) => (200, value),
+        Err(e) => (e.status, serde_json::json!({ "error": e.message })),
+    };
+
+    let response = Response::from_string(payload.to_string())
+        .with_status_code(status)
+        .with_header(json_header());
+    let _ = request.respond(response);
+}
+
+fn open_event_stream(request: Request) {
+    if EVENT_STREAMS.fetch_add(1, Ordering::SeqCst) >= MAX_EVENT_STREAMS {
+        EVENT_STREAMS.fetch_sub(1, Ordering::SeqCst);
+        return respond(
+            request,
+            Err(ApiError::new(503, "이벤트 연결이 너무 많습니다")),
+        );
+    }
+
+    std::thread::spawn(move || {
+        stream_events(request);
+        EVENT_STREAMS.fetch_sub(1, Ordering::SeqCst);
+    });
+}
+
+// Server-sent events: the connection stays open and receives every ChangeEvent
+fn stream_events(request: Request) {
+    let receiver = events::subscribe();
+    let mut writer = request.into_writer();
+
+    let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream; charset=utf-8\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n";
+    if writer
+        .write_all(head.as_bytes())
+        .and_then(|_| writer.flush())
+        .is_err()
+    {
+        return;
+    }
+
+    loop {
+        let message = match receiver.recv_timeout(Duration::from_secs(15)) {
+            Ok(message) => message,
+            Err(RecvTimeoutError::Timeout) => ": keep-alive\n\n".to_string(),
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+
+        if writer
+            .write_all(message.as_bytes())
+            .and_then(|_| writer.flush())
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+fn json_header() -> Header {
+    Header::from_bytes(
+        &b"Content-Type"[..],
+        &b"application/json; charset=utf-8"[..],
+    )
+    .expect("valid header")
+}
+
+fn split_url(url: &str) -> (&str, HashMap<String, String>) {
+    match url.split_once('?') {
+        Some((path, query)) => (path, parse_query(query)),
+        None => (url, HashMap::new()),
+    }
+}
+
+fn dispatch(request: &Request, body: &str) -> ApiResult {
+    let (path, query) = split_url(request.url());
+    let segments: Vec<String> = path
+        .trim_matches('/')
+        .split('/')
+        .map(|segment| percent_decode(segment, false))
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(|s| s.as_str()).collect();
+    let method = request.method();
+
+    let ["api", rest @ ..] = segments.as_slice() else {
+        return Err(ApiError::not_found());
+    };
+
+    // Login is the only route that does not need a token
+    if let (Method::Post, ["login"]) = (method, rest) {
+        return json(auth::login(parse(body)?)?);
+    }
+
+    authorize(request)?;
+
+    match (method, rest) {
+        (Method::Get, ["me"]) => json(auth::get_current_user(bearer_token(request).unwrap_or(""))?),
+        (Method::Get, ["users"]) => json(crate::get_users()),
+        (Method::Post, ["users"]) => json(crate::create_user(parse(body)?)?),
+
+        // Members
+        (Method::Get, ["members"]) => json(crate::get_members(
+            query.get("search").cloned(),
+            query.get("tag").cloned(),
+            query.get("gender").map(|g| Gender::from_str(g)),
+            query.get("marketing_consent").map(|c| c == "true"),
+        )),
+        (Method::Get, ["members", "duplicates"]) => json(crate::find_duplicate_members(
+            query.get("member_id").cloned(),
+        )),
+        (Method::Post, ["members", "merge"]) => json(crate::merge_members(parse(body)?)?),
+        (Method::Get, ["members", "birthdays"]) => json(crate::get_birthday_members(
+            match query.get("range").map(String::as_str) {
+                Some("week") => BirthdayRange::Week,
+                _ => BirthdayRange::Month,
+            },
+            query.get("consented_only").map(|c| c == "true"),
+        )),
+        (Method::Get, ["members", "phones", "report"]) => {
+            json(crate::get_phone_normalization_report())
+        }
+        (Method::Post, ["members"]) => json(crate::create_member(parse(body)?)?),
+        (Method::Get, ["members", "phone", phone]) => {
+            json(crate::search_member_by_phone(phone.to_string())?)
+        }
+        (Method::Get, ["members", id]) => {
+            json(crate::get_member(id.to_string()).map_err(not_found)?)
+        }
+        (Method::Put, ["members", id]) => json(crate::update_member(id.to_string(), parse(body)?)?),
+        (Method::Get, ["members", id, "profile"]) => {
+            json(crate::get_member_profile(id.to_string()).map_err(not_found)?)
+        }
+        (Method::Get, ["members", id, "notes"]) => {
+            json(crate::get_treatment_notes(id.to_string())?)
+        }
+        (Method::Get, ["members", id, "formula"]) => {
+            json(crate::get_latest_formula(id.to_string())?)
+        }
+        (Method::Get, ["members", id, "photos"]) => json(crate::get_member_photos(id.to_string())?),
+        (Method::Delete, ["members", id]) => json(crate::delete_member(id.to_string())?),
+        (Method::Post, ["members", id, "stamps"]) => {
+            json(crate::add_stamp(id.to_string(), parse(body)?)?)
+        }
+        (Method::Delete, ["members", id, "stamps"]) => {
+            json(crate::reset_stamps(id.to_string(), parse(body)?)?)
+        }
+        (Method::Get, ["members", id, "stamps", "history"]) => {
+            json(crate::get_stamp_transactions(id.to_string())?)
+        }
+        (Method::Get, ["members", id, "stamps"]) => json(crate::get_stamp_status(id.to_string())?),
+        (Method::Get, ["members", id, "prepaid"]) => {
+            json(crate::get_prepaid_transactions(id.to_string())?)
+        }
+        (Method::Post, ["members", id, "prepaid", "top-up"]) => {
+            json(crate::top_up_prepaid(id.to_string(), parse(body)?)?)
+        }
+        (Method::Post, ["members", id, "prepaid", "refund"]) => {
+            json(crate::refund_prepaid(id.to_string(), parse(body)?)?)
+        }
+        (Method::Get, ["members", id, "points"]) => {
+            json(crate::get_point_transactions(id.to_string())?)
+        }
+        (Method::Post, ["members", id, "points"]) => {
+            json(crate::adjust_points(id.to_string(), parse(body)?)?)
+        }
+        (Method::Get, ["members", id, "packages"]) => {
+            json(crate::get_member_packages(id.to_string())?)
+        }
+        (Method::Post, ["members", id, "packages"]) => {
+            json(crate::purchase_package(id.to_string(), parse(body)?)?)
+        }
+        (Method::Get, ["packages"]) => json(crate::get_packages()),
+        (Method::Get, ["tiers"]) => json(crate::get_membership_tiers()),
+        (Method::Get, ["tiers", "movements"]) => json(crate::get_tier_movements(
+            query.get("start_date").cloned(),
+            query.get("end_date").cloned(),
+        )),
+
+        // Staff
+        (Method::Get, ["staff"]) => json(crate::get_staff_list()),
+        (Method::Get, ["staff", id]) => json(crate::get_staff(id.to_string()).map_err(not_found)?),
+
+        // Seats
+        (Method::Get, ["seats"]) => json(crate::get_seats()),
+        (Method::Get, ["seats", id]) => json(crate::get_seat(seat_id(id)?).map_err(not_found)?),
+        (Method::Post, ["seats", id, "start"]) => {
+            json(crate::start_service(seat_id(id)?, parse(body)?)?)
+        }
+        (Method::Post, ["seats", id, "complete"]) => json(crate::complete_service(
+            seat_id(id)?,
+            parse_optional(body)?,
+        )?),
+        (Method::Post, ["seats", id, "checkout"]) => json(crate::preview_checkout(
+            seat_id(id)?,
+            parse_optional(body)?,
+        )?),
+        (Method::Post, ["seats", id, "cancel"]) => json(crate::cancel_service(seat_id(id)?)?),
+
+        // Reservations
+        (Method::Get, ["reservations"]) => json(crate::get_reservations(
+            query.get("status").cloned(),
+            query.get("date").cloned(),
+            query.get("all").cloned(),
+        )),
+        (Method::Post, ["reservations"]) => json(crate::create_reservation(parse(body)?)?),
+        (Method::Get, ["reservations", id]) => {
+            json(crate::get_reservation(id.to_string()).map_err(not_found)?)
+        }
+        (Method::Put, ["reservations", id]) => {
+            json(crate::update_reservation(id.to_string(), parse(body)?)?)
+        }
+        (Method::Delete, ["reservations", id]) => json(crate::delete_reservation(id.to_string())?),
+        (Method::Put, ["reservations", id, "status"]) => json(crate::update_reservation_status(
+            id.to_string(),
+            parse(body)?,
+        )?),
+        (Method::Post, ["reservations", id, "deposit"]) => {
+            json(crate::record_deposit(id.to_string(), parse(body)?)?)
+        }
+        (Method::Post, ["reservations", id, "deposit", "refund"]) => {
+            json(crate::refund_deposit(id.to_string(), parse(body)?)?)
+        }
+
+        // Discounts
+        (Method::Get, ["discounts"]) => json(crate::get_discount_rules()),
+        (Method::Get, ["coupons"]) => json(crate::get_coupons()),
+        (Method::Get, ["coupons", code]) => json(crate::validate_coupon(code.to_string())?),
+
+        // Ledger
+        (Method::Get, ["ledger"]) => json(crate::get_ledger_entries(
+            query.get("date").cloned(),
+            query.get("start_date").cloned(),
+            query.get("end_date").cloned(),
+            query.get("staff_id").cloned(),
+        )),
+        (Method::Get, ["ledger", "summary"]) => json(crate::get_ledger_summary(
+            query.get("date").cloned(),
+            query.get("start_date").cloned(),
+            query.get("end_date").cloned(),
+        )),
+        (Method::Get, ["ledger", "daily"]) => json(crate::get_daily_summary(
+            query.get("year").and_then(|y| y.parse().ok()),
+            query.get("month").and_then(|m| m.parse().ok()),
+        )),
+
+        (Method::Put, ["ledger", id]) => {
+            json(crate::correct_ledger_entry(id.to_string(), parse(body)?)?)
+        }
+        (Method::Get, ["ledger", id, "corrections"]) => {
+            json(crate::get_ledger_corrections(id.to_string()))
+        }
+        (Method::Get, ["ledger", id, "receipt"]) => json(crate::get_receipt_text(
+            id.to_string(),
+            query.get("reprint").map(|r| r == "true"),
+        )?),
+        (Method::Post, ["ledger", id, "receipt", "print"]) => {
+            json(crate::reprint_receipt(id.to_string())?)
+        }
+        (Method::Get, ["ledger", id, "refunds"]) => json(crate::get_ledger_refunds(id.to_string())),
+        (Method::Post, ["ledger", id, "refund"]) => {
+            json(crate::refund_ledger_entry(id.to_string(), parse(body)?)?)
+        }
+
+        // Payroll
+        (Method::Get, ["payroll"]) => json(crate::get_payroll_report(
+            query.get("start_date").cloned().unwrap_or_default(),
+            query.get("end_date").cloned().unwrap_or_default(),
+            query.get("staff_id").cloned(),
+        )?),
+        (Method::Post, ["ledger", id, "tips"]) => {
+            json(crate::add_tip(id.to_string(), parse(body)?)?)
+        }
+
+        // Closing
+        (Method::Get, ["closings"]) => json(crate::get_daily_closings(
+            query.get("start_date").cloned(),
+            query.get("end_date").cloned(),
+        )),
+        (Method::Post, ["closings"]) => json(crate::close_day(parse(body)?)?),
+        (Method::Get, ["closings", date]) => {
+            json(crate::get_closing_report(date.to_string()).map_err(not_found)?)
+        }
+
+        _ => Err(ApiError::not_found()),
+    }
+}
+
+// Accepts either the shared API token or a login JWT as a Bearer header only,
+// so tokens never end up in URLs that proxies and logs keep
+fn authorize(request: &Request) -> std::result::Result<(), ApiError> {
+    let token =
+        bearer_token(request).ok_or_else(|| ApiError::new(401, "인증 토큰이 필요합니다"))?;
+    let settings = database::get_api_server_settings();
+
+    if (!settings.token.is_empty() && token_matches(token, &settings.token))
+        || auth::verify_token(token).is_ok()
+    {
+        Ok(())
+    } else {
+        Err(ApiError::new(401, "유효하지 않은 인증 토큰입니다"))
+    }
+}
+
+// Looks at every byte so the time taken does not show how much of it matched
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn bearer_token(request: &Request) -> Option<&str> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Authorization"))
+        .and_then(|h| h.value.as_str().strip_prefix("Bearer "))
+        .map(|t| t.trim())
+}
+
+fn json<T: Serialize>(value: T) -> ApiResult {
+    serde_json::to_value(value).map_err(|e| ApiError::new(500, format!("응답 생성 실패: {}", e)))
+}
+
+fn parse<T: DeserializeOwned>(body: &str) -> std::result::Result<T, ApiError> {
+    serde_json::from_str(body).map_err(|e| ApiError::new(400, format!("잘못된 요청 본문: {}", e)))
+}
+
+fn parse_optional<T: DeserializeOwned>(body: &str) -> std::result::Result<Option<T>, ApiError> {
+    if body.trim().is_empty() {
+        Ok(None)
+    } else {
+        parse(body).map(Some)
+    }
+}
+
+fn not_found(message: String) -> ApiError {
+    ApiError::new(404, message)
+}
+
+fn seat_id(id: &str) -> std::result::Result<i32, ApiError> {
+    id.parse()
+        .map_err(|_| ApiError::new(400, "잘못된 좌석 번호입니다"))
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (!key.is_empty()).then(|| (percent_decode(key, true), percent_decode(value, true)))
+        })
+        .collect()
+}
+
+// `+` only means a space in query strings; in paths it is kept (e.g. "+82" phone numbers)
+fn percent_decode(input: &str, plus_as_space: bool) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' if plus_as_space => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                match (hex_value(bytes[i + 1]), hex_value(bytes[i + 2])) {
+                    (Some(high), Some(low)) => {
+                        decoded.push(high * 16 + low);
+                        i += 2;
+                    }
+                    _ => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|d| d as u8)
+}
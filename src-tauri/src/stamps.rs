@@ -0,0 +1,201 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::database;
+use crate::models::*;
+
+// ==================== Stamp Earning ====================
+// Stamps a completed sale earns; one per visit when no program is set up
+pub fn earned_for(entry: &LedgerEntry, program: Option<&StampProgram>) -> i32 {
+    let Some(program) = program else {
+        return 1;
+    };
+
+    let eligible: Vec<i32> = entry
+        .services
+        .iter()
+        .zip(database::allocate_revenue(entry))
+        .filter(|(s, _)| {
+            program.eligible_services.is_empty() || program.eligible_services.contains(&s.name)
+        })
+        .map(|(_, revenue)| revenue)
+        .collect();
+    if eligible.is_empty() {
+        return 0;
+    }
+
+    match program.amount_per_stamp {
+        Some(per_stamp) if per_stamp > 0 => eligible.iter().sum::<i32>().max(0) / per_stamp,
+        _ => 1,
+    }
+}
+
+// ==================== Stamp Redemption ====================
+// What the reward takes off this bill. `covered` lists the service lines
+// package tickets already paid for; `chargeable` is what is left of the bill.
+pub fn reward_amount(
+    program: &StampProgram,
+    services: &[SelectedService],
+    covered: &[usize],
+    chargeable: i32,
+) -> Result<i32, String> {
+    let mut lines = services
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !covered.contains(i))
+        .map(|(_, s)| s)
+        .filter(|s| {
+            program
+                .reward_service_name
+                .as_deref()
+                .is_none_or(|name| s.name == name)
+        });
+
+    let amount = match program.reward_kind {
+        StampRewardKind::FreeService => lines.next().map(|s| s.price).ok_or_else(|| {
+            format!(
+                "'{}' 무료 시술 대상이 없습니다",
+                program.reward_service_name.as_deref().unwrap_or_default()
+            )
+        })?,
+        StampRewardKind::Percentage | StampRewardKind::FixedAmount => {
+            let base = match program.reward_service_name {
+                Some(_) => lines.map(|s| s.price).sum(),
+                None => chargeable,
+            };
+            if base <= 0 {
+                return Err(format!("'{}' 혜택 대상 시술이 없습니다", program.name));
+            }
+            match program.reward_kind {
+                StampRewardKind::Percentage => base * program.reward_value.clamp(0, 100) / 100,
+                _ => program.reward_value.clamp(0, base),
+            }
+        }
+    };
+
+    Ok(amount)
+}
+
+// The reward as discount terms, kept with the sale for later corrections.
+// A free service is the whole price of one line.
+pub fn reward_terms(program: &StampProgram) -> DiscountTerms {
+    let (kind, value) = match program.reward_kind {
+        StampRewardKind::FreeService => (DiscountKind::Percentage, 100),
+        StampRewardKind::Percentage => (DiscountKind::Percentage, program.reward_value),
+        StampRewardKind::FixedAmount => (DiscountKind::FixedAmount, program.reward_value),
+    };
+    DiscountTerms {
+        kind,
+        value,
+        service_name: program.reward_service_name.clone(),
+        single_line: program.reward_kind == StampRewardKind::FreeService,
+    }
+}
+
+// ==================== Stamp Expiry ====================
+// Clears stamps that lapsed under the active program; returns the expiries written
+pub fn expire() -> Vec<StampTransaction> {
+    let Some(program) = database::get_active_stamp_program().filter(|p| p.expiry_days > 0) else {
+        return vec![];
+    };
+    database::expire_stamps(Utc::now() - Duration::days(i64::from(program.expiry_days)))
+}
+
+pub fn expires_at(member: &Member, program: Option<&StampProgram>) -> Option<DateTime<Utc>> {
+    let program = program.filter(|p| p.expiry_days > 0)?;
+    if member.stamps == 0 {
+        return None;
+    }
+    database::get_stamps_earned_at(&member.id)
+        .map(|t| t + Duration::days(i64::from(program.expiry_days)))
+}
+
+// The member's stamps as of `now`, treating lapsed ones as gone without
+// writing the expiry, so a checkout preview stays read-only
+pub fn usable(member: &Member, program: &StampProgram, now: DateTime<Utc>) -> i32 {
+    match expires_at(member, Some(program)) {
+        Some(t) if t < now => 0,
+        _ => member.stamps,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn program(expiry_days: i32) -> StampProgram {
+        StampProgram {
+            id: "program".to_string(),
+            name: "커트 스탬프".to_string(),
+            stamps_required: 10,
+            reward_kind: StampRewardKind::FreeService,
+            reward_value: 0,
+            reward_service_name: Some("커트".to_string()),
+            amount_per_stamp: None,
+            eligible_services: vec![],
+            expiry_days,
+            active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn member_with_stamps(phone: &str, count: i32) -> Member {
+        database::init_test_database();
+        let member = crate::create_member(
+            serde_json::from_value(json!({ "name": "스탬프", "phone": phone })).unwrap(),
+        )
+        .unwrap();
+        if count > 0 {
+            crate::add_stamp(
+                member.id.clone(),
+                serde_json::from_value(json!({
+                    "count": count,
+                    "actor": { "username": "admin", "password": "12344321" },
+                }))
+                .unwrap(),
+            )
+            .unwrap();
+        }
+        database::get_member_by_id(&member.id).unwrap()
+    }
+
+    #[test]
+    fn stamps_lapse_after_the_expiry_days() {
+        let member = member_with_stamps("010-4444-0101", 3);
+        let earned_at = database::get_stamps_earned_at(&member.id).unwrap();
+        let program = program(30);
+
+        assert_eq!(usable(&member, &program, earned_at), 3);
+        assert_eq!(usable(&member, &program, earned_at + Duration::days(30)), 3);
+        assert_eq!(
+            usable(
+                &member,
+                &program,
+                earned_at + Duration::days(30) + Duration::seconds(1)
+            ),
+            0
+        );
+        // Only a preview: nothing was written
+        assert_eq!(database::get_member_by_id(&member.id).unwrap().stamps, 3);
+    }
+
+    #[test]
+    fn stamps_never_lapse_without_expiry_days() {
+        let member = member_with_stamps("010-4444-0102", 2);
+
+        assert_eq!(expires_at(&member, Some(&program(0))), None);
+        assert_eq!(
+            usable(&member, &program(0), Utc::now() + Duration::days(3650)),
+            2
+        );
+    }
+
+    #[test]
+    fn members_without_stamps_have_no_expiry() {
+        let member = member_with_stamps("010-4444-0103", 0);
+
+        assert_eq!(expires_at(&member, Some(&program(30))), None);
+        assert_eq!(usable(&member, &program(30), Utc::now()), 0);
+    }
+}
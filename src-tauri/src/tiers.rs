@@ -0,0 +1,127 @@
+use chrono::{Duration, Utc};
+
+use crate::database;
+use crate::models::*;
+
+// Tiers are judged on the last 12 months of sales
+const WINDOW_DAYS: i64 = 365;
+
+// ==================== Tier Qualification ====================
+// The highest tier the spend or visits reach
+pub fn qualify(tiers: &[MembershipTier], spend: i32, visits: i32) -> Option<&MembershipTier> {
+    tiers
+        .iter()
+        .filter(|t| {
+            let base = t.min_spend <= 0 && t.min_visits <= 0;
+            let by_spend = t.min_spend > 0 && spend >= t.min_spend;
+            let by_visits = t.min_visits > 0 && visits >= t.min_visits;
+            base || by_spend || by_visits
+        })
+        .max_by_key(|t| t.level)
+}
+
+// Moves the member to the tier their activity reaches; returns the move
+// when the tier changed
+pub fn recalculate(member_id: &str) -> Option<TierChange> {
+    let member = database::get_member_by_id(member_id)?;
+    recalculate_member(&database::get_membership_tiers(), &member)
+}
+
+pub fn recalculate_all() -> Vec<TierChange> {
+    let tiers = database::get_membership_tiers();
+    database::get_members(&MemberQuery::default())
+        .iter()
+        .filter_map(|member| recalculate_member(&tiers, member))
+        .collect()
+}
+
+fn recalculate_member(tiers: &[MembershipTier], member: &Member) -> Option<TierChange> {
+    let since = Utc::now() - Duration::days(WINDOW_DAYS);
+    let (spend, visits) = database::get_member_activity(&member.id, since);
+    let to = qualify(tiers, spend, visits);
+    if to.map(|t| &t.id) == member.tier_id.as_ref() {
+        return None;
+    }
+
+    let from = member
+        .tier_id
+        .as_deref()
+        .and_then(database::get_membership_tier_by_id);
+    database::change_member_tier(member, from.as_ref(), to, spend, visits).ok()
+}
+
+// ==================== Tier Discount ====================
+// The member's tier discount on what is left of the bill
+pub fn discount_line(session: &ServiceSession, chargeable: i32) -> Option<DiscountLine> {
+    let member = database::get_member_by_id(session.member_id.as_deref()?)?;
+    let tier = database::get_membership_tiers()
+        .into_iter()
+        .find(|t| Some(&t.id) == member.tier_id.as_ref())
+        .filter(|t| t.discount_percent > 0 && chargeable > 0)?;
+
+    Some(DiscountLine {
+        source: DiscountSource::Tier,
+        reference_id: Some(tier.id.clone()),
+        amount: chargeable * tier.discount_percent.clamp(0, 100) / 100,
+        name: format!("{} 등급 할인", tier.name),
+        terms: Some(DiscountTerms {
+            kind: DiscountKind::Percentage,
+            value: tier.discount_percent,
+            service_name: None,
+            single_line: false,
+        }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tier(id: &str, level: i32, min_spend: i32, min_visits: i32) -> MembershipTier {
+        MembershipTier {
+            id: id.to_string(),
+            name: id.to_string(),
+            level,
+            min_spend,
+            min_visits,
+            discount_percent: 0,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn tiers() -> Vec<MembershipTier> {
+        vec![
+            tier("gold", 3, 1_000_000, 20),
+            tier("basic", 1, 0, 0),
+            tier("silver", 2, 300_000, 0),
+        ]
+    }
+
+    fn qualified(spend: i32, visits: i32) -> Option<String> {
+        qualify(&tiers(), spend, visits).map(|t| t.id.clone())
+    }
+
+    #[test]
+    fn thresholds_are_inclusive() {
+        assert_eq!(qualified(0, 0).as_deref(), Some("basic"));
+        assert_eq!(qualified(299_999, 0).as_deref(), Some("basic"));
+        assert_eq!(qualified(300_000, 0).as_deref(), Some("silver"));
+        assert_eq!(qualified(999_999, 19).as_deref(), Some("silver"));
+        assert_eq!(qualified(1_000_000, 0).as_deref(), Some("gold"));
+    }
+
+    #[test]
+    fn either_spend_or_visits_is_enough() {
+        assert_eq!(qualified(0, 20).as_deref(), Some("gold"));
+        // Silver sets no visit threshold, so visits alone never reach it
+        assert_eq!(qualified(0, 19).as_deref(), Some("basic"));
+    }
+
+    #[test]
+    fn no_tier_without_a_base_tier() {
+        let tiers = vec![tier("silver", 2, 300_000, 0)];
+        assert!(qualify(&tiers, 100_000, 5).is_none());
+        assert!(qualify(&[], 1_000_000, 50).is_none());
+    }
+}